@@ -2,7 +2,7 @@
 
 # download whisper models for testing
 
-MODELS_DIR="${HOME}/.local/share/whisperia/models"
+MODELS_DIR="${MODELS_DIR:-${HOME}/.local/share/whisperia/models}"
 mkdir -p "$MODELS_DIR"
 
 echo "downloading whisper models..."
@@ -12,7 +12,7 @@ echo ""
 # funcao para baixar modelo
 download_model() {
     local model_name=$1
-    local url="https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-${model_name}.bin"
+    local url="${HF_ENDPOINT:-https://huggingface.co}/ggerganov/whisper.cpp/resolve/main/ggml-${model_name}.bin"
     local output="${MODELS_DIR}/ggml-${model_name}.bin"
     
     if [ -f "$output" ]; then
@@ -35,8 +35,23 @@ download_model() {
 download_model "tiny"
 download_model "base"
 
+# clipe do modo demo (whisperia demo / try_demo)
+SAMPLES_DIR="${SAMPLES_DIR:-$(dirname "$MODELS_DIR")/samples}"
+mkdir -p "$SAMPLES_DIR"
+if [ -f "${SAMPLES_DIR}/demo.wav" ]; then
+    echo "demo clip already exists, skipping..."
+elif wget -q --show-progress -O "${SAMPLES_DIR}/demo.wav" "https://github.com/ggml-org/whisper.cpp/raw/master/samples/jfk.wav"; then
+    echo "[ok] demo clip downloaded successfully"
+else
+    echo "[x] failed to download the demo clip"
+    rm -f "${SAMPLES_DIR}/demo.wav"
+fi
+
 echo ""
 echo "done! models available in: $MODELS_DIR"
 echo ""
+echo "to try the whole setup on the demo clip, run:"
+echo "  whisperia demo"
+echo ""
 echo "to test transcription, run:"
 echo "  ./target/release/whisperia --transcribe 5 --model-path ${MODELS_DIR}/ggml-base.bin"
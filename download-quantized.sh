@@ -3,7 +3,7 @@
 # Download quantized Whisper models (Q5_0 - best quality/size ratio)
 # These are 40% smaller with minimal quality loss
 
-MODELS_DIR="${HOME}/.local/share/whisperia/models"
+MODELS_DIR="${MODELS_DIR:-${HOME}/.local/share/whisperia/models}"
 mkdir -p "$MODELS_DIR"
 
 echo "==================================="
@@ -14,7 +14,7 @@ echo ""
 # Function to download model
 download_model() {
     local model_name=$1
-    local url="https://huggingface.co/ggerganov/whisper.cpp/resolve/main/${model_name}"
+    local url="${HF_ENDPOINT:-https://huggingface.co}/ggerganov/whisper.cpp/resolve/main/${model_name}"
     local output="${MODELS_DIR}/${model_name}"
     
     if [ -f "$output" ]; then
@@ -0,0 +1,209 @@
+//! Decoding in the cloud instead of with a local model, for `model.model_type
+//! = "api"`: the recorded audio goes to the `[api]` provider as a wav. OpenAI
+//! and Groq take it on their speech-to-text endpoint; OpenRouter has none, so
+//! the audio goes to a chat model that hears it and answers with the text.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use reqwest::multipart::{Form, Part};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::{ApiConfig, Config};
+use crate::{audio, usage, Transcript};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Provider {
+    OpenAi,
+    Groq,
+    OpenRouter,
+}
+
+impl Provider {
+    fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "groq" => Ok(Self::Groq),
+            "openrouter" => Ok(Self::OpenRouter),
+            _ => anyhow::bail!("unknown transcription api '{}', use openai, groq or openrouter", name),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::OpenAi => "openai",
+            Self::Groq => "groq",
+            Self::OpenRouter => "openrouter",
+        }
+    }
+
+    fn base_url(self) -> &'static str {
+        match self {
+            Self::OpenAi => "https://api.openai.com/v1",
+            Self::Groq => "https://api.groq.com/openai/v1",
+            Self::OpenRouter => "https://openrouter.ai/api/v1",
+        }
+    }
+
+    // where the usage is counted, the same key as the llm requests to that host
+    fn host(self) -> &'static str {
+        match self {
+            Self::OpenAi => "api.openai.com",
+            Self::Groq => "api.groq.com",
+            Self::OpenRouter => "openrouter.ai",
+        }
+    }
+
+    // for an empty `api.model`
+    fn default_model(self) -> &'static str {
+        match self {
+            Self::OpenAi => "whisper-1",
+            Self::Groq => "whisper-large-v3-turbo",
+            Self::OpenRouter => "openai/gpt-4o-audio-preview",
+        }
+    }
+}
+
+pub struct ApiTranscriber {
+    provider: Provider,
+    api_key: String,
+    model: String,
+    prompt: Option<String>,
+    translate: bool,
+}
+
+impl ApiTranscriber {
+    pub fn new(config: &ApiConfig) -> Result<Self> {
+        let provider = Provider::parse(&config.provider)?;
+        if config.api_key.is_empty() {
+            anyhow::bail!("api.api_key is empty, {} needs a key to transcribe", provider.name());
+        }
+        let model = match config.model.trim() {
+            "" => provider.default_model().to_string(),
+            model => model.to_string(),
+        };
+        Ok(Self {
+            provider,
+            api_key: config.api_key.clone(),
+            model,
+            prompt: None,
+            translate: false,
+        })
+    }
+
+    /// Names and terms to expect, like the local model's initial prompt
+    pub fn with_prompt(mut self, prompt: Option<String>) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Answer in english whatever the language spoken, like `model.translate` locally
+    pub fn with_translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    /// "groq:whisper-large-v3-turbo", for the metrics and reports
+    pub fn name(&self) -> String {
+        format!("{}:{}", self.provider.name(), self.model)
+    }
+
+    /// Send 16khz samples to the provider and wait up to `timeout` for the text
+    pub async fn transcribe(&self, samples: &[f32], language: &str, timeout: Duration) -> Result<Transcript> {
+        Config::ensure_online("the transcription api")?;
+        let wav = audio::encode_wav(samples)?;
+        let language = (language != "auto").then_some(language);
+        let request = match self.provider {
+            Provider::OpenAi | Provider::Groq => self.transcription_request(wav, language)?,
+            Provider::OpenRouter => self.chat_request(&wav, language),
+        };
+        let response = request
+            .bearer_auth(&self.api_key)
+            .timeout(timeout)
+            .send()
+            .await
+            .with_context(|| format!("{} is unreachable", self.provider.base_url()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            match status.as_u16() {
+                401 | 403 => anyhow::bail!("{} refused the api key ({})", self.provider.name(), status),
+                429 => anyhow::bail!("{} is rate limiting the requests: {}", self.provider.name(), message),
+                _ => anyhow::bail!("{} returned {}: {}", self.provider.name(), status, message),
+            }
+        }
+        let reply: Value = response.json().await.context("invalid transcription api response")?;
+        let (text, tokens) = match self.provider {
+            Provider::OpenAi | Provider::Groq => (reply["text"].as_str(), 0),
+            Provider::OpenRouter => (
+                reply["choices"][0]["message"]["content"].as_str(),
+                reply["usage"]["total_tokens"].as_u64().unwrap_or(0),
+            ),
+        };
+        let text = text.context("the transcription api sent no text")?.trim().to_string();
+
+        let audio_secs = samples.len() as f64 / 16000.0;
+        usage::record(self.provider.host(), audio_secs, tokens);
+        info!("{} transcribed {:.1}s of audio", self.name(), audio_secs);
+        Ok(Transcript {
+            text,
+            words: Vec::new(),
+            confidence: None,
+        })
+    }
+
+    // the openai audio api, multipart like the transcription service's; the
+    // translations endpoint takes no language, it always writes english
+    fn transcription_request(&self, wav: Vec<u8>, language: Option<&str>) -> Result<reqwest::RequestBuilder> {
+        let mut form = Form::new()
+            .text("model", self.model.clone())
+            .text("response_format", "json");
+        if let Some(language) = language.filter(|_| !self.translate) {
+            form = form.text("language", language.to_string());
+        }
+        if let Some(prompt) = &self.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        form = form.part("file", Part::bytes(wav).file_name("audio.wav").mime_str("audio/wav")?);
+
+        let endpoint = if self.translate { "translations" } else { "transcriptions" };
+        Ok(reqwest::Client::new()
+            .post(format!("{}/audio/{}", self.provider.base_url(), endpoint))
+            .multipart(form))
+    }
+
+    // a chat model that takes audio, told to answer with the transcript alone
+    fn chat_request(&self, wav: &[u8], language: Option<&str>) -> reqwest::RequestBuilder {
+        let mut instruction = String::from("Transcribe this audio exactly as spoken. Reply with the transcript only.");
+        if let Some(language) = language {
+            instruction.push_str(&format!(" The speech is in '{}'.", language));
+        }
+        if self.translate {
+            instruction.push_str(" Write the transcript in English, translated if it was spoken in another language.");
+        }
+        if let Some(prompt) = &self.prompt {
+            instruction.push_str(&format!(" Words it may contain: {}", prompt));
+        }
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": instruction },
+                    {
+                        "type": "input_audio",
+                        "input_audio": {
+                            "data": base64::engine::general_purpose::STANDARD.encode(wav),
+                            "format": "wav",
+                        },
+                    },
+                ],
+            }],
+        });
+        reqwest::Client::new()
+            .post(format!("{}/chat/completions", self.provider.base_url()))
+            .json(&body)
+    }
+}
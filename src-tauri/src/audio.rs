@@ -1,24 +1,51 @@
 use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, StreamConfig};
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use parking_lot::Mutex;
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::info;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::MonitorConfig;
+use crate::i18n::{t, tf};
+use crate::monitor::Monitor;
 
 pub struct AudioRecorder {
     host: cpal::Host,
     device: cpal::Device,
     config: StreamConfig,
     sample_format: SampleFormat,
+    monitor: Option<MonitorConfig>,
 }
 
 impl AudioRecorder {
     pub fn new() -> Result<Self> {
+        Self::with_device(None)
+    }
+
+    /// Record from the first input device whose name contains `name`
+    /// (case-insensitive), the default one when `None`
+    pub fn with_device(name: Option<&str>) -> Result<Self> {
         let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .context("no input device available")?;
+        let device = match name {
+            Some(name) => {
+                let wanted = name.to_lowercase();
+                host.input_devices()?
+                    .find(|device| {
+                        device
+                            .name()
+                            .map(|n| n.to_lowercase().contains(&wanted))
+                            .unwrap_or(false)
+                    })
+                    .with_context(|| format!("no input device matching '{}'", name))?
+            }
+            None => host
+                .default_input_device()
+                .context("no input device available")?,
+        };
 
         let config = device.default_input_config()?;
         let sample_format = config.sample_format();
@@ -35,9 +62,17 @@ impl AudioRecorder {
             device,
             config,
             sample_format,
+            monitor: None,
         })
     }
 
+    /// Play the microphone back while `capture_until_stopped` records, when
+    /// monitor mode is on
+    pub fn with_monitor(mut self, config: &MonitorConfig) -> Self {
+        self.monitor = config.enabled.then(|| config.clone());
+        self
+    }
+
     pub fn record_for_seconds(&self, seconds: u64) -> Result<Vec<f32>> {
         info!("recording for {} seconds...", seconds);
 
@@ -55,7 +90,7 @@ impl AudioRecorder {
                 self.device.build_input_stream(
                     &self.config,
                     move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        let mut vec = samples.lock().unwrap();
+                        let mut vec = samples.lock();
                         for &sample in data {
                             if vec.len() < samples_needed {
                                 vec.push(sample);
@@ -71,7 +106,7 @@ impl AudioRecorder {
                 self.device.build_input_stream(
                     &self.config,
                     move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                        let mut vec = samples.lock().unwrap();
+                        let mut vec = samples.lock();
                         for &sample in data {
                             if vec.len() < samples_needed {
                                 vec.push(sample as f32 / 32768.0);
@@ -92,7 +127,7 @@ impl AudioRecorder {
 
         drop(stream);
 
-        let samples = recorded_samples.lock().unwrap().clone();
+        let samples = recorded_samples.lock().clone();
         info!("recorded {} samples", samples.len());
 
         // resample to 16khz if needed
@@ -104,7 +139,158 @@ impl AudioRecorder {
         }
     }
 
-    fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    /// Device sample rate of what `capture_until_stopped` returns
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.config.channels
+    }
+
+    pub fn sample_format(&self) -> String {
+        format!("{:?}", self.sample_format)
+    }
+
+    pub fn device_name(&self) -> String {
+        self.device.name().unwrap_or_default()
+    }
+
+    /// Record until `stop` is cancelled or `max_seconds` have passed, at the device
+    /// sample rate (`resample` to 16khz before transcribing)
+    pub fn capture_until_stopped(&self, max_seconds: u64, stop: &CancellationToken) -> Result<Vec<f32>> {
+        info!("recording for up to {} seconds...", max_seconds);
+
+        let samples_needed = (self.config.sample_rate.0 as u64 * max_seconds) as usize;
+        let recorded_samples = Arc::new(Mutex::new(Vec::with_capacity(samples_needed)));
+
+        // without a working output device the dictation is recorded all the same
+        let monitor = self.monitor.as_ref().and_then(|config| {
+            Monitor::start(config, self.config.sample_rate.0, self.config.channels)
+                .map_err(|e| warn!("monitor mode unavailable: {:#}", e))
+                .ok()
+        });
+        let sink = monitor.as_ref().map(Monitor::sink);
+
+        let err_fn = move |err| {
+            eprintln!("audio stream error: {}", err);
+        };
+
+        let stream = match self.sample_format {
+            SampleFormat::F32 => {
+                let samples = recorded_samples.clone();
+                let sink = sink.clone();
+                self.device.build_input_stream(
+                    &self.config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                        if let Some(sink) = &sink {
+                            sink.push(data);
+                        }
+                        let mut vec = samples.lock();
+                        let room = samples_needed.saturating_sub(vec.len());
+                        vec.extend(data.iter().take(room));
+                    },
+                    err_fn,
+                    None,
+                )?
+            }
+            SampleFormat::I16 => {
+                let samples = recorded_samples.clone();
+                let sink = sink.clone();
+                self.device.build_input_stream(
+                    &self.config,
+                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                        let data: Vec<f32> = data.iter().map(|&s| s as f32 / 32768.0).collect();
+                        if let Some(sink) = &sink {
+                            sink.push(&data);
+                        }
+                        let mut vec = samples.lock();
+                        let room = samples_needed.saturating_sub(vec.len());
+                        vec.extend(data.into_iter().take(room));
+                    },
+                    err_fn,
+                    None,
+                )?
+            }
+            _ => anyhow::bail!("unsupported sample format"),
+        };
+
+        stream.play()?;
+
+        let deadline = Instant::now() + std::time::Duration::from_secs(max_seconds);
+        while Instant::now() < deadline && !stop.is_cancelled() {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        drop(stream);
+        drop(monitor);
+
+        let samples = recorded_samples.lock().clone();
+        info!("recorded {} samples", samples.len());
+
+        Ok(samples)
+    }
+
+    /// Record until `stop` is cancelled, handing what arrived to `on_samples` every
+    /// `interval` (at the device sample rate)
+    pub fn stream_until_stopped<F>(
+        &self,
+        stop: &CancellationToken,
+        interval: std::time::Duration,
+        mut on_samples: F,
+    ) -> Result<()>
+    where
+        F: FnMut(Vec<f32>),
+    {
+        info!("streaming audio until stopped...");
+
+        let pending = Arc::new(Mutex::new(Vec::new()));
+
+        let err_fn = move |err| {
+            eprintln!("audio stream error: {}", err);
+        };
+
+        let stream = match self.sample_format {
+            SampleFormat::F32 => {
+                let samples = pending.clone();
+                self.device.build_input_stream(
+                    &self.config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                        samples.lock().extend_from_slice(data);
+                    },
+                    err_fn,
+                    None,
+                )?
+            }
+            SampleFormat::I16 => {
+                let samples = pending.clone();
+                self.device.build_input_stream(
+                    &self.config,
+                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                        samples.lock().extend(data.iter().map(|&s| s as f32 / 32768.0));
+                    },
+                    err_fn,
+                    None,
+                )?
+            }
+            _ => anyhow::bail!("unsupported sample format"),
+        };
+
+        stream.play()?;
+
+        while !stop.is_cancelled() {
+            std::thread::sleep(interval);
+            let samples = std::mem::take(&mut *pending.lock());
+            if !samples.is_empty() {
+                on_samples(samples);
+            }
+        }
+
+        drop(stream);
+        Ok(())
+    }
+
+    pub fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
         if from_rate == to_rate {
             return input.to_vec();
         }
@@ -128,14 +314,14 @@ impl AudioRecorder {
 
     pub fn record_until_interrupt(&self) -> Result<Vec<f32>> {
         info!("recording until ctrl+c...");
-        println!("gravando... pressione ctrl+c para parar");
+        println!("{}", t("cli.press_ctrl_c"));
 
         let should_stop = Arc::new(AtomicBool::new(false));
         let should_stop_clone = should_stop.clone();
 
         // setup ctrl+c handler
         ctrlc::set_handler(move || {
-            println!("\nparando gravacao...");
+            println!("\n{}", t("cli.stopping"));
             should_stop_clone.store(true, Ordering::SeqCst);
         })
         .expect("error setting ctrl+c handler");
@@ -155,7 +341,7 @@ impl AudioRecorder {
                     &self.config,
                     move |data: &[f32], _: &cpal::InputCallbackInfo| {
                         if !stop_flag.load(Ordering::SeqCst) {
-                            let mut vec = samples.lock().unwrap();
+                            let mut vec = samples.lock();
                             for &sample in data {
                                 vec.push(sample);
                             }
@@ -172,7 +358,7 @@ impl AudioRecorder {
                     &self.config,
                     move |data: &[i16], _: &cpal::InputCallbackInfo| {
                         if !stop_flag.load(Ordering::SeqCst) {
-                            let mut vec = samples.lock().unwrap();
+                            let mut vec = samples.lock();
                             for &sample in data {
                                 vec.push(sample as f32 / 32768.0);
                             }
@@ -197,26 +383,22 @@ impl AudioRecorder {
             if elapsed > 0 && elapsed % 5 == 0 {
                 let mins = elapsed / 60;
                 let secs = elapsed % 60;
-                print!(
-                    "\rgravando: {:02}:{:02} - pressione ctrl+c para parar",
-                    mins, secs
-                );
+                let elapsed = format!("{:02}:{:02}", mins, secs);
+                print!("\r{}", tf("cli.elapsed", &[&elapsed]));
                 std::io::Write::flush(&mut std::io::stdout()).unwrap();
             }
         }
 
         drop(stream);
 
-        let samples = recorded_samples.lock().unwrap().clone();
+        let samples = recorded_samples.lock().clone();
         info!(
             "recorded {} samples ({} seconds)",
             samples.len(),
             samples.len() / self.config.sample_rate.0 as usize
         );
-        println!(
-            "\ngravacao finalizada: {} segundos",
-            samples.len() / self.config.sample_rate.0 as usize
-        );
+        let seconds = samples.len() / self.config.sample_rate.0 as usize;
+        println!("\n{}", tf("cli.recorded", &[&seconds]));
 
         // resample to 16khz if needed
         if self.config.sample_rate.0 != 16000 {
@@ -227,3 +409,102 @@ impl AudioRecorder {
         }
     }
 }
+
+/// 16khz mono samples as a 16 bit pcm wav, half the size of the f32 samples,
+/// for sending them over the network
+pub fn encode_wav(samples: &[f32]) -> Result<Vec<u8>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut buffer, spec)?;
+    for sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(buffer.into_inner())
+}
+
+/// Decode a WAV file into 16khz mono samples ready for whisper
+pub fn decode_wav(bytes: &[u8]) -> Result<Vec<f32>> {
+    let reader = hound::WavReader::new(std::io::Cursor::new(bytes)).context("invalid wav file")?;
+    let spec = reader.spec();
+    // a header claiming 0 or 200 bits would overflow the scale below
+    if !(1..=32).contains(&spec.bits_per_sample) {
+        anyhow::bail!("unsupported wav bit depth {}", spec.bits_per_sample);
+    }
+    if spec.sample_rate == 0 {
+        anyhow::bail!("invalid wav sample rate 0");
+    }
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<Result<_, _>>()
+            .context("failed to read wav samples")?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|v| v as f32 / scale))
+                .collect::<Result<_, _>>()
+                .context("failed to read wav samples")?
+        }
+    };
+
+    // downmix to mono
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    info!(
+        "decoded wav: {} samples, {} channels, {}hz",
+        mono.len(),
+        spec.channels,
+        spec.sample_rate
+    );
+
+    Ok(AudioRecorder::resample(&mono, spec.sample_rate, 16000))
+}
+
+/// Decode an audio file to 16khz mono: wav directly, anything else (mp3, m4a,
+/// ogg, the audio of a video...) through ffmpeg
+pub fn decode_file(path: &Path) -> Result<Vec<f32>> {
+    let is_wav = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+    if is_wav {
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read {:?}", path))?;
+        return decode_wav(&bytes);
+    }
+
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-nostdin", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args(["-vn", "-ac", "1", "-ar", "16000", "-f", "s16le", "-"])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("ffmpeg is needed to read {:?}, install it first", path),
+            _ => anyhow::Error::from(e).context("failed to run ffmpeg"),
+        })?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ffmpeg could not read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let samples: Vec<f32> = output
+        .stdout
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+        .collect();
+    if samples.is_empty() {
+        anyhow::bail!("{} has no audio", path.display());
+    }
+    info!("decoded {} samples of audio from {}", samples.len(), path.display());
+    Ok(samples)
+}
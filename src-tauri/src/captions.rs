@@ -0,0 +1,122 @@
+//! Live captions: transcribe the microphone or the system audio continuously
+//! into an always-on-top window, for calls and videos. Nothing is typed.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::pace::Pace;
+use crate::{memory, tuning, AppState, AudioRecorder, Event, Transcriber, WhisperiaError};
+
+pub const WINDOW: &str = "captions";
+
+// how often captured audio is picked up from the device
+const POLL: Duration = Duration::from_millis(250);
+
+/// What the captions window shows: the last finished lines and the one still being heard
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Captions {
+    pub lines: Vec<String>,
+    pub partial: String,
+}
+
+/// Open the captions window and start transcribing, does nothing when already running
+pub fn start(app: &AppHandle) -> Result<(), WhisperiaError> {
+    let state = app.state::<AppState>();
+    let Some(cancel) = state.begin_captions() else {
+        return Ok(());
+    };
+    info!("starting live captions");
+    if let Err(e) = open_window(app) {
+        state.stop_captions();
+        return Err(WhisperiaError::Internal(format!("could not open the captions window: {:#}", e)));
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        if let Err(e) = run(&state, &cancel) {
+            error!("live captions failed: {:#}", e);
+            state.publish(Event::Error(WhisperiaError::from(e)));
+        }
+        // a stop already cleaned up, and maybe started new captions since
+        if !cancel.is_cancelled() {
+            stop(&app);
+        }
+    });
+    Ok(())
+}
+
+/// Stop transcribing and close the window
+pub fn stop(app: &AppHandle) {
+    info!("stopping live captions");
+    app.state::<AppState>().stop_captions();
+    if let Some(window) = app.get_webview_window(WINDOW) {
+        let _ = window.close();
+    }
+}
+
+fn open_window(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(WINDOW) {
+        return window.show();
+    }
+    WebviewWindowBuilder::new(app, WINDOW, WebviewUrl::App("captions.html".into()))
+        .title("Whisperia Captions")
+        .inner_size(720.0, 160.0)
+        .always_on_top(true)
+        .decorations(false)
+        .skip_taskbar(true)
+        .focused(false)
+        .build()?;
+    Ok(())
+}
+
+/// Decode the current line again every `step_ms`, and start a new one after `chunk_secs`;
+/// both stretch when this machine can't keep up, see `captions.adaptive`
+fn run(state: &AppState, cancel: &CancellationToken) -> Result<()> {
+    let config = state.get_config();
+    let captions = &config.captions;
+    let (model_path, _) = memory::pick_model(&config)?;
+    let transcriber = Transcriber::cached(&model_path, config.model.cached_models)
+        .map_err(WhisperiaError::decode_failed)?
+        .with_threads(tuning::threads(&model_path, config.model.threads))
+        .with_translate(config.model.translate);
+    let recorder = AudioRecorder::with_device(captions.source.as_deref()).map_err(WhisperiaError::no_mic)?;
+    let rate = recorder.sample_rate();
+
+    let mut pace = Pace::new(captions.chunk_secs.max(1), captions.adaptive);
+    let step = Duration::from_millis(captions.step_ms.max(POLL.as_millis() as u64));
+    let mut shown = Captions::default();
+    let mut line = Vec::with_capacity(pace.chunk());
+    let mut decoded_at = Instant::now();
+
+    recorder.stream_until_stopped(cancel, POLL, |samples| {
+        line.extend(AudioRecorder::resample(&samples, rate, 16000));
+        let finished = line.len() >= pace.chunk();
+        if !finished && decoded_at.elapsed() < pace.interval(step) {
+            return;
+        }
+        decoded_at = Instant::now();
+
+        match transcriber.transcribe_abortable(&line, &config.language, cancel, |_| {}) {
+            Ok(text) => shown.partial = text,
+            Err(_) if cancel.is_cancelled() => return,
+            Err(e) => warn!("failed to decode caption: {:#}", e),
+        }
+        pace.record(line.len(), decoded_at.elapsed());
+        if finished {
+            let text = std::mem::take(&mut shown.partial);
+            if !text.is_empty() {
+                shown.lines.push(text);
+            }
+            let old = shown.lines.len().saturating_sub(captions.lines);
+            shown.lines.drain(..old);
+            line.clear();
+        }
+        state.publish(Event::Captions(shown.clone()));
+    })?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! The system clipboard, read and written: win32 on Windows, pbcopy on macOS, and on linux
+//! wl-copy under wayland or xclip/xsel under x11.
+
+use anyhow::{Context, Result};
+#[cfg(not(target_os = "windows"))]
+use std::io::Write;
+#[cfg(not(target_os = "windows"))]
+use std::process::{Command, Stdio};
+
+/// Add `text` after the clipboard contents, with `separator` in between when
+/// there was something there already
+pub fn append(text: &str, separator: &str) -> Result<()> {
+    let current = get().unwrap_or_default();
+    if current.is_empty() {
+        set(text)
+    } else {
+        set(&format!("{}{}{}", current, separator, text))
+    }
+}
+
+/// The text on the clipboard, empty when it holds something else
+#[cfg(target_os = "windows")]
+pub fn get() -> Result<String> {
+    crate::win32::get_clipboard().context("failed to read the clipboard")
+}
+
+#[cfg(target_os = "macos")]
+pub fn get() -> Result<String> {
+    read(&["pbpaste"])
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn get() -> Result<String> {
+    let tools: &[&[&str]] = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[&["wl-paste", "--no-newline"]]
+    } else {
+        &[&["xclip", "-selection", "clipboard", "-o"], &["xsel", "--clipboard", "--output"]]
+    };
+    let mut last = None;
+    for tool in tools {
+        match read(tool) {
+            Ok(text) => return Ok(text),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| anyhow::anyhow!("no clipboard tool available")))
+}
+
+/// Replace the clipboard contents with `text`
+#[cfg(target_os = "windows")]
+pub fn set(text: &str) -> Result<()> {
+    crate::win32::set_clipboard(text).context("failed to set the clipboard")
+}
+
+#[cfg(target_os = "macos")]
+pub fn set(text: &str) -> Result<()> {
+    pipe(&["pbcopy"], text)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn set(text: &str) -> Result<()> {
+    let tools: &[&[&str]] = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[&["wl-copy"]]
+    } else {
+        &[&["xclip", "-selection", "clipboard"], &["xsel", "--clipboard", "--input"]]
+    };
+    let mut last = None;
+    for tool in tools {
+        match pipe(tool, text) {
+            Ok(()) => return Ok(()),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| anyhow::anyhow!("no clipboard tool available")))
+}
+
+/// The stdout of `command`. The tools exit with an error on an empty
+/// clipboard, which counts as empty.
+#[cfg(not(target_os = "windows"))]
+fn read(command: &[&str]) -> Result<String> {
+    let output = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .with_context(|| format!("failed to run {}", command[0]))?;
+    if !output.status.success() {
+        return Ok(String::new());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Write `text` to the stdin of `command`
+#[cfg(not(target_os = "windows"))]
+fn pipe(command: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {}", command[0]))?;
+    child
+        .stdin
+        .take()
+        .context("no stdin")?
+        .write_all(text.as_bytes())
+        .with_context(|| format!("failed to write to {}", command[0]))?;
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", command[0], status);
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::process::Command;
+use tracing::{info, warn};
+
+/// Display server of the desktop session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Session {
+    X11,
+    Wayland,
+    Unknown, // windows, macos or no display at all
+}
+
+impl Session {
+    pub fn detect() -> Self {
+        match std::env::var("XDG_SESSION_TYPE").as_deref() {
+            Ok("wayland") => return Self::Wayland,
+            Ok("x11") => return Self::X11,
+            _ => {}
+        }
+        // login managers that don't set XDG_SESSION_TYPE
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Self::Wayland
+        } else if cfg!(target_os = "linux") && std::env::var_os("DISPLAY").is_some() {
+            Self::X11
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// Wayland compositors we can talk to over their IPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compositor {
+    Hyprland,
+    Sway,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusedWindow {
+    pub id: String, // hyprland address or sway con_id
+    pub app_id: String,
+    pub title: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Output {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Compositor {
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(Self::Hyprland)
+        } else if std::env::var_os("SWAYSOCK").is_some() {
+            Some(Self::Sway)
+        } else {
+            None
+        }
+    }
+
+    pub fn focused_window(&self) -> Option<FocusedWindow> {
+        let window = match self {
+            Self::Hyprland => {
+                let value = hyprctl(&["activewindow"]).ok()?;
+                parse_hyprland_window(&value)
+            }
+            Self::Sway => {
+                let tree = swaymsg("get_tree").ok()?;
+                find_sway_focused(&tree).and_then(parse_sway_window)
+            }
+        };
+
+        if window.is_none() {
+            warn!("{:?}: could not determine the focused window", self);
+        }
+        window
+    }
+
+    /// Pointer position in global coordinates. Sway doesn't expose the pointer over
+    /// its IPC, callers fall back to the focused output there.
+    pub fn cursor_position(&self) -> Option<(i32, i32)> {
+        match self {
+            Self::Hyprland => {
+                let value = hyprctl(&["cursorpos"]).ok()?;
+                Some((value["x"].as_i64()? as i32, value["y"].as_i64()? as i32))
+            }
+            Self::Sway => None,
+        }
+    }
+
+    pub fn active_output(&self) -> Option<Output> {
+        let (outputs, rect_key) = match self {
+            Self::Hyprland => (hyprctl(&["monitors"]).ok()?, None),
+            Self::Sway => (swaymsg("get_outputs").ok()?, Some("rect")),
+        };
+
+        let output = outputs
+            .as_array()?
+            .iter()
+            .find(|o| o["focused"].as_bool().unwrap_or(false))?;
+        let rect = match rect_key {
+            Some(key) => &output[key],
+            None => output,
+        };
+
+        Some(Output {
+            name: output["name"].as_str().unwrap_or_default().to_string(),
+            x: as_i32(&rect["x"]),
+            y: as_i32(&rect["y"]),
+            width: as_i32(&rect["width"]),
+            height: as_i32(&rect["height"]),
+        })
+    }
+
+    /// Give focus back to a window, e.g. after the overlay stole it
+    pub fn focus_window(&self, window: &FocusedWindow) -> Result<()> {
+        info!("{:?}: restoring focus to {} ({})", self, window.app_id, window.id);
+
+        let status = match self {
+            Self::Hyprland => Command::new("hyprctl")
+                .args(["dispatch", "focuswindow", &format!("address:{}", window.id)])
+                .status(),
+            Self::Sway => Command::new("swaymsg")
+                .arg(format!("[con_id={}] focus", window.id))
+                .status(),
+        }
+        .context("failed to run compositor command")?;
+
+        if !status.success() {
+            anyhow::bail!("compositor refused to focus window {}", window.id);
+        }
+        Ok(())
+    }
+
+    /// Move a floating window (matched by its exact title) to absolute coordinates,
+    /// since wayland clients can't position themselves
+    pub fn move_window(&self, title: &str, x: i32, y: i32) -> Result<()> {
+        let status = match self {
+            Self::Hyprland => Command::new("hyprctl")
+                .args([
+                    "dispatch",
+                    "movewindowpixel",
+                    &format!("exact {} {},title:^({})$", x, y, title),
+                ])
+                .status(),
+            Self::Sway => Command::new("swaymsg")
+                .arg(format!(
+                    "[title=\"^{}$\"] floating enable, move absolute position {} {}",
+                    title, x, y
+                ))
+                .status(),
+        }
+        .context("failed to run compositor command")?;
+
+        if !status.success() {
+            anyhow::bail!("compositor refused to move window {}", title);
+        }
+        Ok(())
+    }
+}
+
+fn as_i32(value: &Value) -> i32 {
+    value.as_i64().unwrap_or(0) as i32
+}
+
+fn hyprctl(args: &[&str]) -> Result<Value> {
+    let output = Command::new("hyprctl")
+        .arg("-j")
+        .args(args)
+        .output()
+        .context("failed to run hyprctl")?;
+    serde_json::from_slice(&output.stdout).context("invalid hyprctl output")
+}
+
+fn swaymsg(message_type: &str) -> Result<Value> {
+    let output = Command::new("swaymsg")
+        .args(["-r", "-t", message_type])
+        .output()
+        .context("failed to run swaymsg")?;
+    serde_json::from_slice(&output.stdout).context("invalid swaymsg output")
+}
+
+fn parse_hyprland_window(value: &Value) -> Option<FocusedWindow> {
+    // hyprctl prints an empty object when nothing is focused
+    let id = value["address"].as_str()?;
+
+    Some(FocusedWindow {
+        id: id.to_string(),
+        app_id: value["class"].as_str().unwrap_or_default().to_string(),
+        title: value["title"].as_str().unwrap_or_default().to_string(),
+        x: as_i32(&value["at"][0]),
+        y: as_i32(&value["at"][1]),
+        width: as_i32(&value["size"][0]),
+        height: as_i32(&value["size"][1]),
+    })
+}
+
+fn find_sway_focused(node: &Value) -> Option<&Value> {
+    if node["focused"].as_bool().unwrap_or(false) {
+        return Some(node);
+    }
+
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(find_sway_focused)
+}
+
+fn parse_sway_window(node: &Value) -> Option<FocusedWindow> {
+    // workspaces and outputs can be focused too, only containers with a pid are windows
+    node["pid"].as_i64()?;
+
+    // xwayland windows have no app_id, fall back to the X11 class
+    let app_id = node["app_id"]
+        .as_str()
+        .or_else(|| node["window_properties"]["class"].as_str())
+        .unwrap_or_default();
+    let rect = &node["rect"];
+
+    Some(FocusedWindow {
+        id: node["id"].as_i64()?.to_string(),
+        app_id: app_id.to_string(),
+        title: node["name"].as_str().unwrap_or_default().to_string(),
+        x: as_i32(&rect["x"]),
+        y: as_i32(&rect["y"]),
+        width: as_i32(&rect["width"]),
+        height: as_i32(&rect["height"]),
+    })
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+
+// enough of the log to see what the app was doing right before
+const LOG_LINES: usize = 200;
+
+// one panic poisoning a lock often takes other threads down in the same second
+static REPORTS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static RECOVERING: Cell<bool> = const { Cell::new(false) };
+}
+
+tokio::task_local! {
+    static RECOVERING_TASK: ();
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub name: String,
+    pub path: PathBuf,
+    pub timestamp: u64, // unix seconds
+    pub content: String,
+    pub recovered: bool, // caught, whisperia kept running
+}
+
+/// Write a report to the data dir for every panic, then run the default hook.
+/// The hook can't tell whether a panic will be caught, so the code that
+/// catches them runs the work in `recoverable` and those are saved as
+/// `recovered-*.txt` instead of `crash-*.txt`.
+pub fn install() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current();
+        let panic = format!("thread '{}' {}", thread.name().unwrap_or("unnamed"), info);
+        let recovered = RECOVERING.with(Cell::get) || RECOVERING_TASK.try_with(|_| ()).is_ok();
+        if let Err(e) = write_report(&panic, recovered) {
+            eprintln!("failed to write crash report: {:#}", e);
+        }
+        previous(info);
+    }));
+}
+
+/// Run `work` whose panic the caller catches and turns into an error
+pub fn recoverable<T>(work: impl FnOnce() -> T) -> T {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            RECOVERING.with(|recovering| recovering.set(self.0));
+        }
+    }
+    // put back on unwind too, after the hook has looked at it
+    let _reset = Reset(RECOVERING.with(|recovering| recovering.replace(true)));
+    work()
+}
+
+/// `recoverable` for a task whose panic comes back from its `JoinHandle`
+pub async fn recoverable_task<F: Future>(future: F) -> F::Output {
+    RECOVERING_TASK.scope((), future).await
+}
+
+fn write_report(panic: &str, recovered: bool) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let report = format!(
+        "whisperia {} ({} {})\ntimestamp: {}\nrecovered: {}\n\n{}\n\nbacktrace:\n{}\n\nconfig:\n{}\n\nlog:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        timestamp,
+        recovered,
+        panic,
+        Backtrace::force_capture(),
+        config_summary(),
+        log_tail(),
+    );
+
+    // the first of several reports is the one that matters, never overwrite it
+    let prefix = if recovered { "recovered" } else { "crash" };
+    let sequence = REPORTS.fetch_add(1, Ordering::Relaxed);
+    let name = format!("{}-{}-{}-{}.txt", prefix, timestamp, std::process::id(), sequence);
+    let path = Config::crashes_dir()?.join(name);
+    std::fs::write(&path, report)?;
+    eprintln!("crash report written to {:?}", path);
+    Ok(path)
+}
+
+/// The saved config with every key, token and password blanked
+fn config_summary() -> String {
+    let config = Config::config_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str::<Config>(&content).ok());
+    let Some(config) = config else {
+        return "unavailable".to_string();
+    };
+    toml::to_string_pretty(&config.redacted()).unwrap_or_else(|_| "unavailable".to_string())
+}
+
+/// Last lines of the newest log file
+fn log_tail() -> String {
+    let newest = Config::logs_dir().ok().and_then(|dir| newest_file(&dir));
+    let Some(content) = newest.and_then(|path| std::fs::read_to_string(path).ok()) else {
+        return "unavailable".to_string();
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(LOG_LINES)..].join("\n")
+}
+
+fn newest_file(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Reports left by earlier runs, newest first
+pub fn list() -> Result<Vec<CrashReport>> {
+    let mut reports = Vec::new();
+    for entry in std::fs::read_dir(Config::crashes_dir()?)? {
+        let path = entry?.path();
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        // <crash|recovered>-<unix seconds>-<pid>-<sequence>
+        let mut parts = name.split('-');
+        let recovered = match parts.next() {
+            Some("crash") => false,
+            Some("recovered") => true,
+            _ => continue,
+        };
+        let Some(Ok(timestamp)) = parts.next().map(str::parse) else {
+            continue;
+        };
+        let sequence: u64 = parts.nth(1).and_then(|sequence| sequence.parse().ok()).unwrap_or(0);
+        let report = CrashReport {
+            name: name.to_string(),
+            content: std::fs::read_to_string(&path)?,
+            path,
+            timestamp,
+            recovered,
+        };
+        reports.push((report, sequence));
+    }
+    reports.sort_by_key(|(report, sequence)| std::cmp::Reverse((report.timestamp, *sequence)));
+    Ok(reports.into_iter().map(|(report, _)| report).collect())
+}
+
+/// Delete the reports once the user has seen them
+pub fn clear() -> Result<()> {
+    for report in list()? {
+        std::fs::remove_file(&report.path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Trying whisperia without the microphone: `try_demo` runs a dictation on a
+//! known clip through the whole pipeline (model, backend, post-processing,
+//! typing into the focused window), `whisperia demo` decodes it from a terminal.
+//! Both compare what was heard with what the clip says.
+//!
+//! The clip is whisper.cpp's `samples/jfk.wav`, fetched with the models by
+//! `download-models.sh` into `<data dir>/samples/demo.wav`; packages may ship
+//! it as a resource instead.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+use crate::config::Config;
+use crate::{
+    audio, events, hotwords, i18n, memory, postprocess, queue_dictation, transcription, tuning, AppState, Event,
+    TranscriptEvent, Transcriber, WhisperiaError,
+};
+
+const CLIP: &str = "demo.wav";
+/// What the clip says
+pub const EXPECTED: &str =
+    "And so my fellow Americans, ask not what your country can do for you, ask what you can do for your country.";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DemoResult {
+    pub text: String,
+    pub expected: String,
+    pub accuracy: f32, // of the expected words, the share heard in order
+}
+
+impl DemoResult {
+    fn new(text: String) -> Self {
+        Self {
+            accuracy: accuracy(&text, EXPECTED),
+            expected: EXPECTED.to_string(),
+            text,
+        }
+    }
+}
+
+/// The clip as 16khz samples, from the app's resources or the data dir
+pub fn load(app: Option<&AppHandle>) -> Result<Vec<f32>> {
+    let bundled = app
+        .and_then(|app| app.path().resource_dir().ok())
+        .map(|dir| dir.join("samples").join(CLIP))
+        .filter(|path| path.exists());
+    let path = match bundled {
+        Some(path) => path,
+        None => {
+            let path: PathBuf = Config::samples_dir()?.join(CLIP);
+            if !path.exists() {
+                anyhow::bail!("the demo clip isn't at {:?}, download-models.sh fetches it with the models", path);
+            }
+            path
+        }
+    };
+    audio::decode_file(&path).with_context(|| format!("failed to read the demo clip {:?}", path))
+}
+
+/// Dictate the clip like a hotkey dictation would and wait for what was typed
+pub async fn run(app: AppHandle) -> Result<DemoResult, WhisperiaError> {
+    let samples = load(Some(&app)).map_err(|e| WhisperiaError::Invalid(format!("{:#}", e)))?;
+    let state = app.state::<AppState>().inner().clone();
+    // subscribed before queueing, so the result can't go by unseen
+    let mut events = state.subscribe();
+    queue_dictation(app, None, None, None, Some(samples))?;
+    let cancelled = i18n::t("status.cancelled");
+    while let Some(event) = events::next(&mut events).await {
+        match event {
+            Event::Transcript(TranscriptEvent::Final { text, .. }) | Event::Routed(text) => {
+                return Ok(DemoResult::new(text));
+            }
+            Event::Error(error) => return Err(error),
+            Event::Status(status) if status == cancelled => {
+                return Err(WhisperiaError::Invalid("the demo was cancelled".to_string()));
+            }
+            _ => {}
+        }
+    }
+    Err(WhisperiaError::Invalid("whisperia is shutting down".to_string()))
+}
+
+/// `whisperia demo`: decode the clip with the configured model and print it
+/// post-processed, without typing it into the terminal
+pub fn run_cli() -> Result<()> {
+    let config = Config::load_or_create()?;
+    i18n::set_locale(&config.ui.locale);
+    transcription::set_gpu(config.model.use_gpu);
+    let samples = load(None)?;
+    let (model_path, fallback) = memory::pick_model(&config)?;
+    if let Some(model) = fallback {
+        println!("low on memory, decoding with {} instead", model);
+    }
+    let threads = tuning::threads(&model_path, config.model.threads);
+    println!(
+        "model {:?}, {} backend, {} threads, language {}",
+        model_path,
+        transcription::backend().name(),
+        threads,
+        config.language
+    );
+
+    let started = Instant::now();
+    let transcriber = Transcriber::new(&model_path)?
+        .with_threads(threads)
+        .with_paragraphs(config.output.paragraph_pause_ms)
+        .with_prompt(hotwords::prompt(&config.model.hotwords))
+        .with_rules(&config.model.decode)
+        .with_translate(config.model.translate);
+    let loaded = started.elapsed();
+    let started = Instant::now();
+    let text = transcriber.transcribe(&samples, &config.language)?;
+    let decoded = started.elapsed();
+    let typed = postprocess::process(&text, &config, None);
+
+    let result = DemoResult::new(text);
+    println!(
+        "loaded in {:.1}s, decoded {:.1}s of audio in {:.1}s",
+        loaded.as_secs_f32(),
+        samples.len() as f32 / 16000.0,
+        decoded.as_secs_f32()
+    );
+    println!("\nheard:    {}\nexpected: {}", result.text, result.expected);
+    if typed != result.text {
+        println!("typed:    {}", typed);
+    }
+    println!("\n{:.0}% of the words heard", result.accuracy * 100.0);
+    Ok(())
+}
+
+/// Share of `expected`'s words found in `text` in the same order, ignoring
+/// case and punctuation (the longest common subsequence of words)
+fn accuracy(text: &str, expected: &str) -> f32 {
+    let words = |s: &str| -> Vec<String> {
+        s.split_whitespace()
+            .map(|w| w.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+    let (heard, expected) = (words(text), words(expected));
+    if expected.is_empty() {
+        return 1.0;
+    }
+    let mut row = vec![0usize; expected.len() + 1];
+    for word in &heard {
+        let mut diagonal = 0;
+        for (j, wanted) in expected.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if word == wanted { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    row[expected.len()] as f32 / expected.len() as f32
+}
@@ -0,0 +1,120 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::i18n::{t, tf};
+use crate::pipeline::Cancelled;
+
+/// Errors surfaced to the frontend. Serialized as `{ "code": ..., "message": ... }`
+/// so the UI can show a targeted fix instead of a raw message.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum WhisperiaError {
+    #[error("No microphone available: {0}")]
+    NoMic(String),
+    #[error("Model not found: {0}")]
+    ModelMissing(String),
+    #[error("Not enough memory: {0}")]
+    LowMemory(String),
+    #[error("Could not transcribe the audio: {0}")]
+    DecodeFailed(String),
+    #[error("Could not type into the focused window: {0}")]
+    InputBlocked(String),
+    #[error("Busy: {0}")]
+    Busy(String),
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    #[error("Hotkey error: {0}")]
+    Hotkey(String),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    Invalid(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl WhisperiaError {
+    /// Stable identifier the frontend matches on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NoMic(_) => "no_mic",
+            Self::ModelMissing(_) => "model_missing",
+            Self::LowMemory(_) => "low_memory",
+            Self::DecodeFailed(_) => "decode_failed",
+            Self::InputBlocked(_) => "input_blocked",
+            Self::Busy(_) => "busy",
+            Self::Timeout(_) => "timeout",
+            Self::Hotkey(_) => "hotkey",
+            Self::Cancelled => "cancelled",
+            Self::Invalid(_) => "invalid",
+            Self::Internal(_) => "internal",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// The message in the configured `ui.locale`, `Display` stays English for the logs
+    pub fn localized(&self) -> String {
+        let key = format!("error.{}", self.code());
+        match self {
+            Self::Cancelled => t(&key),
+            Self::NoMic(detail)
+            | Self::ModelMissing(detail)
+            | Self::LowMemory(detail)
+            | Self::DecodeFailed(detail)
+            | Self::InputBlocked(detail)
+            | Self::Busy(detail)
+            | Self::Timeout(detail)
+            | Self::Hotkey(detail)
+            | Self::Invalid(detail)
+            | Self::Internal(detail)
+            | Self::Other(detail) => tf(&key, &[detail]),
+        }
+    }
+
+    pub fn no_mic(e: impl Into<anyhow::Error>) -> Self {
+        Self::NoMic(format!("{:#}", e.into()))
+    }
+
+    pub fn model_missing(e: impl Into<anyhow::Error>) -> Self {
+        Self::ModelMissing(format!("{:#}", e.into()))
+    }
+
+    pub fn decode_failed(e: impl Into<anyhow::Error>) -> Self {
+        Self::DecodeFailed(format!("{:#}", e.into()))
+    }
+
+    pub fn input_blocked(e: impl Into<anyhow::Error>) -> Self {
+        Self::InputBlocked(format!("{:#}", e.into()))
+    }
+
+    /// A caught panic, reported like any other failure
+    pub fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Self::Internal(message)
+    }
+}
+
+impl From<anyhow::Error> for WhisperiaError {
+    fn from(error: anyhow::Error) -> Self {
+        // stages tag their failures with a typed error, keep it
+        match error.downcast::<WhisperiaError>() {
+            Ok(typed) => typed,
+            Err(error) if error.is::<Cancelled>() => Self::Cancelled,
+            Err(error) => Self::Other(format!("{:#}", error)),
+        }
+    }
+}
+
+impl Serialize for WhisperiaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("WhisperiaError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.localized())?;
+        error.end()
+    }
+}
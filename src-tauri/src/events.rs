@@ -0,0 +1,298 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::i18n::{t, tf};
+use crate::glossary::Correction;
+use crate::quick_pick::QuickOptions;
+use crate::theme::UiTheme;
+use crate::tray_cycle;
+use crate::two_pass::Refinement;
+use crate::{
+    AccessibilityConfig, AppState, AppStatus, Captions, PipelineState, Segment, WhisperiaError, Word, TRAY_ID,
+};
+
+/// Live transcript updates, streamed to websocket clients
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TranscriptEvent {
+    Partial(Segment),
+    Final {
+        text: String,
+        language: String,
+        // of the decoded words, before rewrites; empty when the decoder gave none
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        words: Vec<Word>,
+        confidence: Option<f32>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub file: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// Everything the pipeline reports. Stages publish here and the adapters
+/// below decide who hears about it (webviews, tray, stdout, mqtt, websocket).
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// pipeline state or last result changed
+    State(AppStatus),
+    /// progress line for the windows ("Transcribing...")
+    Status(String),
+    Transcript(TranscriptEvent),
+    /// a dictation was saved or searched instead of typed
+    Routed(String),
+    Error(WhisperiaError),
+    DownloadProgress(DownloadProgress),
+    Summary(String),
+    /// appearance settings changed, the windows restyle themselves
+    Theme(UiTheme),
+    /// the live captions window has new text
+    Captions(Captions),
+    /// the compose buffer changed, `None` when compose mode was turned off
+    Compose(Option<String>),
+    /// the scratch note changed, its whole text
+    Scratch(String),
+    /// corrections the user keeps making, offered for the glossary
+    GlossarySuggestions(Vec<Correction>),
+    /// a dictation waits for the overlay's quick pick before recording
+    QuickOptions(QuickOptions),
+    /// the dictation decoded to nothing and records again
+    Retrying,
+    /// the configured model heard something else than the typed draft, `None` once it's taken or stale
+    Refinement(Option<Refinement>),
+    /// the overlay should record this many ms with getUserMedia, see `capture.source`
+    WebviewRecord(u64),
+}
+
+pub struct EventBus {
+    tx: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        // partials and download progress are bursty, slow subscribers just skip ahead
+        let (tx, _) = broadcast::channel(128);
+        Self { tx }
+    }
+
+    pub fn publish(&self, event: Event) {
+        // no subscribers is fine, nobody is listening yet
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+}
+
+/// Receive the next event, skipping over the ones a slow subscriber missed
+pub async fn next(events: &mut broadcast::Receiver<Event>) -> Option<Event> {
+    loop {
+        match events.recv().await {
+            Ok(event) => return Some(event),
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("event subscriber lagging, skipped {} events", skipped);
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// Forward bus events to the webviews (as the `status-update`, `transcription-*`, ...
+/// events the frontend listens to) and keep the tray tooltip up to date
+pub fn spawn_tauri(app: AppHandle, mut events: broadcast::Receiver<Event>, shutdown: CancellationToken) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                Some(event) = next(&mut events) => event,
+                _ = shutdown.cancelled() => break,
+                else => break,
+            };
+            emit(&app, event);
+        }
+    });
+}
+
+fn emit(app: &AppHandle, event: Event) {
+    match event {
+        Event::State(status) => {
+            if let Some(tray) = app.tray_by_id(TRAY_ID) {
+                let state = app.state::<AppState>();
+                let listening = state.wake_word_running();
+                let tooltip = match status.pipeline.name() {
+                    "idle" if state.open_mic_running() => tf("tray.tooltip", &[&t("state.open_mic")]),
+                    "idle" if listening => tf("tray.tooltip", &[&t("state.wake_word")]),
+                    "idle" => match tray_cycle::selection(&state.get_config()) {
+                        Some(selection) => tf("tray.tooltip", &[&selection]),
+                        None => "Whisperia".to_string(),
+                    },
+                    state => tf("tray.tooltip", &[&t(&format!("state.{}", state))]),
+                };
+                let _ = tray.set_tooltip(Some(tooltip));
+            }
+        }
+        Event::Status(text) => {
+            let _ = app.emit("status-update", text);
+        }
+        Event::Transcript(TranscriptEvent::Final { text, words, confidence, .. }) => {
+            let scores = serde_json::json!({ "words": words, "confidence": confidence });
+            let _ = app.emit("transcription-confidence", scores);
+            let _ = app.emit("transcription-update", &text);
+            let _ = app.emit("status-update", t("status.ready"));
+            let _ = app.emit("transcription-complete", &text);
+        }
+        Event::Transcript(TranscriptEvent::Partial(segment)) => {
+            let _ = app.emit("transcription-partial", &segment);
+        }
+        Event::Routed(transcript) => {
+            let _ = app.emit("status-update", t("status.ready"));
+            let _ = app.emit("transcription-complete", &transcript);
+        }
+        Event::Error(error) => {
+            let _ = app.emit("status-update", error.localized());
+            let _ = app.emit("transcription-error", &error);
+        }
+        Event::DownloadProgress(progress) => {
+            let _ = app.emit("model-download-progress", progress);
+        }
+        Event::Summary(summary) => {
+            let _ = app.emit("summary-complete", &summary);
+        }
+        Event::Theme(theme) => {
+            let _ = app.emit("ui-theme-changed", &theme);
+        }
+        Event::Captions(captions) => {
+            let _ = app.emit_to(crate::captions::WINDOW, "captions-update", &captions);
+        }
+        Event::Compose(buffer) => {
+            let _ = app.emit("compose-buffer", &buffer);
+        }
+        Event::Scratch(note) => {
+            let _ = app.emit_to(crate::scratch::WINDOW, "scratch-note", &note);
+        }
+        Event::GlossarySuggestions(suggestions) => {
+            let _ = app.emit("glossary-suggestions", &suggestions);
+        }
+        Event::QuickOptions(options) => {
+            let _ = app.emit_to("overlay", "quick-options", &options);
+        }
+        Event::Retrying => {
+            let _ = app.emit("status-update", t("status.retrying"));
+        }
+        Event::Refinement(refinement) => {
+            let _ = app.emit_to("overlay", "refinement-offer", &refinement);
+        }
+        Event::WebviewRecord(max_ms) => {
+            let _ = app.emit_to("overlay", "webview-record", max_ms);
+        }
+    }
+}
+
+/// Windows toasts for finished and failed dictations, the app is in the tray
+/// and the overlay is gone by the time a slow model or LLM is done
+#[cfg(target_os = "windows")]
+pub fn spawn_toasts(app_id: String, mut events: broadcast::Receiver<Event>, shutdown: CancellationToken) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                Some(event) = next(&mut events) => event,
+                _ = shutdown.cancelled() => break,
+                else => break,
+            };
+            let (title, body) = match event {
+                Event::Transcript(TranscriptEvent::Final { text, .. }) => (t("toast.complete"), text),
+                Event::Retrying => (t("toast.retrying"), t("status.retrying")),
+                Event::Error(WhisperiaError::Cancelled) => continue,
+                Event::Error(error) => (t("toast.failed"), error.localized()),
+                _ => continue,
+            };
+            if let Err(e) = crate::win32::show_toast(&app_id, &title, &body) {
+                warn!("failed to show notification: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Speak short confirmations for the `[accessibility]` mode: when recording
+/// starts, how many words went in, and what failed
+pub fn spawn_speech(
+    config: AccessibilityConfig,
+    mut events: broadcast::Receiver<Event>,
+    shutdown: CancellationToken,
+) {
+    info!("speaking feedback through the platform tts");
+    tauri::async_runtime::spawn(async move {
+        // a failed insert still publishes the final transcript, it shouldn't sound like a success
+        let mut failed = false;
+        loop {
+            let event = tokio::select! {
+                Some(event) = next(&mut events) => event,
+                _ = shutdown.cancelled() => break,
+                else => break,
+            };
+            let message = match event {
+                Event::State(status) if status.pipeline == PipelineState::Recording => {
+                    failed = false;
+                    t("speech.listening")
+                }
+                Event::Status(text) if text == t("status.cancelled") => t("speech.cancelled"),
+                Event::Transcript(TranscriptEvent::Final { .. }) if failed => continue,
+                Event::Transcript(TranscriptEvent::Final { text, .. }) => {
+                    match text.split_whitespace().count() {
+                        1 => t("speech.inserted_one"),
+                        words => tf("speech.inserted", &[&words]),
+                    }
+                }
+                Event::Routed(_) => t("speech.done"),
+                Event::Retrying => t("speech.retrying"),
+                Event::Error(WhisperiaError::Cancelled) => t("speech.cancelled"),
+                Event::Error(error) => {
+                    failed = true;
+                    tf("speech.error", &[&t(&format!("speech.error.{}", error.code()))])
+                }
+                _ => continue,
+            };
+            let voice = config.voice.clone();
+            let spoken = tauri::async_runtime::spawn_blocking(move || {
+                crate::speech::speak(&message, voice.as_deref())
+            })
+            .await;
+            if let Ok(Err(e)) = spoken {
+                warn!("failed to speak feedback: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Print final transcripts to stdout (errors to stderr), for `--print`
+pub fn spawn_printer(mut events: broadcast::Receiver<Event>, shutdown: CancellationToken) {
+    info!("printing transcripts to stdout");
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                Some(event) = next(&mut events) => event,
+                _ = shutdown.cancelled() => break,
+                else => break,
+            };
+            match event {
+                Event::Transcript(TranscriptEvent::Final { text, .. }) => println!("{}", text),
+                // on stderr, stdout only gets the final text
+                Event::Transcript(TranscriptEvent::Partial(segment)) => eprintln!("... {}", segment.text.trim()),
+                Event::Error(error) => eprintln!("error ({}): {}", error.code(), error),
+                _ => {}
+            }
+        }
+    });
+}
@@ -0,0 +1,183 @@
+//! Personal glossary: words whisper keeps getting wrong and what to type
+//! instead. It grows from the user's own edits: every correction made before
+//! confirming a transcript is counted, and once the same one has been made
+//! `suggest_after` times it's offered for the glossary.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::Config;
+
+// longer edits are rewrites, not corrections
+const MAX_WORDS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replacement {
+    pub from: String,
+    pub to: String,
+}
+
+/// A correction the user made, and how often
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Correction {
+    pub from: String,
+    pub to: String,
+    pub count: u32,
+    #[serde(default)]
+    pub dismissed: bool,
+}
+
+/// `glossary.toml` in the config directory, editable by hand
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Glossary {
+    pub replacements: Vec<Replacement>,
+    pub corrections: Vec<Correction>,
+}
+
+impl Glossary {
+    pub fn load() -> Result<Self> {
+        let path = Config::glossary_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+        toml::from_str(&content).with_context(|| format!("failed to parse {:?}", path))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Config::glossary_path()?;
+        let content = toml::to_string_pretty(self)?;
+        std::fs::write(&path, content).with_context(|| format!("failed to write {:?}", path))
+    }
+
+    /// Count the corrections between what was transcribed and what the user
+    /// confirmed, returning the ones that are now worth suggesting
+    pub fn learn(&mut self, original: &str, edited: &str, suggest_after: u32) -> Vec<Correction> {
+        let mut suggestions = Vec::new();
+        for (from, to) in corrections(original, edited) {
+            if self.replacements.iter().any(|r| r.from.eq_ignore_ascii_case(&from)) {
+                continue;
+            }
+            let index = match self.corrections.iter().position(|c| c.from == from && c.to == to) {
+                Some(index) => index,
+                None => {
+                    self.corrections.push(Correction {
+                        from,
+                        to,
+                        count: 0,
+                        dismissed: false,
+                    });
+                    self.corrections.len() - 1
+                }
+            };
+            let correction = &mut self.corrections[index];
+            correction.count += 1;
+            if correction.count >= suggest_after && !correction.dismissed {
+                suggestions.push(correction.clone());
+            }
+        }
+        suggestions
+    }
+
+    /// Type `to` from now on where `from` was heard
+    pub fn add(&mut self, from: &str, to: &str) {
+        info!("adding '{}' -> '{}' to the glossary", from, to);
+        self.replacements.retain(|r| !r.from.eq_ignore_ascii_case(from));
+        self.replacements.push(Replacement {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+        self.corrections.retain(|c| !c.from.eq_ignore_ascii_case(from));
+    }
+
+    pub fn remove(&mut self, from: &str) {
+        self.replacements.retain(|r| !r.from.eq_ignore_ascii_case(from));
+    }
+
+    /// Stop suggesting this correction, it was a one-off after all
+    pub fn dismiss(&mut self, from: &str, to: &str) {
+        for correction in self.corrections.iter_mut().filter(|c| c.from == from && c.to == to) {
+            correction.dismissed = true;
+        }
+    }
+
+    /// Replace glossary words in `text`, whole words and case-insensitively
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for replacement in &self.replacements {
+            text = replace_words(&text, &replacement.from, &replacement.to);
+        }
+        text
+    }
+}
+
+fn replace_words(text: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return text.to_string();
+    }
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut word_start = true;
+    while let Some(c) = rest.chars().next() {
+        let matched = word_start
+            && rest.get(..from.len()).is_some_and(|head| head.eq_ignore_ascii_case(from))
+            && !rest[from.len()..].starts_with(char::is_alphanumeric);
+        if matched {
+            replaced.push_str(to);
+            rest = &rest[from.len()..];
+            word_start = false;
+            continue;
+        }
+        replaced.push(c);
+        word_start = !c.is_alphanumeric();
+        rest = &rest[c.len_utf8()..];
+    }
+    replaced
+}
+
+/// Words replaced between the two texts, from a word-level diff. Only
+/// substitutions count; added or deleted words aren't corrections.
+pub fn corrections(original: &str, edited: &str) -> Vec<(String, String)> {
+    let clean = |word: &str| word.trim_matches(|c: char| !c.is_alphanumeric()).to_string();
+    let a: Vec<String> = original.split_whitespace().map(clean).filter(|w| !w.is_empty()).collect();
+    let b: Vec<String> = edited.split_whitespace().map(clean).filter(|w| !w.is_empty()).collect();
+
+    // longest common subsequence, compared without case so "Rust" vs "rust" isn't an edit
+    let same = |x: &str, y: &str| x.to_lowercase() == y.to_lowercase();
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if same(&a[i], &b[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut found = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && same(&a[i], &b[j]) {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // a run of changed words on both sides
+        let (from, to) = (i, j);
+        while (i < a.len() || j < b.len()) && !(i < a.len() && j < b.len() && same(&a[i], &b[j])) {
+            if j >= b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        let (removed, added) = (&a[from..i], &b[to..j]);
+        if !removed.is_empty() && !added.is_empty() && removed.len() <= MAX_WORDS && added.len() <= MAX_WORDS {
+            found.push((removed.join(" "), added.join(" ")));
+        }
+    }
+    found
+}
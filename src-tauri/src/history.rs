@@ -0,0 +1,268 @@
+//! Every transcription, newest last: kept in memory for the api and the windows
+//! and, with `history.persist`, appended to `history.jsonl` in the data dir so
+//! a dictation lost to the wrong window can still be found after a restart.
+//! Failing to write the file never fails the dictation, it's only logged.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::config::{Config, HistoryConfig};
+use crate::RunMetrics;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(default)]
+    pub id: u64, // for `delete_history_entry`, given when it's added, see `next_id`
+    pub text: String,
+    pub language: String,
+    pub timestamp: u64, // unix seconds
+    pub source: String, // hotkey, tray, api
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>, // raw transcript when it was rewritten before typing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>, // average word confidence of the transcript, 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>, // of the audio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(text: &str, language: &str, source: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            id: 0,
+            text: text.to_string(),
+            language: language.to_string(),
+            timestamp,
+            source: source.to_string(),
+            original: None,
+            confidence: None,
+            duration_ms: None,
+            model: None,
+        }
+    }
+
+    pub fn with_original(mut self, original: &str) -> Self {
+        if original != self.text {
+            self.original = Some(original.to_string());
+        }
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: Option<f32>) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    /// The audio length and the model of the dictation's run
+    pub fn with_metrics(mut self, metrics: &RunMetrics) -> Self {
+        self.duration_ms = (metrics.audio_ms > 0).then_some(metrics.audio_ms);
+        self.model = (!metrics.model.is_empty()).then(|| metrics.model.clone());
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    max_entries: usize,
+    path: Option<PathBuf>, // unset when not persisted
+    last_id: u64,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_entries: HistoryConfig::default().max_entries,
+            path: None,
+            last_id: 0,
+        }
+    }
+}
+
+impl History {
+    /// The history as `history.jsonl` left it, or an empty one kept in memory
+    pub fn load(config: &HistoryConfig) -> Self {
+        let mut history = Self {
+            max_entries: config.max_entries.max(1),
+            ..Default::default()
+        };
+        if !config.persist {
+            return history;
+        }
+        let path = match Config::history_path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("history won't be saved: {:#}", e);
+                return history;
+            }
+        };
+        match read(&path) {
+            Ok(entries) => {
+                info!("loaded {} history entries from {:?}", entries.len(), path);
+                history.last_id = entries.iter().map(|entry| entry.id).max().unwrap_or(0);
+                history.entries = entries.into();
+            }
+            Err(e) => warn!("failed to read the history: {:#}", e),
+        }
+        history.path = Some(path);
+        // a lower max_entries since the last start
+        if history.entries.len() > history.max_entries {
+            history.entries.drain(..history.entries.len() - history.max_entries);
+            history.save();
+        }
+        history
+    }
+
+    pub fn push(&mut self, mut entry: HistoryEntry) {
+        entry.id = next_id(self.last_id);
+        self.last_id = entry.id;
+        let full = self.entries.len() >= self.max_entries;
+        if full {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        // dropping the oldest rewrites the file, otherwise the entry is only appended
+        if full {
+            self.save();
+        } else if let (Some(path), Some(entry)) = (&self.path, self.entries.back()) {
+            if let Err(e) = append(path, entry) {
+                warn!("failed to save the history entry: {:#}", e);
+            }
+        }
+    }
+
+    /// Newest entries first
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.entries.iter().rev().cloned().collect()
+    }
+
+    /// Delete one entry, false when there is none with that id
+    pub fn remove(&mut self, id: u64) -> bool {
+        let Some(position) = self.entries.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        self.entries.remove(position);
+        self.save();
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+
+    // the whole file again, after a removal
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut lines = String::new();
+        for entry in &self.entries {
+            match serde_json::to_string(entry) {
+                Ok(line) => {
+                    lines.push_str(&line);
+                    lines.push('\n');
+                }
+                Err(e) => warn!("failed to save a history entry: {}", e),
+            }
+        }
+        if let Err(e) = std::fs::write(path, lines) {
+            warn!("failed to save the history to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Unix milliseconds, or one more than `last` when it's that late already
+/// (two entries in the same millisecond, a clock set back). Never a deleted
+/// or cleared entry's id again, also across restarts.
+fn next_id(last: u64) -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    now.max(last + 1)
+}
+
+fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {:?}", path))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// The entries of a history file, oldest first; lines that don't parse (a
+/// write cut short by a crash) are skipped
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("skipping a history line that doesn't parse: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// `whisperia history`: the last `limit` transcriptions, oldest first so the
+/// newest ends up next to the prompt
+pub fn print(limit: usize) -> Result<()> {
+    let entries = read(&Config::history_path()?)?;
+    if entries.is_empty() {
+        println!("no transcriptions yet");
+        return Ok(());
+    }
+    for entry in &entries[entries.len().saturating_sub(limit)..] {
+        let when = chrono::DateTime::from_timestamp(entry.timestamp as i64, 0)
+            .map(|at| at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!("#{} {} [{}, {}] {}", entry.id, when, entry.language, entry.source, entry.text);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(history: &mut History, text: &str) -> u64 {
+        history.push(HistoryEntry::new(text, "en", "test"));
+        history.entries()[0].id
+    }
+
+    #[test]
+    fn ids_of_deleted_entries_are_not_given_again() {
+        let mut history = History::default();
+        let first = push(&mut history, "first");
+        let newest = push(&mut history, "second");
+        assert!(newest > first);
+
+        assert!(history.remove(newest));
+        assert!(push(&mut history, "third") > newest);
+
+        let last = history.entries()[0].id;
+        history.clear();
+        assert!(push(&mut history, "fourth") > last);
+    }
+}
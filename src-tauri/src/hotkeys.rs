@@ -0,0 +1,301 @@
+use anyhow::Context;
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::{error, info, warn};
+
+use crate::config::{Config, RecordingMode};
+use crate::i18n::t;
+use crate::{
+    crash, meeting, read_last_result, trigger_profile_flow, trigger_revise_flow, trigger_transcription_flow, AppState,
+    Event, WhisperiaError,
+};
+
+// restarts back off up to this; a listener that ran longer starts over at one second
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Create the hotkey manager and register the dictation hotkeys. A shortcut
+/// in the config that can't be read or registered (another app holds it)
+/// falls back to Super+Shift+T, so a bad config.toml never stops the app.
+pub fn register(state: &AppState) -> anyhow::Result<()> {
+    let manager = GlobalHotKeyManager::new()?;
+    let config = state.get_config();
+
+    // Super+Shift+T is Cmd+Shift+T on macOS
+    let registered = match register_shortcut(&manager, &config.shortcut) {
+        Ok(hotkey) => vec![hotkey],
+        Err(e) => {
+            warn!("{:#}, using Super+Shift+T", e);
+            let hotkey = HotKey::new(Some(Modifiers::SUPER | Modifiers::SHIFT), Code::KeyT);
+            match manager.register(hotkey) {
+                Ok(()) => vec![hotkey],
+                // the tray and `whisperia ctl` still start a dictation
+                Err(e) => {
+                    warn!("Failed to register Super+Shift+T too, no dictation hotkey: {}", e);
+                    Vec::new()
+                }
+            }
+        }
+    };
+    register_others(state, &config, manager, registered);
+    Ok(())
+}
+
+/// Register the hotkeys of `config` in place of the ones in use, before it's
+/// saved. When its dictation shortcut can't be registered the hotkeys of the
+/// config in use come back, and the error is returned.
+pub fn replace(app: &AppHandle, config: Config) -> anyhow::Result<()> {
+    on_main_thread(app, move |state| {
+        state.unregister_hotkeys();
+        let manager = GlobalHotKeyManager::new()?;
+        match register_shortcut(&manager, &config.shortcut) {
+            Ok(hotkey) => {
+                register_others(state, &config, manager, vec![hotkey]);
+                Ok(())
+            }
+            Err(e) => {
+                drop(manager);
+                if let Err(e) = register(state) {
+                    error!("Failed to register the previous hotkeys again: {:#}", e);
+                }
+                Err(e)
+            }
+        }
+    })
+}
+
+fn register_shortcut(manager: &GlobalHotKeyManager, shortcut: &str) -> anyhow::Result<HotKey> {
+    let hotkey = parse_shortcut(shortcut)?;
+    manager
+        .register(hotkey)
+        .with_context(|| format!("Failed to register the shortcut {}", shortcut))?;
+    info!("Global hotkey {} registered successfully", shortcut);
+    Ok(hotkey)
+}
+
+/// The optional hotkeys of `config` next to the dictation one, then the
+/// manager goes to the app state
+fn register_others(state: &AppState, config: &Config, manager: GlobalHotKeyManager, mut registered: Vec<HotKey>) {
+    // Optional per-intent hotkeys, dictating once with that intent
+    for intent in config.llm.intents.clone() {
+        let Some(accelerator) = &intent.hotkey else {
+            continue;
+        };
+        match register_accelerator(&manager, accelerator) {
+            Ok(hotkey) => {
+                info!("Hotkey {} registered for intent '{}'", accelerator, intent.name);
+                state.register_intent_hotkey(hotkey.id(), intent.name);
+                registered.push(hotkey);
+            }
+            Err(e) => warn!("Failed to register hotkey {} for intent '{}': {}", accelerator, intent.name, e),
+        }
+    }
+
+    // Optional per-profile hotkeys, dictating once with that language, model and intent
+    for profile in config.profiles.clone() {
+        let Some(accelerator) = &profile.hotkey else {
+            continue;
+        };
+        match register_accelerator(&manager, accelerator) {
+            Ok(hotkey) => {
+                info!("Hotkey {} registered for profile '{}'", accelerator, profile.name);
+                state.register_profile_hotkey(hotkey.id(), profile.name);
+                registered.push(hotkey);
+            }
+            Err(e) => warn!("Failed to register hotkey {} for profile '{}': {}", accelerator, profile.name, e),
+        }
+    }
+
+    // Optional hotkey for meeting bookmarks, only does something while recording one
+    state.set_bookmark_hotkey(None);
+    if let Some(accelerator) = &config.meeting.bookmark_hotkey {
+        match register_accelerator(&manager, accelerator) {
+            Ok(hotkey) => {
+                info!("Hotkey {} registered for meeting bookmarks", accelerator);
+                state.set_bookmark_hotkey(Some(hotkey.id()));
+                registered.push(hotkey);
+            }
+            Err(e) => warn!("Failed to register bookmark hotkey {}: {}", accelerator, e),
+        }
+    }
+
+    // Optional hotkey for revising the selected text with a dictated instruction
+    state.set_revise_hotkey(None);
+    if let Some(accelerator) = &config.llm.revise_hotkey {
+        match register_accelerator(&manager, accelerator) {
+            Ok(hotkey) => {
+                info!("Hotkey {} registered for revising the selection", accelerator);
+                state.set_revise_hotkey(Some(hotkey.id()));
+                registered.push(hotkey);
+            }
+            Err(e) => warn!("Failed to register revise hotkey {}: {}", accelerator, e),
+        }
+    }
+
+    // Optional hotkey for hearing the last result read aloud
+    state.set_read_aloud_hotkey(None);
+    if let Some(accelerator) = &config.accessibility.read_aloud_hotkey {
+        match register_accelerator(&manager, accelerator) {
+            Ok(hotkey) => {
+                info!("Hotkey {} registered for reading the last result aloud", accelerator);
+                state.set_read_aloud_hotkey(Some(hotkey.id()));
+                registered.push(hotkey);
+            }
+            Err(e) => warn!("Failed to register read-aloud hotkey {}: {}", accelerator, e),
+        }
+    }
+
+    // Store manager in app state, the hotkeys are unregistered on shutdown
+    state.set_hotkey_manager(manager, registered);
+}
+
+/// `config.shortcut` as a hotkey: modifiers and a key joined by "+", case-insensitive,
+/// e.g. "Super+Shift+T", "ctrl+alt+KeyD" or "super+F9"
+pub fn parse_shortcut(shortcut: &str) -> Result<HotKey, WhisperiaError> {
+    shortcut
+        .parse::<HotKey>()
+        .map_err(|e| WhisperiaError::Invalid(format!("invalid shortcut '{}': {}", shortcut, e)))
+}
+
+fn register_accelerator(manager: &GlobalHotKeyManager, accelerator: &str) -> anyhow::Result<HotKey> {
+    let hotkey = accelerator.parse::<HotKey>()?;
+    manager.register(hotkey)?;
+    Ok(hotkey)
+}
+
+/// Listen for hotkey presses on a supervised thread. When the listener panics
+/// the manager is re-created, the hotkeys registered again and the user told.
+/// A backend that silently stops delivering presses can't be noticed: the
+/// event channel is global and never closes, and nothing tells a dead one
+/// from a quiet one.
+pub fn spawn_listener(app: AppHandle) {
+    thread::spawn(move || {
+        info!("Starting global hotkey event listener thread");
+        let mut backoff = Duration::from_secs(1);
+
+        loop {
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| crash::recoverable(|| listen(&app))));
+
+            let state = app.state::<AppState>();
+            if state.is_shutting_down() {
+                break;
+            }
+
+            let reason = match result {
+                Ok(()) => "listener stopped".to_string(),
+                Err(panic) => WhisperiaError::from_panic(panic).to_string(),
+            };
+            error!("Global hotkey listener failed: {}", reason);
+            state.publish(Event::Error(WhisperiaError::Hotkey(format!(
+                "hotkeys stopped working ({}), restarting",
+                reason
+            ))));
+
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = Duration::from_secs(1);
+            }
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+
+            match restart(&app) {
+                Ok(()) => {
+                    info!("Global hotkeys registered again");
+                    state.publish(Event::Status(t("status.hotkeys_restored")));
+                }
+                Err(e) => error!("Failed to register hotkeys again: {:#}", e),
+            }
+        }
+
+        info!("Global hotkey event listener stopped");
+    });
+}
+
+fn listen(app: &AppHandle) {
+    let receiver = GlobalHotKeyEvent::receiver();
+    let state = app.state::<AppState>();
+
+    // wake up regularly so the thread ends with the app
+    while !state.is_shutting_down() {
+        let Ok(event) = receiver.recv_timeout(Duration::from_millis(250)) else {
+            continue;
+        };
+        info!("Global hotkey event received: {:?}", event);
+        // the selection is copied with a shortcut of our own, which the held
+        // hotkey keys would turn into another one, so that waits for the release
+        let revise = state.is_revise_hotkey(event.id);
+        match event.state {
+            HotKeyState::Pressed if !revise => handle_press(app, event.id),
+            HotKeyState::Released if revise => handle_press(app, event.id),
+            _ => {}
+        }
+    }
+}
+
+fn handle_press(app: &AppHandle, hotkey_id: u32) {
+    // a panicking trigger shouldn't cost a re-registration of every hotkey
+    let trigger = panic::catch_unwind(AssertUnwindSafe(|| crash::recoverable(|| {
+        let state = app.state::<AppState>();
+        if state.is_bookmark_hotkey(hotkey_id) {
+            return meeting::bookmark(&state, None).map(|_| ());
+        }
+        if state.is_revise_hotkey(hotkey_id) {
+            return trigger_revise_flow(app.clone());
+        }
+        if state.is_read_aloud_hotkey(hotkey_id) {
+            // speaking takes a while, the listener has other hotkeys to hear meanwhile
+            let state = state.inner().clone();
+            thread::spawn(move || {
+                if let Err(e) = read_last_result(&state) {
+                    error!("Failed to read the last result aloud: {}", e);
+                    state.publish(Event::Error(e));
+                }
+            });
+            return Ok(());
+        }
+        // in toggle mode a dictation hotkey also ends the recording it started
+        if state.get_config().capture.mode == RecordingMode::Toggle && state.finish_recording() {
+            info!("Dictation hotkey pressed again, stopping the recording");
+            return Ok(());
+        }
+        if let Some(profile) = state.profile_for_hotkey(hotkey_id) {
+            return trigger_profile_flow(app.clone(), &profile);
+        }
+        let intent = state.intent_for_hotkey(hotkey_id);
+        trigger_transcription_flow(app.clone(), intent)
+    })));
+    match trigger {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!("Failed to trigger transcription from hotkey: {}", e),
+        Err(panic) => {
+            let error = WhisperiaError::from_panic(panic);
+            error!("Hotkey handler panicked: {}", error);
+            app.state::<AppState>().publish(Event::Error(error));
+        }
+    }
+}
+
+/// Drop the old manager and register everything again
+pub fn restart(app: &AppHandle) -> anyhow::Result<()> {
+    on_main_thread(app, |state| {
+        state.unregister_hotkeys();
+        register(state)
+    })
+}
+
+// some platforms only deliver hotkeys to a manager created on the main thread
+fn on_main_thread<F>(app: &AppHandle, work: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&AppState) -> anyhow::Result<()> + Send + 'static,
+{
+    let (done_tx, done_rx) = mpsc::channel();
+    let handle = app.clone();
+    app.run_on_main_thread(move || {
+        let _ = done_tx.send(work(&handle.state::<AppState>()));
+    })?;
+    done_rx.recv()?
+}
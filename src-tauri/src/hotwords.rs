@@ -0,0 +1,121 @@
+//! Hotwords: names and product terms whisper should expect. They go into the
+//! initial prompt, which makes the decoder favour them, and after decoding a
+//! near miss ("Kubernetis", "whisper ia") is replaced by the hotword itself.
+
+// whisper reads at most half its 448 token context as prompt
+const MAX_PROMPT_CHARS: usize = 600;
+
+/// The initial prompt for `hotwords`, `None` without any
+pub fn prompt(hotwords: &[String]) -> Option<String> {
+    let mut prompt = String::new();
+    for hotword in hotwords.iter().map(|h| h.trim()).filter(|h| !h.is_empty()) {
+        if prompt.len() + hotword.len() + 2 > MAX_PROMPT_CHARS {
+            break;
+        }
+        if !prompt.is_empty() {
+            prompt.push_str(", ");
+        }
+        prompt.push_str(hotword);
+    }
+    (!prompt.is_empty()).then(|| format!("{}.", prompt.replace('\0', "")))
+}
+
+/// Replace words in `text` that are a few letters off a hotword, or only
+/// differ in case, with the hotword
+pub fn apply(text: &str, hotwords: &[String]) -> String {
+    let mut text = text.to_string();
+    for hotword in hotwords.iter().map(|h| h.trim()).filter(|h| !h.is_empty()) {
+        text = replace_near(&text, hotword);
+    }
+    text
+}
+
+fn replace_near(text: &str, hotword: &str) -> String {
+    let wanted = normalize(hotword);
+    let span = hotword.split_whitespace().count();
+    // spaces are dropped before comparing, so a split "whisper ia" matches too
+    let widths = [span, span + 1];
+    let words = word_spans(text);
+
+    let mut replaced = String::with_capacity(text.len());
+    let (mut copied, mut i) = (0, 0);
+    while i < words.len() {
+        // the closest of the two, so "whisper ia" isn't taken for "Whisperia ia"
+        let found = widths
+            .iter()
+            .filter(|&&n| n > 0 && i + n <= words.len())
+            .filter_map(|&n| {
+                let (start, end) = trim_punctuation(text, words[i].0, words[i + n - 1].1);
+                let heard = normalize(&text[start..end]);
+                close(&heard, &wanted).map(|distance| (distance, n, start, end))
+            })
+            .min_by_key(|&(distance, n, _, _)| (distance, n))
+            .map(|(_, n, start, end)| (n, start, end));
+        match found {
+            Some((n, start, end)) => {
+                replaced.push_str(&text[copied..start]);
+                replaced.push_str(hotword);
+                copied = end;
+                i += n;
+            }
+            None => i += 1,
+        }
+    }
+    replaced.push_str(&text[copied..]);
+    replaced
+}
+
+/// Byte ranges of the whitespace separated words
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+    spans
+}
+
+/// The range without the punctuation around it, which stays in the text
+fn trim_punctuation(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let inner = &text[start..end];
+    let leading = inner.len() - inner.trim_start_matches(|c: char| !c.is_alphanumeric()).len();
+    let trailing = inner.len() - inner.trim_end_matches(|c: char| !c.is_alphanumeric()).len();
+    (start + leading, (end - trailing).max(start + leading))
+}
+
+fn normalize(text: &str) -> Vec<char> {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// The edit distance when within a quarter of the hotword's letters, short
+/// ones only match exactly (which still fixes their case)
+fn close(heard: &[char], wanted: &[char]) -> Option<usize> {
+    let allowed = wanted.len() / 4;
+    if heard.is_empty() || heard.len().abs_diff(wanted.len()) > allowed {
+        return None;
+    }
+    Some(distance(heard, wanted)).filter(|&d| d <= allowed)
+}
+
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,195 @@
+//! Message catalogs for user-facing text: tray menus, status messages,
+//! notifications, errors and CLI output. Logs stay in English.
+//! Shared by both crates like `config`, keep the two copies identical.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Pt,
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::En as u8);
+
+// key, english, portuguese
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // tray
+    ("tray.transcribe", "Transcribe", "Transcrever"),
+    ("tray.summarize", "Summarize last recording", "Resumir última gravação"),
+    ("tray.captions", "Live captions", "Legendas ao vivo"),
+    ("tray.meeting", "Meeting mode", "Modo reunião"),
+    ("tray.wake_word", "Listen for \"hey whisperia\"", "Ouvir \"hey whisperia\""),
+    ("tray.open_mic", "Open mic", "Microfone aberto"),
+    ("tray.offline", "Offline mode", "Modo offline"),
+    ("tray.translate", "Translate to English", "Traduzir para inglês"),
+    ("tray.compose", "Compose (type when sent)", "Compor (digitar ao enviar)"),
+    ("tray.compose_send", "Send composed text", "Enviar texto composto"),
+    ("tray.scratch", "Scratch note", "Nota rápida"),
+    ("tray.repro", "Record the next dictation for a bug report", "Gravar o próximo ditado para relatar um bug"),
+    ("tray.settings", "Settings", "Configurações"),
+    ("tray.quit", "Quit", "Sair"),
+    ("tray.send_to", "Send last to", "Enviar último para"),
+    ("tray.format_as", "Format as", "Formatar como"),
+    ("tray.plain_text", "Plain text", "Texto simples"),
+    ("tray.tone", "Tone", "Tom"),
+    ("tray.tone_original", "Original", "Original"),
+    ("tray.insert_as", "Insert dictations", "Inserir ditados"),
+    ("tray.insert_type", "Type them", "Digitando"),
+    ("tray.insert_clipboard", "Copy to the clipboard", "Copiando para a área de transferência"),
+    ("tray.insert_paste", "Paste them", "Colando"),
+    ("tray.tooltip", "Whisperia ({})", "Whisperia ({})"),
+    ("state.wake_word", "listening for the wake word", "ouvindo a palavra de ativação"),
+    ("state.open_mic", "open mic, typing what it hears", "microfone aberto, digitando o que ouve"),
+    ("state.recording", "recording", "gravando"),
+    ("state.transcribing", "transcribing", "transcrevendo"),
+    ("state.inserting", "typing", "digitando"),
+    ("state.error", "error", "erro"),
+    // status messages
+    ("status.ready", "Ready", "Pronto"),
+    ("status.recording", "Recording...", "Gravando..."),
+    ("status.quick_pick", "Pick a model or language...", "Escolha um modelo ou idioma..."),
+    ("status.transcribing", "Transcribing...", "Transcrevendo..."),
+    ("status.retrying", "Nothing heard, listening again...", "Nada ouvido, ouvindo de novo..."),
+    ("status.warming_up", "Warming up the model...", "Carregando o modelo..."),
+    ("status.formatting", "Formatting...", "Formatando..."),
+    ("status.revising", "Revising the selection...", "Revisando a seleção..."),
+    ("status.cancelled", "Cancelled", "Cancelado"),
+    ("status.hotkeys_restored", "Hotkeys restored", "Atalhos restaurados"),
+    ("status.meeting", "Meeting: {} transcribed", "Reunião: {} transcritos"),
+    ("status.meeting_saved", "Meeting saved to {}", "Reunião salva em {}"),
+    ("status.bookmark", "Bookmark at {}", "Marcador em {}"),
+    ("status.budget", "API usage this month: {} of the {} budget", "Uso das apis neste mês: {} do orçamento de {}"),
+    ("status.repro_saved", "Repro saved to {}", "Repro salvo em {}"),
+    ("status.low_memory", "Low memory, using the {} model...", "Pouca memória, usando o modelo {}..."),
+    // notifications
+    ("toast.complete", "Transcription complete", "Transcrição concluída"),
+    ("toast.failed", "Transcription failed", "Falha na transcrição"),
+    ("toast.retrying", "Nothing was heard, speak now", "Nada foi ouvido, fale agora"),
+    // spoken feedback
+    ("speech.listening", "Listening", "Ouvindo"),
+    ("speech.inserted_one", "Inserted 1 word", "1 palavra inserida"),
+    ("speech.inserted", "Inserted {} words", "{} palavras inseridas"),
+    ("speech.done", "Done", "Pronto"),
+    ("speech.cancelled", "Cancelled", "Cancelado"),
+    ("speech.retrying", "Nothing heard, listening again", "Nada ouvido, ouvindo de novo"),
+    ("speech.error", "Error: {}", "Erro: {}"),
+    ("speech.error.no_mic", "no microphone", "sem microfone"),
+    ("speech.error.model_missing", "model not found", "modelo não encontrado"),
+    ("speech.error.low_memory", "not enough memory", "memória insuficiente"),
+    ("speech.error.decode_failed", "could not transcribe", "não foi possível transcrever"),
+    ("speech.error.input_blocked", "could not type the text, it's in the history", "não foi possível digitar, o texto está no histórico"),
+    ("speech.error.busy", "busy", "ocupado"),
+    ("speech.error.timeout", "timed out", "tempo esgotado"),
+    ("speech.error.hotkey", "hotkeys stopped working", "os atalhos pararam de funcionar"),
+    ("speech.error.invalid", "invalid request", "pedido inválido"),
+    ("speech.error.internal", "internal error", "erro interno"),
+    ("speech.error.other", "something went wrong", "algo deu errado"),
+    // errors, by WhisperiaError code
+    ("error.no_mic", "No microphone available: {}", "Nenhum microfone disponível: {}"),
+    ("error.model_missing", "Model not found: {}", "Modelo não encontrado: {}"),
+    ("error.low_memory", "Not enough memory: {}", "Memória insuficiente: {}"),
+    ("error.decode_failed", "Could not transcribe the audio: {}", "Não foi possível transcrever o áudio: {}"),
+    ("error.input_blocked", "Could not type into the focused window: {}", "Não foi possível digitar na janela em foco: {}"),
+    ("error.busy", "Busy: {}", "Ocupado: {}"),
+    ("error.timeout", "Timed out: {}", "Tempo esgotado: {}"),
+    ("error.hotkey", "Hotkey error: {}", "Erro nos atalhos: {}"),
+    ("error.cancelled", "Cancelled", "Cancelado"),
+    ("error.invalid", "{}", "{}"),
+    ("error.internal", "Internal error: {}", "Erro interno: {}"),
+    ("error.other", "{}", "{}"),
+    // cli
+    ("cli.daemon_starting", "whisperia daemon starting...", "daemon do whisperia iniciando..."),
+    ("cli.daemon_hint", "use the tray icon or the hotkey to transcribe", "use o ícone da bandeja ou o atalho para transcrever"),
+    ("cli.daemon_ready", "daemon ready!", "daemon pronto!"),
+    ("cli.hotkey", "hotkey: {}", "atalho: {}"),
+    ("cli.recording", "recording...", "gravando..."),
+    ("cli.transcribing", "transcribing...", "transcrevendo..."),
+    ("cli.typing", "typing: {}", "digitando: {}"),
+    ("cli.settings_unavailable", "settings are not implemented yet", "configuração ainda não implementada"),
+    ("cli.quitting", "quitting whisperia...", "encerrando whisperia..."),
+    ("cli.title", "whisperia transcription", "transcrição whisperia"),
+    ("cli.title_interactive", "whisperia transcription (interactive mode)", "transcrição whisperia (modo interativo)"),
+    ("cli.recording_for", "recording for {} seconds...", "gravando por {} segundos..."),
+    ("cli.speak_now", "speak now!", "fale agora!"),
+    ("cli.recording_complete", "recording complete! transcribing...", "gravação concluída! transcrevendo..."),
+    ("cli.result", "transcription result:", "resultado da transcrição:"),
+    ("cli.press_ctrl_c", "recording... press ctrl+c to stop", "gravando... pressione ctrl+c para parar"),
+    ("cli.elapsed", "recording: {} - press ctrl+c to stop", "gravando: {} - pressione ctrl+c para parar"),
+    ("cli.stopping", "stopping the recording...", "parando a gravação..."),
+    ("cli.recorded", "recording finished: {} seconds", "gravação finalizada: {} segundos"),
+    ("cli.download_unavailable", "model download is not implemented in this version yet", "download de modelos ainda não implementado nesta versão"),
+    ("cli.tool_title", "whisperia voice transcription tool", "whisperia, transcrição por voz"),
+    ("cli.system_info", "system information:", "informações do sistema:"),
+    ("cli.available_models", "available models:", "modelos disponíveis:"),
+    ("cli.configuration", "configuration:", "configuração:"),
+    ("cli.usage", "usage:", "uso:"),
+    ("cli.examples", "examples:", "exemplos:"),
+    ("cli.usage_check_hardware", "check system compatibility", "verifica a compatibilidade do sistema"),
+    ("cli.usage_check_model", "check if a huggingface model works", "verifica se um modelo do huggingface funciona"),
+    ("cli.usage_list_models", "list all available models", "lista os modelos disponíveis"),
+    ("cli.usage_transcribe", "record for a fixed number of seconds", "grava por um número fixo de segundos"),
+    ("cli.usage_interactive", "record until ctrl+c", "grava até ctrl+c"),
+    ("cli.usage_file", "transcribe a wav or video file, or stdin", "transcreve um arquivo wav ou de vídeo, ou o stdin"),
+    ("cli.usage_output_format", "txt, srt, vtt or json with timestamps", "txt, srt, vtt ou json com timestamps"),
+    ("cli.usage_output_file", "write the transcript to a file", "grava a transcrição num arquivo"),
+    ("cli.usage_translate", "write the speech in english", "escreve a fala em inglês"),
+    ("cli.usage_model_path", "use a specific model file", "usa um arquivo de modelo específico"),
+    ("cli.usage_native_messaging", "run as a browser native messaging host", "roda como host de native messaging do navegador"),
+    ("cli.usage_ctl", "control the running daemon: transcribe, toggle, status, last-result", "controla o daemon rodando: transcribe, toggle, status, last-result"),
+    ("cli.usage_portable", "keep config and models next to the executable", "guarda config e modelos ao lado do executável"),
+];
+
+impl Locale {
+    /// `setting` is "en", "pt" or "auto" (the system locale)
+    pub fn from_setting(setting: &str) -> Self {
+        let setting = match setting {
+            "auto" | "" => ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+                .unwrap_or_default(),
+            other => other.to_string(),
+        };
+        if setting.to_lowercase().starts_with("pt") {
+            Self::Pt
+        } else {
+            Self::En
+        }
+    }
+}
+
+pub fn set_locale(setting: &str) {
+    LOCALE.store(Locale::from_setting(setting) as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    if LOCALE.load(Ordering::Relaxed) == Locale::Pt as u8 {
+        Locale::Pt
+    } else {
+        Locale::En
+    }
+}
+
+/// The message for `key` in the current locale, the key itself when it's unknown
+pub fn t(key: &str) -> String {
+    let Some((_, en, pt)) = MESSAGES.iter().find(|(k, _, _)| *k == key) else {
+        return key.to_string();
+    };
+    match locale() {
+        Locale::En => en.to_string(),
+        Locale::Pt => pt.to_string(),
+    }
+}
+
+/// Like `t`, with every `{}` replaced by the next argument
+pub fn tf(key: &str, args: &[&dyn Display]) -> String {
+    let mut message = t(key);
+    for arg in args {
+        let Some(position) = message.find("{}") else {
+            break;
+        };
+        message.replace_range(position..position + 2, &arg.to_string());
+    }
+    message
+}
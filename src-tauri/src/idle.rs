@@ -0,0 +1,97 @@
+//! Idle release: after `[idle].release_after_min` minutes without a dictation
+//! the cached whisper models (and the local LLM) are unloaded and, with
+//! `stop_listeners`, the wake word and open mic stopped, giving the memory back
+//! on small machines. The next dictation loads its model again while the
+//! overlay says it's warming up, and restarts the listeners once it's done.
+
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::{open_mic, wake_word, AppState, Transcriber};
+
+// how often the idle time is checked
+const CHECK: Duration = Duration::from_secs(30);
+
+static LAST_ACTIVITY: Mutex<Option<Instant>> = Mutex::new(None);
+static RELEASED: Mutex<Released> = Mutex::new(Released { wake_word: false, open_mic: false });
+
+// the listeners that were stopped, to start again
+struct Released {
+    wake_word: bool,
+    open_mic: bool,
+}
+
+/// Something was dictated or is being dictated
+pub fn touch() {
+    *LAST_ACTIVITY.lock() = Some(Instant::now());
+}
+
+/// Check the idle time until shutdown
+pub fn spawn(app: AppHandle) {
+    touch();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>().inner().clone();
+        let shutdown = state.shutdown_token();
+        let mut released = false;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK) => {}
+                _ = shutdown.cancelled() => break,
+            }
+            let minutes = state.get_config().idle.release_after_min;
+            // a meeting or the captions use the model without going through dictations
+            if state.get_status().pipeline.is_busy() || state.meeting_running() || state.captions_running() {
+                touch();
+            }
+            let idle = LAST_ACTIVITY.lock().map_or(Duration::ZERO, |at| at.elapsed());
+            if minutes == 0 || idle < Duration::from_secs(minutes * 60) {
+                released = false;
+                continue;
+            }
+            if !released {
+                release(&state);
+                released = true;
+            }
+        }
+    });
+}
+
+fn release(state: &AppState) {
+    let models = Transcriber::unload_all();
+    #[cfg(feature = "local-llm")]
+    let models = models + usize::from(crate::local_llm::unload());
+
+    let mut stopped = RELEASED.lock();
+    if state.get_config().idle.stop_listeners {
+        if state.wake_word_running() {
+            wake_word::stop(state);
+            stopped.wake_word = true;
+        }
+        if state.open_mic_running() {
+            open_mic::stop(state);
+            stopped.open_mic = true;
+        }
+    }
+    info!(
+        "idle, released {} models{}{}",
+        models,
+        if stopped.wake_word { ", the wake word" } else { "" },
+        if stopped.open_mic { ", open mic" } else { "" }
+    );
+}
+
+/// After a dictation: start the listeners the idle release stopped
+pub fn resume(app: &AppHandle) {
+    touch();
+    let mut stopped = RELEASED.lock();
+    if std::mem::take(&mut stopped.wake_word) {
+        info!("starting the wake word listener again");
+        wake_word::start(app);
+    }
+    if std::mem::take(&mut stopped.open_mic) {
+        info!("turning open mic on again");
+        open_mic::start(app);
+    }
+}
@@ -0,0 +1,115 @@
+//! Voice-memo inbox: audio files dropped in `[inbox].dir` are transcribed on
+//! the cron-like `[inbox].schedule`, while no dictation is running, into the
+//! history. Each processed file is moved to the archive; one that fails stays
+//! in the inbox for the next run.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::schedule::Schedule;
+use crate::{audio, memory, postprocess, tuning, AppState, HistoryEntry, Transcriber, WhisperiaError};
+
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "m4a", "ogg", "opus", "flac", "aac", "webm", "mp4"];
+
+/// Check the schedule every minute until shutdown
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>().inner().clone();
+        let shutdown = state.shutdown_token();
+        loop {
+            // wake at the start of the next minute
+            let second = chrono::Local::now().timestamp() % 60;
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(60 - second as u64)) => {}
+                _ = shutdown.cancelled() => break,
+            }
+            let config = state.get_config();
+            if !due(&config) {
+                continue;
+            }
+            if state.get_status().pipeline.is_busy() {
+                info!("inbox run skipped, a dictation is running");
+                continue;
+            }
+            let worker = state.clone();
+            match tokio::task::spawn_blocking(move || process(&worker, &config)).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(count)) => info!("inbox: transcribed {} files", count),
+                Ok(Err(e)) => warn!("inbox run failed: {:#}", e),
+                Err(e) => warn!("inbox run panicked: {}", e),
+            }
+        }
+    });
+}
+
+fn due(config: &Config) -> bool {
+    let now = chrono::Local::now();
+    config.inbox.dir.is_some()
+        && config.inbox.schedule.iter().any(|entry| match Schedule::parse(entry) {
+            Ok(schedule) => schedule.matches(&now),
+            Err(e) => {
+                warn!("ignoring inbox schedule: {:#}", e);
+                false
+            }
+        })
+}
+
+/// Transcribe every file waiting in the inbox, returning how many were done
+pub fn process(state: &AppState, config: &Config) -> Result<usize> {
+    let dir = config.inbox.dir.as_ref().context("no [inbox] dir is configured")?;
+    let archive = config.inbox.archive.clone().unwrap_or_else(|| dir.join("archive"));
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read the inbox {:?}", dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && is_audio(path))
+        .collect();
+    if files.is_empty() {
+        return Ok(0);
+    }
+    files.sort();
+    info!("inbox: {} files waiting in {:?}", files.len(), dir);
+
+    let (model_path, _) = memory::pick_model(config)?;
+    let transcriber = Transcriber::cached(&model_path, config.model.cached_models)
+        .map_err(WhisperiaError::decode_failed)?
+        .with_threads(tuning::threads(&model_path, config.model.threads))
+        .with_paragraphs(config.output.paragraph_pause_ms)
+        .with_translate(config.model.translate);
+    std::fs::create_dir_all(&archive).with_context(|| format!("failed to create {:?}", archive))?;
+
+    let mut done = 0;
+    for file in files {
+        // a dictation started meanwhile has the cpu, the rest waits for the next run
+        if state.get_status().pipeline.is_busy() || state.is_shutting_down() {
+            break;
+        }
+        let transcribed = audio::decode_file(&file)
+            .and_then(|samples| transcriber.transcribe(&samples, &config.language));
+        let transcript = match transcribed {
+            Ok(text) => text.trim().to_string(),
+            Err(e) => {
+                warn!("inbox: failed to transcribe {:?}: {:#}", file, e);
+                continue;
+            }
+        };
+        let text = postprocess::process(&transcript, config, None);
+        let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        info!("inbox: transcribed {} ({} words)", name, text.split_whitespace().count());
+        state.add_history(HistoryEntry::new(&text, &config.language, "inbox").with_original(&transcript));
+        if let Err(e) = std::fs::rename(&file, archive.join(&name)) {
+            warn!("inbox: failed to archive {:?}: {}", file, e);
+        }
+        done += 1;
+    }
+    Ok(done)
+}
+
+fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
@@ -1,19 +1,210 @@
-use anyhow::Result;
+//! Typing into the focused app. enigo works on X11, Windows and macOS, but many
+//! wayland compositors ignore or garble its events, so there the keys go
+//! through wtype (the virtual-keyboard protocol of wlroots compositors like
+//! sway and hyprland) or ydotool (uinput, any compositor, needs ydotoold
+//! running). `output.typing` picks one, by default from the session.
+
+use anyhow::{Context, Result};
 use enigo::{Enigo, Keyboard, Settings};
+use parking_lot::Mutex;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::warn;
+
+use crate::clipboard;
+use crate::compositor::Session;
+use crate::config::{InsertMode, TypingBackend};
+
+// follows `output.typing`, see `set_backend`
+static BACKEND: Mutex<TypingBackend> = Mutex::new(TypingBackend::Auto);
+
+// linux input event codes, for ydotool
+const KEY_BACKSPACE: u16 = 14;
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_C: u16 = 46;
+const KEY_V: u16 = 47;
+
+/// Type with `backend` from now on
+pub fn set_backend(backend: TypingBackend) {
+    *BACKEND.lock() = backend;
+}
+
+enum Backend {
+    Enigo(Enigo),
+    Wtype,
+    Ydotool,
+}
 
 pub struct InputSimulator {
-    enigo: Enigo,
+    backend: Backend,
 }
 
 impl InputSimulator {
     pub fn new() -> Result<Self> {
-        let settings = Settings::default();
-        let enigo = Enigo::new(&settings)?;
-        Ok(Self { enigo })
+        let backend = match *BACKEND.lock() {
+            TypingBackend::Auto => auto()?,
+            TypingBackend::Enigo => Backend::Enigo(Enigo::new(&Settings::default())?),
+            TypingBackend::Wtype => Backend::Wtype,
+            TypingBackend::Ydotool => Backend::Ydotool,
+        };
+        Ok(Self { backend })
     }
 
     pub fn type_text(&mut self, text: &str) -> Result<()> {
-        self.enigo.text(text)?;
+        match &mut self.backend {
+            // both read the text from stdin, so one starting with "-" isn't taken for a flag
+            Backend::Wtype => run("wtype", &["-"], Some(text)),
+            Backend::Ydotool => run("ydotool", &["type", "--file", "-"], Some(text)),
+            #[cfg(target_os = "windows")]
+            Backend::Enigo(enigo) => Self::type_text_windows(enigo, text),
+            #[cfg(not(target_os = "windows"))]
+            Backend::Enigo(enigo) => {
+                enigo.text(text)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Delete the `chars` characters before the cursor
+    pub fn erase(&mut self, chars: usize) -> Result<()> {
+        use enigo::{Direction, Key};
+
+        if chars == 0 {
+            return Ok(());
+        }
+        match &mut self.backend {
+            Backend::Wtype => {
+                let args: Vec<&str> = std::iter::repeat(["-k", "BackSpace"]).take(chars).flatten().collect();
+                run("wtype", &args, None)
+            }
+            Backend::Ydotool => {
+                let press = [format!("{}:1", KEY_BACKSPACE), format!("{}:0", KEY_BACKSPACE)];
+                let mut args = vec!["key".to_string()];
+                args.extend(std::iter::repeat(press).take(chars).flatten());
+                run("ydotool", &args.iter().map(String::as_str).collect::<Vec<_>>(), None)
+            }
+            Backend::Enigo(enigo) => {
+                for _ in 0..chars {
+                    enigo.key(Key::Backspace, Direction::Click)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Put `text` into the focused app following `output.insert`
+    pub fn insert(&mut self, text: &str, mode: InsertMode) -> Result<()> {
+        match mode {
+            InsertMode::Type => self.type_text(text),
+            InsertMode::Clipboard => clipboard::set(text),
+            InsertMode::Paste => {
+                clipboard::set(text)?;
+                self.paste()
+            }
+        }
+    }
+
+    /// Press the copy shortcut in the focused app
+    pub fn copy(&mut self) -> Result<()> {
+        self.shortcut('c', KEY_C)
+    }
+
+    /// Press the paste shortcut in the focused app
+    pub fn paste(&mut self) -> Result<()> {
+        self.shortcut('v', KEY_V)
+    }
+
+    // ctrl (cmd on macos) and `key`, `code` being its linux input event code
+    fn shortcut(&mut self, key: char, code: u16) -> Result<()> {
+        use enigo::{Direction, Key};
+
+        match &mut self.backend {
+            Backend::Wtype => run("wtype", &["-M", "ctrl", &key.to_string(), "-m", "ctrl"], None),
+            Backend::Ydotool => {
+                let keys = [
+                    format!("{}:1", KEY_LEFTCTRL),
+                    format!("{}:1", code),
+                    format!("{}:0", code),
+                    format!("{}:0", KEY_LEFTCTRL),
+                ];
+                run("ydotool", &["key", &keys[0], &keys[1], &keys[2], &keys[3]], None)
+            }
+            Backend::Enigo(enigo) => {
+                let modifier = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+                enigo.key(modifier, Direction::Press)?;
+                let pressed = enigo.key(Key::Unicode(key), Direction::Click);
+                enigo.key(modifier, Direction::Release)?;
+                pressed?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Unicode key events, or a paste when Windows rejects them. Elevated windows
+    /// get nothing at all, the text is left on the clipboard for the user instead.
+    #[cfg(target_os = "windows")]
+    fn type_text_windows(enigo: &mut Enigo, text: &str) -> Result<()> {
+        use crate::win32;
+        use enigo::{Direction, Key};
+
+        if win32::foreground_is_elevated() {
+            win32::set_clipboard(text)?;
+            anyhow::bail!(
+                "the focused window runs as administrator, the text was copied to the clipboard instead"
+            );
+        }
+        // SendInput is all or nothing in practice, so this doesn't paste a half typed text
+        if let Err(e) = win32::send_unicode(text) {
+            tracing::warn!("unicode typing failed ({:#}), pasting from the clipboard", e);
+            win32::set_clipboard(text)?;
+            enigo.key(Key::Control, Direction::Press)?;
+            enigo.key(Key::Unicode('v'), Direction::Click)?;
+            enigo.key(Key::Control, Direction::Release)?;
+        }
         Ok(())
     }
 }
+
+/// On wayland wtype, or ydotool on GNOME (mutter has no virtual-keyboard
+/// protocol) and when wtype isn't installed; enigo everywhere else
+fn auto() -> Result<Backend> {
+    if Session::detect() == Session::Wayland {
+        let gnome = std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.contains("GNOME"));
+        if !gnome && which("wtype") {
+            return Ok(Backend::Wtype);
+        }
+        if which("ydotool") {
+            return Ok(Backend::Ydotool);
+        }
+        warn!("neither wtype nor ydotool is installed, typing with enigo, which many wayland compositors ignore");
+    }
+    Ok(Backend::Enigo(Enigo::new(&Settings::default())?))
+}
+
+fn which(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Run a typing tool, with `stdin` written to it
+fn run(program: &str, args: &[&str], stdin: Option<&str>) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("{} isn't installed, see output.typing", program),
+            _ => anyhow::Error::from(e).context(format!("failed to run {}", program)),
+        })?;
+    if let (Some(text), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(text.as_bytes()).with_context(|| format!("failed to send the text to {}", program))?;
+    }
+    let output = child.wait_with_output().with_context(|| format!("failed to run {}", program))?;
+    if !output.status.success() {
+        anyhow::bail!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
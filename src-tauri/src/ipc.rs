@@ -0,0 +1,163 @@
+//! `whisperia ctl`: scripts and the keybinding daemons of tiling window
+//! managers drive the running app through a unix socket (a named pipe on
+//! windows) instead of the global hotkeys. A connection sends one command on
+//! a line and reads the answer until the app hangs up; a failure comes back
+//! as `error: ...`.
+
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::{hide_overlay_window, trigger_transcription_flow, AppState};
+
+const COMMANDS: &str = "transcribe, toggle, cancel, status or last-result";
+// a client that connects and never sends its line is dropped after this
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Listen for `whisperia ctl` until shutdown
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = listen(app).await {
+            warn!("whisperia ctl can't reach this instance: {:#}", e);
+        }
+    });
+}
+
+#[cfg(unix)]
+async fn listen(app: AppHandle) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+
+    let path = Config::ipc_path()?;
+    // a socket left by a crash answers nobody, one that answers is another instance's
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("another whisperia is listening on {:?}", path);
+        }
+        std::fs::remove_file(&path).with_context(|| format!("failed to remove the stale socket {:?}", path))?;
+    }
+    let listener = UnixListener::bind(&path).with_context(|| format!("failed to listen on {:?}", path))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    info!("ipc listening on {:?}", path);
+
+    let shutdown = app.state::<AppState>().shutdown_token();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(serve(app.clone(), stream));
+                }
+                Err(e) => warn!("ipc connection failed: {}", e),
+            },
+            _ = shutdown.cancelled() => break,
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn listen(app: AppHandle) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let path = Config::ipc_path()?;
+    let mut pipe = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&path)
+        .with_context(|| format!("failed to listen on {:?}, is another whisperia running?", path))?;
+    info!("ipc listening on {:?}", path);
+
+    let shutdown = app.state::<AppState>().shutdown_token();
+    loop {
+        tokio::select! {
+            connected = pipe.connect() => {
+                // every client gets its own instance of the pipe
+                let next = ServerOptions::new().create(&path).context("failed to reopen the pipe")?;
+                let client = std::mem::replace(&mut pipe, next);
+                match connected {
+                    Ok(()) => {
+                        tauri::async_runtime::spawn(serve(app.clone(), client));
+                    }
+                    Err(e) => warn!("ipc connection failed: {}", e),
+                }
+            }
+            _ = shutdown.cancelled() => break,
+        }
+    }
+    Ok(())
+}
+
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(app: AppHandle, stream: S) {
+    let mut stream = BufReader::new(stream);
+    let mut command = String::new();
+    match tokio::time::timeout(READ_TIMEOUT, stream.read_line(&mut command)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(_)) => return,
+        Err(_) => {
+            warn!("whisperia ctl sent no command in {:?}, hanging up", READ_TIMEOUT);
+            return;
+        }
+    }
+    // starting a dictation asks the compositor for the focused window, which blocks
+    let command = command.trim().to_string();
+    let reply = tauri::async_runtime::spawn_blocking(move || answer(&app, &command))
+        .await
+        .unwrap_or_else(|e| format!("error: {}", e));
+    let stream = stream.get_mut();
+    if let Err(e) = stream.write_all(format!("{}\n", reply).as_bytes()).await {
+        warn!("failed to answer whisperia ctl: {}", e);
+    }
+    let _ = stream.shutdown().await;
+}
+
+fn answer(app: &AppHandle, command: &str) -> String {
+    info!("ipc command '{}'", command);
+    let state = app.state::<AppState>();
+    let started = |result: Result<(), crate::WhisperiaError>| match result {
+        Ok(()) => "ok".to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    match command {
+        "transcribe" => started(trigger_transcription_flow(app.clone(), None)),
+        // the second press ends the recording, which is transcribed as usual
+        "toggle" if state.finish_recording() => "ok".to_string(),
+        "toggle" => started(trigger_transcription_flow(app.clone(), None)),
+        "cancel" => {
+            state.jobs().clear();
+            state.cancel_run();
+            let _ = hide_overlay_window(app);
+            "ok".to_string()
+        }
+        "status" => state.get_status().pipeline.name().to_string(),
+        "last-result" => state.get_status().last_result.unwrap_or_default(),
+        _ => format!("error: unknown command '{}', use {}", command, COMMANDS),
+    }
+}
+
+/// `whisperia ctl <command>`: send it to the running app and print the answer
+pub fn run_ctl(command: &str) -> Result<()> {
+    let path = Config::ipc_path()?;
+    let unreachable = || format!("whisperia isn't running, nothing listens on {:?}", path);
+    #[cfg(unix)]
+    let mut stream = std::os::unix::net::UnixStream::connect(&path).with_context(unreachable)?;
+    #[cfg(windows)]
+    let mut stream = std::fs::OpenOptions::new().read(true).write(true).open(&path).with_context(unreachable)?;
+
+    writeln!(stream, "{}", command)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).context("whisperia hung up without an answer")?;
+    let reply = reply.trim_end();
+    match reply.strip_prefix("error: ") {
+        Some(error) => anyhow::bail!("{}", error),
+        None => {
+            if !reply.is_empty() {
+                println!("{}", reply);
+            }
+            Ok(())
+        }
+    }
+}
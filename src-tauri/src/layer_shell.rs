@@ -0,0 +1,74 @@
+//! Anchor the overlay with wlr-layer-shell on wayland compositors we have no IPC
+//! for. Wayland clients can't position their own windows, a layer surface is the
+//! one kind of window the compositor places where we ask. libgtk-layer-shell is
+//! loaded at runtime so it stays an optional system package.
+
+use gtk::glib::translate::ToGlibPtr;
+use gtk::prelude::*;
+use libloading::Library;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tauri::WebviewWindow;
+use tracing::info;
+
+type GtkWindow = *mut gtk::ffi::GtkWindow;
+
+// values of the GtkLayerShellLayer, GtkLayerShellEdge and GtkLayerShellKeyboardMode enums
+const LAYER_OVERLAY: i32 = 3;
+const EDGE_BOTTOM: i32 = 3;
+const KEYBOARD_NONE: i32 = 0;
+
+// distance between the overlay and the bottom of the screen
+const MARGIN: i32 = 80;
+
+static LIBRARY: OnceLock<Option<Library>> = OnceLock::new();
+static ANCHORED: AtomicBool = AtomicBool::new(false);
+
+fn library() -> Option<&'static Library> {
+    LIBRARY
+        .get_or_init(|| unsafe { Library::new("libgtk-layer-shell.so.0") }.ok())
+        .as_ref()
+}
+
+/// Whether the overlay is a layer surface, moving it is up to the compositor then
+pub fn is_anchored() -> bool {
+    ANCHORED.load(Ordering::Relaxed)
+}
+
+/// Turn the overlay into a layer surface at the bottom center of the screen.
+/// Must run on the main thread while the overlay is hidden.
+pub fn anchor_overlay(overlay: &WebviewWindow) -> anyhow::Result<()> {
+    let Some(library) = library() else {
+        anyhow::bail!("libgtk-layer-shell is not installed");
+    };
+    let window = overlay.gtk_window()?;
+
+    unsafe {
+        let is_supported = library.get::<unsafe extern "C" fn() -> i32>(b"gtk_layer_is_supported")?;
+        if is_supported() == 0 {
+            anyhow::bail!("the compositor doesn't support wlr-layer-shell");
+        }
+        let init = library.get::<unsafe extern "C" fn(GtkWindow)>(b"gtk_layer_init_for_window")?;
+        let set_layer = library.get::<unsafe extern "C" fn(GtkWindow, i32)>(b"gtk_layer_set_layer")?;
+        let set_anchor = library.get::<unsafe extern "C" fn(GtkWindow, i32, i32)>(b"gtk_layer_set_anchor")?;
+        let set_margin = library.get::<unsafe extern "C" fn(GtkWindow, i32, i32)>(b"gtk_layer_set_margin")?;
+        let set_keyboard_mode =
+            library.get::<unsafe extern "C" fn(GtkWindow, i32)>(b"gtk_layer_set_keyboard_mode")?;
+
+        // a layer surface has to be set up before the window is realized
+        window.hide();
+        window.unrealize();
+
+        let raw: GtkWindow = window.upcast_ref::<gtk::Window>().to_glib_none().0;
+        init(raw);
+        set_layer(raw, LAYER_OVERLAY);
+        set_anchor(raw, EDGE_BOTTOM, 1);
+        set_margin(raw, EDGE_BOTTOM, MARGIN);
+        // never take the keyboard, the text goes to the window the user was in
+        set_keyboard_mode(raw, KEYBOARD_NONE);
+    }
+
+    ANCHORED.store(true, Ordering::Relaxed);
+    info!("overlay anchored as a layer surface");
+    Ok(())
+}
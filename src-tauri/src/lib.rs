@@ -1,513 +1,2790 @@
-use anyhow::Result;
-use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use anyhow::{Context, Result};
+use clap::Parser;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::GlobalHotKeyManager;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::Mutex;
-use std::thread;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter, Manager, State, WindowEvent};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, WindowEvent};
 use tauri::tray::TrayIconBuilder;
-use tauri::tray::TrayIconEvent;
-use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconEvent};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tokio::sync::{broadcast, oneshot};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, error, warn};
 
 #[cfg(target_os = "linux")]
 use x11rb::protocol::xproto::ConnectionExt;
 
+mod api_transcription;
 mod audio;
+mod captions;
+mod clipboard;
+mod compositor;
 mod config;
+mod crash;
+mod demo;
+mod error;
+mod events;
+mod glossary;
 mod hardware;
+mod history;
+mod hotwords;
+mod hotkeys;
+mod i18n;
+mod idle;
+mod inbox;
 mod input;
+mod ipc;
+#[cfg(target_os = "linux")]
+mod layer_shell;
+mod llm;
+mod logging;
+#[cfg(feature = "local-llm")]
+mod local_llm;
+mod media;
+mod meeting;
+mod memory;
+mod metrics;
+mod messaging;
+mod models;
+mod monitor;
+mod open_mic;
+mod pace;
+mod password_guard;
+mod permissions;
+mod mqtt;
+mod pipeline;
+mod postprocess;
+mod privacy;
+mod prompts;
+mod queue;
+mod quick_pick;
+mod report;
+mod repro;
+mod routing;
+mod schedule;
+mod scratch;
+mod selection;
+mod server;
+mod service;
+mod shutdown;
+mod speech;
+mod symbols;
+mod theme;
 mod transcription;
+mod tray_cycle;
+mod tuning;
+mod two_pass;
+mod usage;
+mod vad;
+mod wake_word;
+mod webview_audio;
+#[cfg(target_os = "windows")]
+mod win32;
 
+pub use api_transcription::ApiTranscriber;
 pub use audio::AudioRecorder;
-pub use config::{ApiConfig, Config, ModelConfig, UiConfig};
+pub use captions::Captions;
+pub use compositor::{Compositor, FocusedWindow, Session};
+pub use config::{
+    AccessibilityConfig, ApiConfig, BusyPolicy, Config, FormatIntent, InsertMode, LlmConfig, LoggingConfig,
+    MediaConfig, ModelConfig, MqttConfig, OutputConfig, OutputTarget, RoutingConfig, SendTarget, UiConfig,
+};
+pub use crash::CrashReport;
+pub use error::WhisperiaError;
+pub use events::{DownloadProgress, Event, EventBus, TranscriptEvent};
+pub use glossary::{Correction, Glossary};
 pub use hardware::HardwareDetector;
+pub use history::HistoryEntry;
 pub use input::InputSimulator;
-pub use transcription::Transcriber;
+pub use meeting::Meeting;
+pub use metrics::{Metrics, RunMetrics};
+pub use permissions::{Permission, PermissionsStatus};
+pub use pipeline::{Cancelled, PipelineState};
+pub use report::RunReport;
+pub use prompts::PromptTemplate;
+pub use quick_pick::{QuickOptions, QuickPick};
+pub use theme::UiTheme;
+pub use transcription::{Segment, Transcriber, Transcript, Word};
+pub use usage::{ApiUsage, ProviderUsage};
+
+use i18n::{t, tf};
+use queue::JobQueue;
+
+const TRAY_ID: &str = "main";
+// hotkey dictations stop after this (with `capture.mode = "fixed"`), or earlier when stopped
+const MAX_RECORDING_SECS: u64 = 5;
+
+/// Command line flags accepted by the desktop app
+#[derive(Debug, Parser)]
+#[command(name = "whisperia")]
+struct LaunchArgs {
+    /// serve the local HTTP API on this address (e.g. 127.0.0.1:7391)
+    #[arg(long, value_name = "addr")]
+    serve: Option<SocketAddr>,
+    
+    /// print final transcripts to stdout
+    #[arg(long)]
+    print: bool,
+    
+    /// run only the shared transcription service on this address, without any window
+    #[arg(long, value_name = "addr")]
+    service: Option<SocketAddr>,
+    
+    /// keep config, models and logs next to the executable
+    #[arg(long)]
+    portable: bool,
+    
+    #[command(subcommand)]
+    command: Option<LaunchCommand>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum LaunchCommand {
+    /// run a repro bundle's stages again and show what differs from the recording
+    Replay {
+        /// the bundle directory, e.g. ~/.local/share/whisperia/repros/repro-1760000000
+        bundle: PathBuf,
+    },
+    /// decode the demo clip with the configured model, to check the setup without a microphone
+    Demo,
+    /// print the last transcriptions of the saved history
+    History {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// control the running app: transcribe, toggle, cancel, status or last-result
+    Ctl {
+        command: String,
+    },
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppStatus {
+    pub pipeline: PipelineState,
+    // derived from `pipeline`, kept for the frontend
     pub is_recording: bool,
     pub is_transcribing: bool,
     pub last_result: Option<String>,
 }
 
+/// Shared app state. Cloning is cheap and every clone sees the same flags,
+/// config and hotkey manager, so it can be moved into worker threads.
+#[derive(Clone)]
 pub struct AppState {
+    inner: Arc<AppStateInner>,
+}
+
+struct AppStateInner {
     status: Mutex<AppStatus>,
     config: Mutex<Config>,
+    // the config as last loaded or saved, see `flush`
+    config_saved: Mutex<String>,
     audio_data: Mutex<Option<Vec<f32>>>,
-    hotkey_manager: Mutex<Option<GlobalHotKeyManager>>,
+    hotkey_manager: Mutex<Option<(GlobalHotKeyManager, Vec<HotKey>)>>,
+    history: Mutex<history::History>,
+    metrics: Mutex<Metrics>,
+    jobs: JobQueue<Job>,
+    format_intent: Mutex<Option<String>>,
+    tone: Mutex<Option<String>>,
+    intent_hotkeys: Mutex<HashMap<u32, String>>,
+    profile_hotkeys: Mutex<HashMap<u32, String>>,
+    // drops a meeting bookmark instead of dictating
+    bookmark_hotkey: Mutex<Option<u32>>,
+    revise_hotkey: Mutex<Option<u32>>,
+    read_aloud_hotkey: Mutex<Option<u32>>,
+    run_cancel: Mutex<CancellationToken>,
+    // set while the live captions window is open
+    captions: Mutex<Option<CancellationToken>>,
+    // dictations held back until `send_compose`, `None` outside compose mode
+    compose: Mutex<Option<Compose>>,
+    scratch: Mutex<String>,
+    // answers the quick pick the overlay is showing before recording
+    quick_pick: Mutex<Option<oneshot::Sender<QuickPick>>>,
+    // the dictation waiting for audio recorded in the webview
+    webview_audio: Mutex<Option<oneshot::Sender<webview_audio::Recorded>>>,
+    // ends the capture of the dictation recording now, which is then transcribed
+    recording: Mutex<Option<CancellationToken>>,
+    meeting: Mutex<Option<meeting::Session>>,
+    wake_word: Mutex<Option<CancellationToken>>,
+    open_mic: Mutex<Option<CancellationToken>>,
+    // the dictation kept as a repro bundle, armed by `record_repro`
+    repro: Mutex<Option<repro::Capture>>,
+    shutdown: CancellationToken,
+    events: EventBus,
 }
 
 impl AppState {
     pub fn new() -> anyhow::Result<Self> {
-        let config = Config::load_or_create()?;
+        Ok(Self::with_config(Config::load_or_create()?))
+    }
+    
+    fn with_config(config: Config) -> Self {
+        i18n::set_locale(&config.ui.locale);
+        Config::set_offline(config.offline);
+        input::set_backend(config.output.typing);
+        Config::set_network(&config.network);
+        transcription::set_gpu(config.model.use_gpu);
         
-        Ok(Self {
+        let history = history::History::load(&config.history);
+        let saved = toml::to_string(&config).unwrap_or_default();
+        let inner = AppStateInner {
             status: Mutex::new(AppStatus {
+                pipeline: PipelineState::Idle,
                 is_recording: false,
                 is_transcribing: false,
                 last_result: None,
             }),
             config: Mutex::new(config),
+            config_saved: Mutex::new(saved),
             audio_data: Mutex::new(None),
             hotkey_manager: Mutex::new(None),
-        })
+            history: Mutex::new(history),
+            metrics: Mutex::new(Metrics::default()),
+            jobs: JobQueue::new(),
+            format_intent: Mutex::new(None),
+            tone: Mutex::new(None),
+            intent_hotkeys: Mutex::new(HashMap::new()),
+            profile_hotkeys: Mutex::new(HashMap::new()),
+            bookmark_hotkey: Mutex::new(None),
+            revise_hotkey: Mutex::new(None),
+            read_aloud_hotkey: Mutex::new(None),
+            run_cancel: Mutex::new(CancellationToken::new()),
+            captions: Mutex::new(None),
+            compose: Mutex::new(None),
+            scratch: Mutex::new(String::new()),
+            quick_pick: Mutex::new(None),
+            webview_audio: Mutex::new(None),
+            recording: Mutex::new(None),
+            meeting: Mutex::new(None),
+            wake_word: Mutex::new(None),
+            open_mic: Mutex::new(None),
+            repro: Mutex::new(None),
+            shutdown: CancellationToken::new(),
+            events: EventBus::new(),
+        };
+        
+        Self { inner: Arc::new(inner) }
     }
     
     pub fn get_status(&self) -> AppStatus {
-        self.status.lock().unwrap().clone()
+        self.inner.status.lock().clone()
+    }
+    
+    /// Move the pipeline to `next`, refusing transitions that make no sense
+    /// (e.g. Idle → Inserting) so a failed step can't leave stale flags behind
+    pub fn transition(&self, next: PipelineState) -> anyhow::Result<()> {
+        let mut status = self.inner.status.lock();
+        if !status.pipeline.can_transition_to(&next) {
+            anyhow::bail!(
+                "Invalid pipeline transition {} -> {}",
+                status.pipeline.name(),
+                next.name()
+            );
+        }
+        
+        info!("Pipeline {} -> {}", status.pipeline.name(), next.name());
+        status.is_recording = next == PipelineState::Recording;
+        status.is_transcribing = next == PipelineState::Transcribing;
+        status.pipeline = next;
+        self.publish(Event::State(status.clone()));
+        Ok(())
+    }
+    
+    /// Claim the pipeline for a new dictation and get the token that cancels it;
+    /// returns the current state when busy
+    pub fn begin_recording(&self) -> Result<CancellationToken, PipelineState> {
+        // the check and the switch happen under the same lock, two triggers can't both win
+        self.transition(PipelineState::Recording)
+            .map_err(|_| self.get_status().pipeline)?;
+        
+        let token = CancellationToken::new();
+        *self.inner.run_cancel.lock() = token.clone();
+        Ok(token)
+    }
+    
+    /// Cancel the running dictation, if any
+    pub fn cancel_run(&self) {
+        info!("Cancelling the current dictation");
+        self.inner.run_cancel.lock().cancel();
+    }
+    
+    /// Hold the next dictations back until `send_compose`, or type them again
+    fn set_compose_mode(&self, enabled: bool) {
+        let mut compose = self.inner.compose.lock();
+        if enabled != compose.is_some() {
+            *compose = enabled.then(Compose::default);
+            self.publish(Event::Compose(enabled.then(String::new)));
+        }
+    }
+    
+    fn composing(&self) -> bool {
+        self.inner.compose.lock().is_some()
+    }
+    
+    /// Add a dictation to the buffer and return all of it, `None` outside compose mode.
+    /// The message goes to the window the first dictation was for.
+    fn add_to_compose(&self, text: &str, target: &InsertTarget) -> Option<String> {
+        let mut compose = self.inner.compose.lock();
+        let compose = compose.as_mut()?;
+        compose.parts.push(text.to_string());
+        compose.target.get_or_insert_with(|| target.clone());
+        Some(compose.text())
+    }
+    
+    /// What `send_compose` would type now, `None` with nothing composed
+    fn composed(&self) -> Option<String> {
+        self.inner.compose.lock().as_ref().map(Compose::text).filter(|text| !text.is_empty())
+    }
+    
+    /// Empty the buffer, staying in compose mode
+    fn take_compose(&self) -> Option<Compose> {
+        self.inner.compose.lock().as_mut().map(std::mem::take)
+    }
+    
+    pub fn get_scratch(&self) -> String {
+        self.inner.scratch.lock().clone()
+    }
+    
+    pub fn set_scratch(&self, note: String) {
+        *self.inner.scratch.lock() = note;
+    }
+    
+    /// Add a dictation on its own line and return the whole note
+    pub fn append_scratch(&self, text: &str) -> String {
+        let mut note = self.inner.scratch.lock();
+        if !note.is_empty() && !note.ends_with('\n') {
+            note.push('\n');
+        }
+        note.push_str(text);
+        note.clone()
+    }
+    
+    pub fn set_quick_pick(&self, answer: Option<oneshot::Sender<QuickPick>>) {
+        *self.inner.quick_pick.lock() = answer;
+    }
+    
+    /// Hand the overlay's choice to the waiting dictation, false when none is waiting
+    pub fn answer_quick_pick(&self, pick: QuickPick) -> bool {
+        match self.inner.quick_pick.lock().take() {
+            Some(answer) => answer.send(pick).is_ok(),
+            None => false,
+        }
+    }
+    
+    pub fn set_webview_audio(&self, answer: Option<oneshot::Sender<webview_audio::Recorded>>) {
+        *self.inner.webview_audio.lock() = answer;
+    }
+    
+    /// Hand audio from the webview to the waiting dictation, false when none is waiting
+    pub fn answer_webview_audio(&self, samples: Vec<f32>, sample_rate: u32) -> bool {
+        match self.inner.webview_audio.lock().take() {
+            Some(answer) => answer.send((samples, sample_rate)).is_ok(),
+            None => false,
+        }
+    }
+    
+    fn set_recording(&self, stop: Option<CancellationToken>) {
+        *self.inner.recording.lock() = stop;
+    }
+    
+    /// End the recording of the running dictation and go on to transcribe it,
+    /// false when no dictation is recording
+    pub fn finish_recording(&self) -> bool {
+        match self.inner.recording.lock().take() {
+            Some(stop) => {
+                stop.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Token for a new live captions session, `None` when one is already running
+    pub fn begin_captions(&self) -> Option<CancellationToken> {
+        let mut captions = self.inner.captions.lock();
+        if captions.is_some() {
+            return None;
+        }
+        let token = CancellationToken::new();
+        *captions = Some(token.clone());
+        Some(token)
+    }
+    
+    pub fn stop_captions(&self) {
+        if let Some(token) = self.inner.captions.lock().take() {
+            token.cancel();
+        }
+    }
+    
+    pub fn captions_running(&self) -> bool {
+        self.inner.captions.lock().is_some()
+    }
+    
+    /// Token for the wake word listener, `None` when it's already listening
+    pub fn begin_wake_word(&self) -> Option<CancellationToken> {
+        let mut wake_word = self.inner.wake_word.lock();
+        if wake_word.is_some() {
+            return None;
+        }
+        let token = CancellationToken::new();
+        *wake_word = Some(token.clone());
+        Some(token)
+    }
+    
+    /// Returns false when it wasn't listening
+    pub fn stop_wake_word(&self) -> bool {
+        let token = self.inner.wake_word.lock().take();
+        token.map(|token| token.cancel()).is_some()
+    }
+    
+    pub fn wake_word_running(&self) -> bool {
+        self.inner.wake_word.lock().is_some()
+    }
+    
+    /// Token for the open mic listener, `None` when it's already on
+    pub fn begin_open_mic(&self) -> Option<CancellationToken> {
+        let mut open_mic = self.inner.open_mic.lock();
+        if open_mic.is_some() {
+            return None;
+        }
+        let token = CancellationToken::new();
+        *open_mic = Some(token.clone());
+        Some(token)
+    }
+    
+    /// Returns false when it wasn't on
+    pub fn stop_open_mic(&self) -> bool {
+        let token = self.inner.open_mic.lock().take();
+        token.map(|token| token.cancel()).is_some()
+    }
+    
+    pub fn open_mic_running(&self) -> bool {
+        self.inner.open_mic.lock().is_some()
+    }
+    
+    pub fn set_meeting(&self, session: meeting::Session) {
+        *self.inner.meeting.lock() = Some(session);
+    }
+    
+    pub fn take_meeting(&self) -> Option<meeting::Session> {
+        self.inner.meeting.lock().take()
+    }
+    
+    pub fn with_meeting<R>(&self, f: impl FnOnce(&meeting::Session) -> R) -> Option<R> {
+        self.inner.meeting.lock().as_ref().map(f)
+    }
+    
+    pub fn meeting_running(&self) -> bool {
+        self.inner.meeting.lock().is_some()
+    }
+    
+    /// Keep the next dictation as a repro bundle
+    pub fn arm_repro(&self) {
+        info!("The next dictation is recorded as a repro bundle");
+        self.inner.repro.lock().get_or_insert_with(repro::Capture::default);
+    }
+    
+    /// Add to the repro being recorded, if any
+    pub fn with_repro(&self, f: impl FnOnce(&mut repro::Capture)) {
+        if let Some(capture) = self.inner.repro.lock().as_mut() {
+            f(capture);
+        }
     }
     
-    pub fn set_recording(&self, recording: bool) {
-        let mut status = self.status.lock().unwrap();
-        status.is_recording = recording;
+    /// Save the repro of the dictation that just ended; one armed during it waits for the next
+    fn finish_repro(&self, report: Option<&RunReport>, outcome: &str) {
+        let capture = {
+            let mut repro = self.inner.repro.lock();
+            match repro.take() {
+                Some(capture) if capture.started() => capture,
+                armed => {
+                    *repro = armed;
+                    return;
+                }
+            }
+        };
+        match repro::save(capture, report, outcome) {
+            Ok(dir) => self.publish(Event::Status(tf("status.repro_saved", &[&dir.display()]))),
+            Err(e) => warn!("Failed to save the repro bundle: {:#}", e),
+        }
     }
     
-    pub fn set_transcribing(&self, transcribing: bool) {
-        let mut status = self.status.lock().unwrap();
-        status.is_transcribing = transcribing;
+    pub fn fail(&self, message: &str) {
+        let _ = self.transition(PipelineState::Error {
+            message: message.to_string(),
+        });
     }
     
     pub fn set_result(&self, result: String) {
-        let mut status = self.status.lock().unwrap();
+        let mut status = self.inner.status.lock();
         status.last_result = Some(result);
-        status.is_transcribing = false;
-        status.is_recording = false;
+        self.publish(Event::State(status.clone()));
     }
     
     pub fn store_audio(&self, data: Vec<f32>) {
-        let mut audio = self.audio_data.lock().unwrap();
+        let mut audio = self.inner.audio_data.lock();
         *audio = Some(data);
     }
     
     pub fn take_audio(&self) -> Option<Vec<f32>> {
-        let mut audio = self.audio_data.lock().unwrap();
+        let mut audio = self.inner.audio_data.lock();
         audio.take()
     }
     
     pub fn get_config(&self) -> Config {
-        self.config.lock().unwrap().clone()
+        self.inner.config.lock().clone()
     }
     
     pub fn update_config(&self, config: Config) -> anyhow::Result<()> {
         config.save()?;
-        let mut cfg = self.config.lock().unwrap();
+        *self.inner.config_saved.lock() = toml::to_string(&config)?;
+        i18n::set_locale(&config.ui.locale);
+        Config::set_offline(config.offline);
+        input::set_backend(config.output.typing);
+        Config::set_network(&config.network);
+        let mut cfg = self.inner.config.lock();
+        // `set_inference_backend` picks one for this session, kept until use_gpu itself changes
+        if config.model.use_gpu != cfg.model.use_gpu {
+            transcription::set_gpu(config.model.use_gpu);
+        }
         *cfg = config;
         Ok(())
     }
     
-    pub fn set_hotkey_manager(&self, manager: GlobalHotKeyManager) {
-        let mut hm = self.hotkey_manager.lock().unwrap();
-        *hm = Some(manager);
+    pub fn set_hotkey_manager(&self, manager: GlobalHotKeyManager, hotkeys: Vec<HotKey>) {
+        let mut hm = self.inner.hotkey_manager.lock();
+        *hm = Some((manager, hotkeys));
     }
-}
-
-// Implement Clone for AppState
-impl Clone for AppState {
-    fn clone(&self) -> Self {
-        Self {
-            status: Mutex::new(self.get_status()),
-            config: Mutex::new(self.get_config()),
-            audio_data: Mutex::new(None),
-            hotkey_manager: Mutex::new(None),
+    
+    pub fn unregister_hotkeys(&self) {
+        if let Some((manager, hotkeys)) = self.inner.hotkey_manager.lock().take() {
+            if let Err(e) = manager.unregister_all(&hotkeys) {
+                warn!("Failed to unregister hotkeys: {}", e);
+            }
+        }
+    }
+    
+    /// Cancelled once when the app starts shutting down; background tasks watch it
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.inner.shutdown.clone()
+    }
+    
+    pub fn is_shutting_down(&self) -> bool {
+        self.inner.shutdown.is_cancelled()
+    }
+    
+    /// Returns false when a shutdown was already under way
+    pub fn begin_shutdown(&self) -> bool {
+        if self.is_shutting_down() {
+            return false;
+        }
+        self.inner.shutdown.cancel();
+        true
+    }
+    
+    /// Write out everything that lives in memory. The config only when it
+    /// changed since it was loaded or saved, so a config.toml edited by hand
+    /// while the app runs isn't overwritten on quit.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let config = self.get_config();
+        let current = toml::to_string(&config)?;
+        let mut saved = self.inner.config_saved.lock();
+        if *saved != current {
+            config.save()?;
+            *saved = current;
+        }
+        Ok(())
+    }
+    
+    pub fn add_history(&self, mut entry: HistoryEntry) {
+        let redaction = self.get_config().redaction;
+        if redaction.scope.history() {
+            entry.text = postprocess::redact(&entry.text, &redaction);
+            entry.original = entry.original.map(|original| postprocess::redact(&original, &redaction));
         }
+        self.inner.history.lock().push(entry);
+    }
+    
+    pub fn get_history(&self) -> Vec<HistoryEntry> {
+        self.inner.history.lock().entries()
+    }
+    
+    /// Delete one entry of the history, false when there's none with that id
+    pub fn delete_history(&self, id: u64) -> bool {
+        self.inner.history.lock().remove(id)
+    }
+    
+    pub fn clear_history(&self) {
+        self.inner.history.lock().clear();
+    }
+    
+    /// Finish the run's report with how it ended, saving it for `get_last_run_report`
+    pub fn record_run(&self, mut report: RunReport, outcome: &str) {
+        let run = &report.metrics;
+        info!(
+            "Run metrics: {}ms audio, {}ms load, {}ms decode (rtf {:.2})",
+            run.audio_ms, run.model_load_ms, run.decode_ms, run.real_time_factor
+        );
+        self.inner.metrics.lock().record(run.clone());
+        report.finish(outcome);
+        report::save(&report);
+        self.finish_repro(Some(&report), outcome);
+    }
+    
+    pub fn get_metrics(&self) -> Metrics {
+        self.inner.metrics.lock().clone()
+    }
+    
+    /// Dictations waiting for the running one to finish
+    fn jobs(&self) -> &JobQueue<Job> {
+        &self.inner.jobs
+    }
+    
+    /// The formatting intent picked in the tray, used when none is given explicitly
+    pub fn get_format_intent(&self) -> Option<String> {
+        self.inner.format_intent.lock().clone()
+    }
+    
+    pub fn set_format_intent(&self, intent: Option<String>) {
+        info!("Format intent set to {:?}", intent);
+        *self.inner.format_intent.lock() = intent;
+    }
+    
+    /// Tone preset (formal, casual, ...) applied after the formatting intent
+    pub fn get_tone(&self) -> Option<String> {
+        self.inner.tone.lock().clone()
+    }
+    
+    pub fn set_tone(&self, tone: Option<String>) {
+        info!("Tone preset set to {:?}", tone);
+        *self.inner.tone.lock() = tone;
+    }
+    
+    pub fn register_intent_hotkey(&self, hotkey_id: u32, intent: String) {
+        self.inner.intent_hotkeys.lock().insert(hotkey_id, intent);
+    }
+    
+    pub fn intent_for_hotkey(&self, hotkey_id: u32) -> Option<String> {
+        self.inner.intent_hotkeys.lock().get(&hotkey_id).cloned()
+    }
+    
+    pub fn register_profile_hotkey(&self, hotkey_id: u32, profile: String) {
+        self.inner.profile_hotkeys.lock().insert(hotkey_id, profile);
+    }
+    
+    pub fn profile_for_hotkey(&self, hotkey_id: u32) -> Option<String> {
+        self.inner.profile_hotkeys.lock().get(&hotkey_id).cloned()
+    }
+    
+    pub fn set_bookmark_hotkey(&self, hotkey_id: Option<u32>) {
+        *self.inner.bookmark_hotkey.lock() = hotkey_id;
+    }
+    
+    pub fn is_bookmark_hotkey(&self, hotkey_id: u32) -> bool {
+        *self.inner.bookmark_hotkey.lock() == Some(hotkey_id)
+    }
+    
+    pub fn set_revise_hotkey(&self, hotkey_id: Option<u32>) {
+        *self.inner.revise_hotkey.lock() = hotkey_id;
+    }
+    
+    pub fn is_revise_hotkey(&self, hotkey_id: u32) -> bool {
+        *self.inner.revise_hotkey.lock() == Some(hotkey_id)
+    }
+    
+    pub fn set_read_aloud_hotkey(&self, hotkey_id: Option<u32>) {
+        *self.inner.read_aloud_hotkey.lock() = hotkey_id;
+    }
+    
+    pub fn is_read_aloud_hotkey(&self, hotkey_id: u32) -> bool {
+        *self.inner.read_aloud_hotkey.lock() == Some(hotkey_id)
+    }
+    
+    pub fn publish(&self, event: Event) {
+        self.inner.events.publish(event);
+    }
+    
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.inner.events.subscribe()
     }
 }
 
 #[tauri::command]
-async fn get_status(state: State<'_, AppState>) -> Result<AppStatus, String> {
+async fn get_status(state: State<'_, AppState>) -> Result<AppStatus, WhisperiaError> {
     Ok(state.get_status())
 }
 
 #[tauri::command]
-async fn get_config(state: State<'_, AppState>) -> Result<Config, String> {
+async fn get_config(state: State<'_, AppState>) -> Result<Config, WhisperiaError> {
     Ok(state.get_config())
 }
 
 #[tauri::command]
 async fn update_config(
     config: Config,
+    app: AppHandle,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    state.update_config(config).map_err(|e| e.to_string())
+) -> Result<(), WhisperiaError> {
+    let theme = theme::resolve(&config.ui);
+    // a shortcut that can't be read, or registered since another app holds it,
+    // is refused before it's saved and the hotkeys in use stay
+    let previous = state.get_config();
+    let shortcut_changed = config.shortcut != previous.shortcut;
+    if shortcut_changed {
+        hotkeys::parse_shortcut(&config.shortcut)?;
+        info!("Shortcut changed, registering the hotkeys again");
+        hotkeys::replace(&app, config.clone()).map_err(|e| WhisperiaError::Hotkey(format!("{:#}", e)))?;
+    }
+    // the language can pick another model too, see `prefer_language_models`
+    let model_changed = config.model.local_model != previous.model.local_model
+        || config.model.model_type != previous.model.model_type
+        || config.model.use_quantized != previous.model.use_quantized
+        || config.model.use_gpu != previous.model.use_gpu
+        || config.language != previous.language;
+    if let Err(e) = state.update_config(config) {
+        // back to the hotkeys of the config still in use
+        if shortcut_changed {
+            let _ = hotkeys::restart(&app);
+        }
+        return Err(e.into());
+    }
+    state.publish(Event::Theme(theme));
+    if model_changed {
+        preload_in_background(state.get_config());
+    }
+    Ok(())
 }
 
+/// Theme, opacity, font scale and reduced motion for the webviews, from the
+/// config and the OS accessibility settings
 #[tauri::command]
-fn get_available_models() -> Result<Vec<hardware::ModelCompatibility>, String> {
-    let detector = HardwareDetector::new().map_err(|e| e.to_string())?;
-    Ok(detector.get_available_models())
+async fn get_ui_theme(state: State<'_, AppState>) -> Result<UiTheme, WhisperiaError> {
+    Ok(theme::resolve(&state.get_config().ui))
 }
 
+/// Change the log level now and for the next starts ("debug", or full directives
+/// like "info,whisperia_lib::transcription=trace")
 #[tauri::command]
-fn get_system_info() -> Result<hardware::SystemInfo, String> {
-    let detector = HardwareDetector::new().map_err(|e| e.to_string())?;
-    Ok(detector.get_system_info())
+async fn set_log_level(level: String, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    logging::set_level(&level).map_err(|e| WhisperiaError::Invalid(format!("{:#}", e)))?;
+    
+    let mut config = state.get_config();
+    config.logging.level = level;
+    Ok(state.update_config(config)?)
 }
 
+/// Reports of crashes in earlier runs, newest first
 #[tauri::command]
-async fn open_settings(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("main") {
-        let _ = window.show();
-        let _ = window.set_focus();
-        let _ = window.center();
+fn get_crash_reports() -> Result<Vec<CrashReport>, WhisperiaError> {
+    Ok(crash::list()?)
+}
+
+#[tauri::command]
+fn dismiss_crash_reports() -> Result<(), WhisperiaError> {
+    Ok(crash::clear()?)
+}
+
+/// Stage timings of the last dictation and totals since start, for picking a model
+#[tauri::command]
+async fn get_metrics(state: State<'_, AppState>) -> Result<Metrics, WhisperiaError> {
+    Ok(state.get_metrics())
+}
+
+/// Device, audio path, VAD, model, decode settings and timings of the last dictation
+#[tauri::command]
+async fn get_last_run_report() -> Result<Option<RunReport>, WhisperiaError> {
+    Ok(report::load()?)
+}
+
+/// Load the configured model now instead of on the next dictation, returning its file
+#[tauri::command]
+async fn preload_model(state: State<'_, AppState>) -> Result<PathBuf, WhisperiaError> {
+    let config = state.get_config();
+    Ok(tauri::async_runtime::spawn_blocking(move || load_model(&config))
+        .await
+        .context("Model loading task failed")??)
+}
+
+#[tauri::command]
+fn get_available_models(state: State<'_, AppState>) -> Result<Vec<hardware::ModelCompatibility>, WhisperiaError> {
+    let detector = HardwareDetector::new()?;
+    let mut models = detector.get_available_models();
+    // Language packs from the config, listed with the language they're for
+    for pack in state.get_config().model.language_models {
+        let mut compatibility = detector.check_model_compatibility(&pack.name);
+        compatibility.language = Some(pack.language);
+        models.push(compatibility);
     }
-    Ok(())
+    Ok(models)
+}
+
+/// The hardware, and the backend whisper decodes on with it
+#[derive(Debug, Clone, Serialize)]
+struct SystemReport {
+    #[serde(flatten)]
+    system: hardware::SystemInfo,
+    inference_backend: transcription::Backend,
 }
 
 #[tauri::command]
-async fn show_overlay(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("overlay") {
-        let (x, y) = get_cursor_position();
-        let _ = window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y }));
-        let _ = window.show();
-        let _ = window.set_focus();
+fn get_system_info() -> Result<SystemReport, WhisperiaError> {
+    let detector = HardwareDetector::new()?;
+    Ok(SystemReport {
+        system: detector.get_system_info(),
+        inference_backend: transcription::backend(),
+    })
+}
+
+/// The backend whisper decodes on, and the ones this build can switch to
+#[derive(Debug, Clone, Serialize)]
+struct InferenceBackends {
+    current: transcription::Backend,
+    available: Vec<transcription::Backend>,
+}
+
+impl InferenceBackends {
+    fn now() -> Self {
+        Self {
+            current: transcription::backend(),
+            available: transcription::Backend::available(),
+        }
     }
-    Ok(())
 }
 
 #[tauri::command]
-async fn hide_overlay(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("overlay") {
-        let _ = window.hide();
+fn get_inference_backends() -> InferenceBackends {
+    InferenceBackends::now()
+}
+
+/// Decode on the cpu or a gpu backend from now on. The current model is loaded
+/// there right away, so the next dictation's timings are the backend's own.
+#[tauri::command]
+async fn set_inference_backend(
+    state: State<'_, AppState>,
+    backend: transcription::Backend,
+) -> Result<InferenceBackends, WhisperiaError> {
+    let previous = transcription::backend();
+    transcription::set_backend(backend).map_err(|e| WhisperiaError::Invalid(format!("{:#}", e)))?;
+    
+    let config = state.get_config();
+    let (model_path, _) = memory::pick_model(&config)?;
+    let cached_models = config.model.cached_models;
+    let loaded = tokio::task::spawn_blocking(move || Transcriber::cached(&model_path, cached_models))
+        .await
+        .map_err(|e| WhisperiaError::Internal(e.to_string()))?;
+    if let Err(e) = loaded {
+        warn!("Failed to load the model on {}, back to {}: {:#}", backend.name(), previous.name(), e);
+        let _ = transcription::set_backend(previous);
+        return Err(WhisperiaError::decode_failed(e));
     }
-    Ok(())
+    Ok(InferenceBackends::now())
 }
 
 #[tauri::command]
-async fn start_recording(app: AppHandle) -> Result<(), String> {
-    info!("start_recording command invoked");
-    trigger_transcription_flow(app).map_err(|e| {
-        error!("Failed to start recording: {}", e);
-        e.to_string()
+fn get_permissions_status() -> PermissionsStatus {
+    permissions::status()
+}
+
+#[tauri::command]
+fn open_permission_settings(permission: String) -> Result<(), WhisperiaError> {
+    permissions::open_settings(&permission)
+}
+
+#[tauri::command]
+async fn get_send_targets(state: State<'_, AppState>) -> Result<Vec<String>, WhisperiaError> {
+    let config = state.get_config();
+    Ok(config.send_targets.iter().map(|t| t.name().to_string()).collect())
+}
+
+/// Send a confirmed transcript (defaults to the last result) to a messaging target.
+/// Edits made to it are learned for the glossary.
+#[tauri::command]
+async fn send_transcript(
+    target: String,
+    text: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), WhisperiaError> {
+    let last_result = state.get_status().last_result;
+    let text = match text {
+        Some(text) => {
+            if let Some(original) = last_result.as_deref().filter(|original| *original != text) {
+                if let Err(e) = learn_corrections(&state, original, &text) {
+                    warn!("Failed to learn corrections: {}", e);
+                }
+            }
+            text
+        }
+        None => last_result.ok_or_else(|| WhisperiaError::Invalid("No transcript to send".to_string()))?,
+    };
+    send_to_target(&state.get_config(), &target, &text).await
+}
+
+/// Count the corrections in an edited transcript (`original` defaults to the
+/// last result) and return the ones now offered for the glossary
+#[tauri::command]
+async fn learn_correction(
+    original: Option<String>,
+    edited: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Correction>, WhisperiaError> {
+    let original = match original {
+        Some(original) => original,
+        None => state
+            .get_status()
+            .last_result
+            .ok_or_else(|| WhisperiaError::Invalid("No transcript to compare with".to_string()))?,
+    };
+    learn_corrections(&state, &original, &edited)
+}
+
+fn learn_corrections(state: &AppState, original: &str, edited: &str) -> Result<Vec<Correction>, WhisperiaError> {
+    let config = state.get_config().glossary;
+    if !config.learn {
+        return Ok(Vec::new());
+    }
+    let mut glossary = Glossary::load()?;
+    let suggestions = glossary.learn(original, edited, config.suggest_after.max(1));
+    glossary.save()?;
+    if !suggestions.is_empty() {
+        info!("Offering {} corrections for the glossary", suggestions.len());
+        state.publish(Event::GlossarySuggestions(suggestions.clone()));
+    }
+    Ok(suggestions)
+}
+
+#[tauri::command]
+async fn get_glossary() -> Result<Glossary, WhisperiaError> {
+    Ok(Glossary::load()?)
+}
+
+/// Type `to` from now on where whisper hears `from`
+#[tauri::command]
+async fn add_glossary_entry(from: String, to: String) -> Result<(), WhisperiaError> {
+    if from.trim().is_empty() {
+        return Err(WhisperiaError::Invalid("The word to replace is empty".to_string()));
+    }
+    let mut glossary = Glossary::load()?;
+    glossary.add(from.trim(), to.trim());
+    Ok(glossary.save()?)
+}
+
+#[tauri::command]
+async fn remove_glossary_entry(from: String) -> Result<(), WhisperiaError> {
+    let mut glossary = Glossary::load()?;
+    glossary.remove(&from);
+    Ok(glossary.save()?)
+}
+
+/// Add a hotword to a profile, or to `model.hotwords` without one
+#[tauri::command]
+async fn add_hotword(
+    phrase: String,
+    profile: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, WhisperiaError> {
+    edit_hotwords(&state, profile.as_deref(), |hotwords| {
+        let phrase = phrase.trim();
+        if !hotwords.iter().any(|h| h.eq_ignore_ascii_case(phrase)) {
+            hotwords.push(phrase.to_string());
+        }
     })
 }
 
-#[tauri::command]
-async fn stop_recording(app: AppHandle) -> Result<(), String> {
-    info!("stop_recording command invoked");
-    // For now, this just hides the overlay and resets state
-    let state = app.state::<AppState>();
-    state.set_recording(false);
-    state.set_transcribing(false);
-    hide_overlay_window(&app).map_err(|e| e.to_string())
+#[tauri::command]
+async fn remove_hotword(
+    phrase: String,
+    profile: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, WhisperiaError> {
+    edit_hotwords(&state, profile.as_deref(), |hotwords| {
+        hotwords.retain(|h| !h.eq_ignore_ascii_case(phrase.trim()))
+    })
+}
+
+/// Add the last word the user corrected in the edit window (`original`
+/// defaults to the last result) as a hotword, returning it
+#[tauri::command]
+async fn add_hotword_from_edit(
+    original: Option<String>,
+    edited: String,
+    profile: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, WhisperiaError> {
+    let original = match original {
+        Some(original) => original,
+        None => state
+            .get_status()
+            .last_result
+            .ok_or_else(|| WhisperiaError::Invalid("No transcript to compare with".to_string()))?,
+    };
+    let (_, corrected) = glossary::corrections(&original, &edited)
+        .pop()
+        .ok_or_else(|| WhisperiaError::Invalid("No corrected word in the edit".to_string()))?;
+    info!("Adding corrected '{}' as a hotword", corrected);
+    edit_hotwords(&state, profile.as_deref(), |hotwords| {
+        if !hotwords.iter().any(|h| h.eq_ignore_ascii_case(&corrected)) {
+            hotwords.push(corrected.clone());
+        }
+    })?;
+    Ok(corrected)
+}
+
+fn edit_hotwords(
+    state: &AppState,
+    profile: Option<&str>,
+    edit: impl FnOnce(&mut Vec<String>),
+) -> Result<Vec<String>, WhisperiaError> {
+    let mut config = state.get_config();
+    let hotwords = config
+        .hotwords_mut(profile)
+        .ok_or_else(|| WhisperiaError::Invalid(format!("no profile named '{}'", profile.unwrap_or_default())))?;
+    edit(hotwords);
+    let hotwords = hotwords.clone();
+    state.update_config(config)?;
+    Ok(hotwords)
+}
+
+/// Stop offering a correction that was a one-off
+#[tauri::command]
+async fn dismiss_glossary_suggestion(from: String, to: String) -> Result<(), WhisperiaError> {
+    let mut glossary = Glossary::load()?;
+    glossary.dismiss(&from, &to);
+    Ok(glossary.save()?)
+}
+
+async fn send_to_target(config: &Config, target: &str, text: &str) -> Result<(), WhisperiaError> {
+    let target = config
+        .send_targets
+        .iter()
+        .find(|t| t.name() == target)
+        .ok_or_else(|| WhisperiaError::Invalid(format!("Unknown send target: {}", target)))?;
+    
+    messaging::send(target, text).await.map_err(|e| {
+        error!("Failed to send transcript: {}", e);
+        e.into()
+    })
+}
+
+#[tauri::command]
+async fn get_format_intents(state: State<'_, AppState>) -> Result<Vec<FormatIntent>, WhisperiaError> {
+    Ok(state.get_config().llm.intents)
+}
+
+/// Select the intent applied to the next dictations (`None` types the plain transcript)
+#[tauri::command]
+async fn set_format_intent(intent: Option<String>, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    if let Some(name) = &intent {
+        if state.get_config().llm.intent(name).is_none() {
+            return Err(WhisperiaError::Invalid(format!("Unknown format intent: {}", name)));
+        }
+    }
+    state.set_format_intent(intent);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_tones(state: State<'_, AppState>) -> Result<Vec<FormatIntent>, WhisperiaError> {
+    Ok(state.get_config().llm.tones)
+}
+
+/// Select the tone preset for the next dictations, e.g. from the overlay before inserting
+#[tauri::command]
+async fn set_tone(tone: Option<String>, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    if let Some(name) = &tone {
+        if state.get_config().llm.tone(name).is_none() {
+            return Err(WhisperiaError::Invalid(format!("Unknown tone preset: {}", name)));
+        }
+    }
+    state.set_tone(tone);
+    Ok(())
+}
+
+#[tauri::command]
+fn list_prompt_templates() -> Result<Vec<PromptTemplate>, WhisperiaError> {
+    Ok(prompts::list()?)
+}
+
+/// Create or overwrite a template; bind it with `template = "<name>"` on an llm intent
+#[tauri::command]
+fn save_prompt_template(template: PromptTemplate) -> Result<(), WhisperiaError> {
+    Ok(prompts::save(&template)?)
+}
+
+#[tauri::command]
+fn delete_prompt_template(name: String) -> Result<(), WhisperiaError> {
+    Ok(prompts::delete(&name)?)
+}
+
+/// Past transcriptions, newest first
+#[tauri::command]
+async fn get_history(state: State<'_, AppState>) -> Result<Vec<HistoryEntry>, WhisperiaError> {
+    Ok(state.get_history())
+}
+
+#[tauri::command]
+async fn delete_history_entry(state: State<'_, AppState>, id: u64) -> Result<(), WhisperiaError> {
+    if !state.delete_history(id) {
+        return Err(WhisperiaError::Invalid(format!("No history entry {}", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn clear_history(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    state.clear_history();
+    Ok(())
+}
+
+/// Summary and action items for a transcript (defaults to the latest history entry),
+/// saved into history next to the raw text
+#[tauri::command]
+async fn summarize_transcript(
+    text: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, WhisperiaError> {
+    let (text, language) = match text {
+        Some(text) => (text, state.get_config().language),
+        None => {
+            let latest = state
+                .get_history()
+                .into_iter()
+                .next()
+                .ok_or_else(|| WhisperiaError::Invalid("Nothing to summarize yet".to_string()))?;
+            (latest.original.unwrap_or(latest.text), latest.language)
+        }
+    };
+    
+    summarize(&state, &text, &language).await
+}
+
+async fn summarize(state: &AppState, text: &str, language: &str) -> Result<String, WhisperiaError> {
+    let config = state.get_config();
+    let summary = llm::summarize(&config.llm, text).await.map_err(|e| {
+        error!("Failed to summarize transcript: {}", e);
+        WhisperiaError::from(e)
+    })?;
+    warn_about_budget(state, &config);
+    
+    state.add_history(HistoryEntry::new(&summary, language, "summary").with_original(text));
+    state.publish(Event::Summary(summary.clone()));
+    Ok(summary)
+}
+
+/// Download a whisper model by name (tiny, base.en, a language pack, ...) or, with "llm", the local LLM model.
+/// Progress is emitted as `model-download-progress` events.
+#[tauri::command]
+async fn download_model(
+    model: String,
+    state: State<'_, AppState>,
+) -> Result<String, WhisperiaError> {
+    let config = state.get_config();
+    let (url, file_name) = if model == "llm" {
+        if !config.llm.local_model.starts_with("http") {
+            return Err(WhisperiaError::Invalid(format!(
+                "llm.local_model is not a download url: {}",
+                config.llm.local_model
+            )));
+        }
+        (config.llm.local_model.clone(), models::llm_model_file(&config.llm).to_string())
+    } else if models::is_known_model(&config.model, &model) {
+        models::whisper_model_source(&config.model, &model)
+    } else {
+        return Err(WhisperiaError::Invalid(format!("unknown model: {}", model)));
+    };
+    
+    let progress_file = file_name.clone();
+    let events = state.inner().clone();
+    let mut last_percent = None;
+    let on_progress = move |downloaded: u64, total: Option<u64>| {
+        // one event per percent is plenty for a progress bar
+        let percent = total.map(|t| downloaded * 100 / t.max(1));
+        if percent.is_none() || percent != last_percent {
+            last_percent = percent;
+            events.publish(Event::DownloadProgress(DownloadProgress {
+                file: progress_file.clone(),
+                downloaded,
+                total,
+            }));
+        }
+    };
+    
+    let result = models::download(&url, &file_name, on_progress).await;
+    result.map(|path| path.display().to_string()).map_err(|e| {
+        error!("Model download failed: {}", e);
+        e.into()
+    })
+}
+
+#[tauri::command]
+async fn open_settings(app: AppHandle) -> Result<(), WhisperiaError> {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        let _ = window.center();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn show_overlay(app: AppHandle) -> Result<(), WhisperiaError> {
+    if let Some(window) = app.get_webview_window("overlay") {
+        let (x, y) = get_cursor_position();
+        place_overlay(&window, x, y);
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn hide_overlay(app: AppHandle) -> Result<(), WhisperiaError> {
+    if let Some(window) = app.get_webview_window("overlay") {
+        let _ = window.hide();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_recording(app: AppHandle) -> Result<(), WhisperiaError> {
+    info!("start_recording command invoked");
+    trigger_transcription_flow(app, None).map_err(|e| {
+        error!("Failed to start recording: {}", e);
+        e
+    })
+}
+
+/// Dictate an instruction for the text selected in the focused app, see `llm.revise_hotkey`
+#[tauri::command]
+async fn revise_selection(app: AppHandle) -> Result<(), WhisperiaError> {
+    info!("revise_selection command invoked");
+    trigger_revise_flow(app).map_err(|e| {
+        error!("Failed to revise the selection: {}", e);
+        e
+    })
+}
+
+/// Audio the overlay recorded for the running dictation (`capture.source =
+/// "webview"`): raw 32 bit float mono with an `x-sample-rate` header, or a wav file
+#[tauri::command]
+async fn submit_webview_audio(request: tauri::ipc::Request<'_>, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    let tauri::ipc::InvokeBody::Raw(body) = request.body() else {
+        return Err(WhisperiaError::Invalid("webview audio must be sent as raw bytes".to_string()));
+    };
+    let sample_rate = request
+        .headers()
+        .get("x-sample-rate")
+        .map(|rate| rate.to_str().ok().and_then(|rate| rate.parse::<u32>().ok()).filter(|rate| *rate > 0))
+        .map(|rate| rate.ok_or_else(|| WhisperiaError::Invalid("invalid x-sample-rate header".to_string())))
+        .transpose()?;
+    let (samples, sample_rate) = webview_audio::decode(body, sample_rate)?;
+    info!("Received {} samples at {}hz from the webview", samples.len(), sample_rate);
+    if !state.answer_webview_audio(samples, sample_rate) {
+        return Err(WhisperiaError::Invalid("No dictation is waiting for webview audio".to_string()));
+    }
+    Ok(())
+}
+
+/// Read the last result aloud, or the compose buffer while composing, to check
+/// it without looking at the screen
+#[tauri::command]
+async fn speak_last_result(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || read_last_result(&state))
+        .await
+        .map_err(|e| WhisperiaError::Internal(e.to_string()))?
+}
+
+/// Speak what will be (the compose buffer) or was (the last result) inserted,
+/// in the dictation language. Blocks until it's spoken.
+pub fn read_last_result(state: &AppState) -> Result<(), WhisperiaError> {
+    let text = state
+        .composed()
+        .or_else(|| state.get_status().last_result)
+        .ok_or_else(|| WhisperiaError::Invalid("No transcript to read".to_string()))?;
+    let config = state.get_config();
+    let language = Some(config.language.as_str()).filter(|language| *language != "auto");
+    info!("Reading the last result aloud");
+    Ok(speech::speak_in(&text, config.accessibility.voice.as_deref(), language)?)
+}
+
+/// Keep the next dictation as a repro bundle for a bug report, see `whisperia replay`
+#[tauri::command]
+async fn record_repro(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    state.arm_repro();
+    Ok(())
+}
+
+/// Dictate the demo clip instead of the microphone, see `demo`
+#[tauri::command]
+async fn try_demo(app: AppHandle) -> Result<demo::DemoResult, WhisperiaError> {
+    info!("try_demo command invoked");
+    demo::run(app).await
+}
+
+/// Step through the models or languages like the tray does, see `ui.tray_cycle`
+#[tauri::command]
+async fn cycle_tray_selection(step: i32, state: State<'_, AppState>) -> Result<String, WhisperiaError> {
+    tray_cycle::cycle(&state, step)
+}
+
+/// Replace the typed draft with the refined transcript offered on the overlay (`[two_pass]`)
+#[tauri::command]
+async fn accept_refinement(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    two_pass::accept(&state).await
+}
+
+#[tauri::command]
+async fn dismiss_refinement(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    two_pass::dismiss(&state);
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_recording(app: AppHandle) -> Result<(), WhisperiaError> {
+    info!("stop_recording command invoked");
+    // In toggle mode this ends the capture, what was recorded so far is transcribed
+    let state = app.state::<AppState>();
+    if state.get_config().capture.mode == config::RecordingMode::Toggle && state.finish_recording() {
+        return Ok(());
+    }
+    // otherwise the dictation is dropped, the pipeline task goes back to idle
+    state.cancel_run();
+    Ok(hide_overlay_window(&app)?)
+}
+
+/// Throw away the running dictation in any mode, and the ones queued after it:
+/// the recording stops, whisper aborts mid-decode and nothing is typed
+#[tauri::command]
+async fn cancel_recording(app: AppHandle) -> Result<(), WhisperiaError> {
+    let state = app.state::<AppState>();
+    let dropped = state.jobs().clear();
+    info!("cancel_recording command invoked ({} queued dropped)", dropped);
+    state.cancel_run();
+    Ok(hide_overlay_window(&app)?)
+}
+
+/// What the user did to the overlay
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum OverlayGesture {
+    /// pressed and released after `held_ms`
+    Press { held_ms: u64 },
+    Escape,
+}
+
+/// Cancel the running dictation from the overlay: Esc, or holding it down for
+/// `ui.overlay_cancel_hold_ms` so a stray click doesn't throw a dictation away
+#[tauri::command]
+async fn overlay_gesture(
+    gesture: OverlayGesture,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<bool, WhisperiaError> {
+    if window.label() != "overlay" || !state.get_status().pipeline.is_busy() {
+        return Ok(false);
+    }
+    let cancel = match gesture {
+        OverlayGesture::Escape => true,
+        OverlayGesture::Press { held_ms } => held_ms >= state.get_config().ui.overlay_cancel_hold_ms,
+    };
+    if cancel {
+        info!("Overlay gesture {:?}, cancelling", gesture);
+        state.cancel_run();
+    }
+    Ok(cancel)
+}
+
+/// Models and languages the overlay can offer before recording starts
+#[tauri::command]
+async fn get_quick_options(state: State<'_, AppState>) -> Result<QuickOptions, WhisperiaError> {
+    Ok(quick_pick::options(&state.get_config()))
+}
+
+/// Use another model or language for the dictation about to record
+#[tauri::command]
+async fn select_quick_option(pick: QuickPick, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    quick_pick::validate(&pick, &state.get_config())?;
+    if !state.answer_quick_pick(pick) {
+        return Err(WhisperiaError::Invalid("no dictation is waiting for a quick pick".to_string()));
+    }
+    Ok(())
+}
+
+/// Requests, audio and tokens sent to cloud providers this month, with the estimated cost
+#[tauri::command]
+async fn get_api_usage(state: State<'_, AppState>) -> Result<ApiUsage, WhisperiaError> {
+    Ok(usage::this_month(&state.get_config().usage)?)
+}
+
+/// Tell the user once a month when the estimated cost gets close to `usage.monthly_budget`
+fn warn_about_budget(state: &AppState, config: &Config) {
+    if let Some((spent, budget)) = usage::budget_warning(&config.usage) {
+        warn!("API usage this month is {:.2} of a {:.2} budget", spent, budget);
+        let (spent, budget) = (format!("{:.2}", spent), format!("{:.2}", budget));
+        state.publish(Event::Status(tf("status.budget", &[&spent, &budget])));
+    }
+}
+
+/// Open the live captions window and transcribe the `[captions]` source into it
+#[tauri::command]
+async fn start_captions(app: AppHandle) -> Result<(), WhisperiaError> {
+    captions::start(&app)
+}
+
+#[tauri::command]
+async fn stop_captions(app: AppHandle) -> Result<(), WhisperiaError> {
+    captions::stop(&app);
+    Ok(())
+}
+
+/// Collect dictations in a buffer instead of typing each one, see `send_compose`
+#[tauri::command]
+async fn set_compose_mode(enabled: bool, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    info!("Compose mode: {}", enabled);
+    state.set_compose_mode(enabled);
+    Ok(())
+}
+
+/// Open mic: type every utterance the microphone hears until turned off
+#[tauri::command]
+async fn set_open_mic(enabled: bool, app: AppHandle) -> Result<(), WhisperiaError> {
+    info!("Open mic: {}", enabled);
+    if enabled {
+        open_mic::start(&app);
+    } else {
+        open_mic::stop(&app.state::<AppState>());
+    }
+    Ok(())
+}
+
+/// Type the composed message into the window of its first dictation
+#[tauri::command]
+async fn send_compose(app: AppHandle) -> Result<String, WhisperiaError> {
+    send_composed(&app).await
+}
+
+/// Throw the composed message away, the dictations stay in the history
+#[tauri::command]
+async fn discard_compose(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    if state.take_compose().is_some() {
+        state.publish(Event::Compose(Some(String::new())));
+    }
+    Ok(())
+}
+
+async fn send_composed(app: &AppHandle) -> Result<String, WhisperiaError> {
+    let state = app.state::<AppState>().inner().clone();
+    if state.get_status().pipeline.is_busy() {
+        return Err(WhisperiaError::Busy("a dictation is running".to_string()));
+    }
+    let compose = state
+        .take_compose()
+        .ok_or_else(|| WhisperiaError::Invalid("Compose mode is off".to_string()))?;
+    let text = compose.text();
+    state.publish(Event::Compose(Some(String::new())));
+    let _ = hide_overlay_window(app);
+    if text.is_empty() {
+        return Ok(text);
+    }
+    
+    info!("Typing the composed message ({} dictations)", compose.parts.len());
+    let typed = text.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Some(InsertTarget { compositor: Some(compositor), window: Some(window), .. }) = &compose.target {
+            if let Err(e) = compositor.focus_window(window) {
+                warn!("Failed to restore focus: {}", e);
+            }
+        }
+        permissions::ensure_accessibility()?;
+        let mut input = InputSimulator::new().map_err(WhisperiaError::input_blocked)?;
+        input.type_text(&typed).map_err(WhisperiaError::input_blocked)
+    })
+    .await
+    .map_err(|e| WhisperiaError::Internal(e.to_string()))?
+    .map_err(|e| {
+        state.publish(Event::Error(e.clone()));
+        e
+    })?;
+    state.set_result(text.clone());
+    Ok(text)
+}
+
+/// Open the scratch note, dictations go there while it's focused
+#[tauri::command]
+async fn open_scratch_note(app: AppHandle) -> Result<(), WhisperiaError> {
+    scratch::open(&app)
+}
+
+#[tauri::command]
+async fn get_scratch_note(state: State<'_, AppState>) -> Result<String, WhisperiaError> {
+    Ok(state.get_scratch())
+}
+
+/// Keep the user's edits to the note
+#[tauri::command]
+async fn set_scratch_note(note: String, state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    state.set_scratch(note);
+    Ok(())
+}
+
+/// Save the note as Markdown and return where
+#[tauri::command]
+async fn save_scratch_note(state: State<'_, AppState>) -> Result<PathBuf, WhisperiaError> {
+    let note = state.get_scratch();
+    if note.trim().is_empty() {
+        return Err(WhisperiaError::Invalid("The scratch note is empty".to_string()));
+    }
+    Ok(scratch::save(&note)?)
+}
+
+/// Transcribe the voice memos waiting in the inbox now instead of on its schedule
+#[tauri::command]
+async fn process_inbox(state: State<'_, AppState>) -> Result<usize, WhisperiaError> {
+    let (state, config) = (state.inner().clone(), state.get_config());
+    tokio::task::spawn_blocking(move || inbox::process(&state, &config))
+        .await
+        .map_err(|e| WhisperiaError::Internal(e.to_string()))?
+        .map_err(WhisperiaError::from)
+}
+
+/// Record a meeting until `stop_meeting`, transcribing it in the background
+#[tauri::command]
+async fn start_meeting(state: State<'_, AppState>) -> Result<(), WhisperiaError> {
+    meeting::start(&state)
+}
+
+/// Finish the meeting: the transcript goes to the history and SRT/Markdown exports
+#[tauri::command]
+async fn stop_meeting(state: State<'_, AppState>) -> Result<Meeting, WhisperiaError> {
+    meeting::stop(&state).await
+}
+
+/// Mark the current moment of the meeting, highlighted in its transcript
+#[tauri::command]
+async fn add_meeting_bookmark(
+    state: State<'_, AppState>,
+    label: Option<String>,
+) -> Result<meeting::Bookmark, WhisperiaError> {
+    meeting::bookmark(&state, label)
+}
+
+#[tauri::command]
+async fn start_interactive_recording(app: AppHandle) -> Result<String, WhisperiaError> {
+    info!("start_interactive_recording command invoked");
+    
+    let state = app.state::<AppState>();
+    if let Err(current) = state.begin_recording() {
+        return Err(WhisperiaError::Busy(current.name().to_string()));
+    }
+    
+    run_interactive_recording(&app, &state).map_err(|e| {
+        let error = WhisperiaError::from(e);
+        error!("Interactive recording failed: {}", error);
+        state.fail(&error.to_string());
+        let _ = hide_overlay_window(&app);
+        error
+    })
+}
+
+fn run_interactive_recording(app: &AppHandle, state: &AppState) -> anyhow::Result<String> {
+    // Show overlay
+    show_overlay_window(app)?;
+    
+    // Record until Ctrl+C, with media players paused
+    let config = state.get_config();
+    let media_guard = media::pause_for_recording(&config.media);
+    let recorder = audio::AudioRecorder::new().map_err(WhisperiaError::no_mic)?;
+    let recording = recorder.record_until_interrupt().map_err(WhisperiaError::no_mic);
+    drop(media_guard);
+    let audio_data = recording?;
+    
+    state.transition(PipelineState::Transcribing)?;
+    
+    // Transcribe
+    let (model_path, _) = memory::pick_model(&config)?;
+    let transcriber =
+        transcription::Transcriber::cached(&model_path, config.model.cached_models)
+            .map_err(WhisperiaError::decode_failed)?
+            .with_threads(tuning::threads(&model_path, config.model.threads))
+            .with_paragraphs(config.output.paragraph_pause_ms)
+            .with_translate(config.model.translate);
+    let partials = state.clone();
+    let text = transcriber
+        .transcribe_with_callback(&audio_data, &config.language, move |segment| {
+            partials.publish(Event::Transcript(TranscriptEvent::Partial(segment)));
+        })
+        .map_err(WhisperiaError::decode_failed)?;
+    let text = postprocess::process(&text, &config, None);
+    
+    state.transition(PipelineState::Inserting)?;
+    
+    // Type the result
+    let mut input = input::InputSimulator::new().map_err(WhisperiaError::input_blocked)?;
+    input.insert(&text, config.output.insert).map_err(WhisperiaError::input_blocked)?;
+    
+    state.set_result(text.clone());
+    state.transition(PipelineState::Idle)?;
+    
+    // Hide overlay
+    hide_overlay_window(app)?;
+    
+    Ok(text)
+}
+
+fn get_model_path(config: &Config) -> anyhow::Result<PathBuf> {
+    let models_dir = Config::models_dir()?;
+    
+    // A model made for the language beats the general one of the same size
+    if config.model.prefer_language_models {
+        let variant = models::language_variant(&config.model, &config.model.local_model, &config.language);
+        if let Some(path) = variant.and_then(|name| installed_model(&models_dir, &config.model, &name)) {
+            return Ok(path);
+        }
+    }
+    
+    match installed_model(&models_dir, &config.model, &config.model.local_model) {
+        Some(path) => Ok(path),
+        None => anyhow::bail!("Model not found. Run ./download-quantized.sh to download optimized models (40% smaller, same quality)"),
+    }
+}
+
+fn installed_model(models_dir: &Path, model: &config::ModelConfig, name: &str) -> Option<PathBuf> {
+    if let Some(pack) = models::language_pack(model, name) {
+        let path = models_dir.join(models::pack_file(pack));
+        return path.exists().then_some(path);
+    }
+    
+    // Try quantized version first (Q5_0 has best quality/size ratio), then the standard model
+    [true, false]
+        .into_iter()
+        .map(|quantized| models_dir.join(models::whisper_model_file(name, quantized)))
+        .find(|path| path.exists())
+}
+
+fn get_cursor_position() -> (i32, i32) {
+    // xwayland only sees the pointer while it is over an x11 window, ask the compositor
+    #[cfg(target_os = "linux")]
+    if Session::detect() == Session::Wayland {
+        if let Some(compositor) = Compositor::detect() {
+            if let Some(position) = compositor.cursor_position() {
+                return position;
+            }
+            if let Some(output) = compositor.active_output() {
+                return (output.x + output.width / 2, output.y + output.height / 2);
+            }
+        }
+    }
+    
+    #[cfg(target_os = "linux")]
+    if Session::detect() == Session::X11 {
+        use x11rb::connection::Connection;
+        
+        if let Ok((conn, screen_num)) = x11rb::connect(None) {
+            let screen = &conn.setup().roots[screen_num];
+            if let Ok(cookie) = conn.query_pointer(screen.root) {
+                if let Ok(reply) = cookie.reply() {
+                    return (reply.root_x as i32, reply.root_y as i32);
+                }
+            }
+        }
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        use std::mem::zeroed;
+        unsafe {
+            let mut point: windows::Win32::Foundation::POINT = zeroed();
+            if windows::Win32::UI::WindowsAndMessaging::GetCursorPos(&mut point).is_ok() {
+                return (point.x, point.y);
+            }
+        }
+    }
+    
+    #[cfg(target_os = "macos")]
+    {
+        use cocoa::appkit::NSEvent;
+        use cocoa::base::nil;
+        use cocoa::foundation::NSPoint;
+        
+        unsafe {
+            let point: NSPoint = NSEvent::mouseLocation(nil);
+            return (point.x as i32, point.y as i32);
+        }
+    }
+    
+    // Default position if we can't get cursor position
+    (100, 100)
+}
+
+/// Where the overlay should be centered: the focused window when the compositor
+/// can tell us, otherwise the mouse cursor
+fn overlay_anchor(compositor: Option<Compositor>, focused: Option<&FocusedWindow>) -> (i32, i32) {
+    if let Some(window) = focused {
+        return (window.x + window.width / 2, window.y + window.height / 2);
+    }
+    if let Some(output) = compositor.and_then(|c| c.active_output()) {
+        return (output.x + output.width / 2, output.y + output.height / 2);
+    }
+    get_cursor_position()
+}
+
+/// Move the overlay's top-left corner to (x, y). Wayland clients can't position
+/// themselves, so there the compositor moves it, or it stays where layer-shell anchored it.
+fn place_overlay(overlay: &tauri::WebviewWindow, x: i32, y: i32) {
+    #[cfg(target_os = "linux")]
+    if layer_shell::is_anchored() {
+        return;
+    }
+    let _ = overlay.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y }));
+    if let Some(compositor) = Compositor::detect() {
+        if let Err(e) = compositor.move_window("Whisperia Overlay", x, y) {
+            warn!("Failed to place overlay through compositor: {}", e);
+        }
+    }
+}
+
+fn setup_tray(app: &mut tauri::App) -> anyhow::Result<()> {
+    // Create menu items
+    let transcribe_i = MenuItem::with_id(app, "transcribe", t("tray.transcribe"), true, None::<&str>)?;
+    let summarize_i = MenuItem::with_id(app, "summarize", t("tray.summarize"), true, None::<&str>)?;
+    let scratch_i = MenuItem::with_id(app, "scratch", t("tray.scratch"), true, None::<&str>)?;
+    let captions_i = MenuItem::with_id(app, "captions", t("tray.captions"), true, None::<&str>)?;
+    let meeting_i = MenuItem::with_id(app, "meeting", t("tray.meeting"), true, None::<&str>)?;
+    let wake_enabled = app.state::<AppState>().get_config().wake_word.enabled;
+    let wake_i = CheckMenuItem::with_id(app, "wake_word", t("tray.wake_word"), true, wake_enabled, None::<&str>)?;
+    let open_mic_i = CheckMenuItem::with_id(app, "open_mic", t("tray.open_mic"), true, false, None::<&str>)?;
+    let offline = app.state::<AppState>().get_config().offline;
+    let offline_i = CheckMenuItem::with_id(app, "offline", t("tray.offline"), true, offline, None::<&str>)?;
+    let translate = app.state::<AppState>().get_config().model.translate;
+    let translate_i = CheckMenuItem::with_id(app, "translate", t("tray.translate"), true, translate, None::<&str>)?;
+    let compose_i = CheckMenuItem::with_id(app, "compose", t("tray.compose"), true, false, None::<&str>)?;
+    let compose_send_i = MenuItem::with_id(app, "compose_send", t("tray.compose_send"), true, None::<&str>)?;
+    let repro_i = MenuItem::with_id(app, "repro", t("tray.repro"), true, None::<&str>)?;
+    let settings_i = MenuItem::with_id(app, "settings", t("tray.settings"), true, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let quit_i = MenuItem::with_id(app, "quit", t("tray.quit"), true, None::<&str>)?;
+    
+    // Create menu
+    let menu = Menu::with_items(app, &[
+        &transcribe_i,
+        &summarize_i,
+        &compose_i,
+        &compose_send_i,
+        &scratch_i,
+        &captions_i,
+        &meeting_i,
+        &wake_i,
+        &open_mic_i,
+        &offline_i,
+        &translate_i,
+        &repro_i,
+        &settings_i,
+        &separator,
+        &quit_i,
+    ])?;
+    
+    // "Send to" submenu for the configured messaging targets
+    let targets = app.state::<AppState>().get_config().send_targets;
+    if !targets.is_empty() {
+        let send_menu = Submenu::new(app, t("tray.send_to"), true)?;
+        for target in &targets {
+            let item = MenuItem::with_id(
+                app,
+                format!("send:{}", target.name()),
+                target.name(),
+                true,
+                None::<&str>,
+            )?;
+            send_menu.append(&item)?;
+        }
+        menu.insert(&send_menu, 1)?;
+    }
+    
+    // "Format as" submenu for the LLM rewrite intents
+    let intents = app.state::<AppState>().get_config().llm.intents;
+    if !intents.is_empty() {
+        let format_menu = Submenu::new(app, t("tray.format_as"), true)?;
+        let plain_i = MenuItem::with_id(app, "intent:", t("tray.plain_text"), true, None::<&str>)?;
+        format_menu.append(&plain_i)?;
+        for intent in &intents {
+            let item = MenuItem::with_id(
+                app,
+                format!("intent:{}", intent.name),
+                &intent.label,
+                true,
+                None::<&str>,
+            )?;
+            format_menu.append(&item)?;
+        }
+        menu.insert(&format_menu, 1)?;
+    }
+    
+    // Tone presets, applied on top of the intent
+    let tones = app.state::<AppState>().get_config().llm.tones;
+    if !tones.is_empty() {
+        let tone_menu = Submenu::new(app, t("tray.tone"), true)?;
+        let neutral_i = MenuItem::with_id(app, "tone:", t("tray.tone_original"), true, None::<&str>)?;
+        tone_menu.append(&neutral_i)?;
+        for tone in &tones {
+            let item = MenuItem::with_id(
+                app,
+                format!("tone:{}", tone.name),
+                &tone.label,
+                true,
+                None::<&str>,
+            )?;
+            tone_menu.append(&item)?;
+        }
+        menu.insert(&tone_menu, 2)?;
+    }
+    
+    // How dictations get into the focused app, `output.insert`
+    let insert_menu = Submenu::new(app, t("tray.insert_as"), true)?;
+    for (id, label) in [
+        ("insert:type", "tray.insert_type"),
+        ("insert:clipboard", "tray.insert_clipboard"),
+        ("insert:paste", "tray.insert_paste"),
+    ] {
+        insert_menu.append(&MenuItem::with_id(app, id, t(label), true, None::<&str>)?)?;
+    }
+    menu.insert(&insert_menu, 1)?;
+    
+    // Build tray icon with event handler
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip(match tray_cycle::selection(&app.state::<AppState>().get_config()) {
+            Some(selection) => tf("tray.tooltip", &[&selection]),
+            None => "Whisperia".to_string(),
+        })
+        .icon(app.default_window_icon().unwrap().clone())
+        .on_tray_icon_event(|tray, event| {
+            // no wheel events come from the tray, a middle click steps forward instead
+            if let TrayIconEvent::Click { button: MouseButton::Middle, button_state, .. } = event {
+                if matches!(button_state, MouseButtonState::Up) {
+                    if let Err(e) = tray_cycle::cycle(&tray.app_handle().state::<AppState>(), 1) {
+                        warn!("Failed to cycle from the tray: {}", e);
+                    }
+                }
+                return;
+            }
+            if let TrayIconEvent::Click { .. } = event {
+                let app = tray.app_handle();
+                info!("Tray icon clicked - triggering transcription");
+                if let Err(e) = trigger_transcription_flow(app.clone(), None) {
+                    error!("Failed to trigger transcription from tray: {}", e);
+                }
+            }
+        })
+        .build(app)?;
+    
+    Ok(())
+}
+
+/// Record, transcribe and type. `intent` overrides the formatting intent selected in the tray.
+/// While a dictation is running the new one is queued, replaces it or is rejected,
+/// following `queue.busy_policy`.
+fn trigger_transcription_flow(app: AppHandle, intent: Option<String>) -> Result<(), WhisperiaError> {
+    queue_dictation(app, intent, None, None, None)
+}
+
+/// Copy the text selected in the focused app and dictate an instruction for it
+/// ("make it shorter"); the LLM's revision is typed over the selection
+fn trigger_revise_flow(app: AppHandle) -> Result<(), WhisperiaError> {
+    let selection = selection::copy()
+        .map_err(WhisperiaError::input_blocked)?
+        .ok_or_else(|| WhisperiaError::Invalid("nothing is selected to revise".to_string()))?;
+    info!("Revising the selection ({} chars)", selection.len());
+    queue_dictation(app, None, None, Some(selection), None)
+}
+
+/// Start a dictation with a profile's language, model and intent
+fn trigger_profile_flow(app: AppHandle, name: &str) -> Result<(), WhisperiaError> {
+    let profile = app
+        .state::<AppState>()
+        .get_config()
+        .profile(name)
+        .cloned()
+        .ok_or_else(|| WhisperiaError::Invalid(format!("no profile named '{}'", name)))?;
+    info!("Dictating with profile '{}'", profile.name);
+    queue_dictation(app, profile.intent, Some(profile.name), None, None)
+}
+
+fn queue_dictation(
+    app: AppHandle,
+    intent: Option<String>,
+    profile: Option<String>,
+    selection: Option<String>,
+    audio: Option<Vec<f32>>,
+) -> Result<(), WhisperiaError> {
+    info!("Triggering transcription flow");
+    
+    let state = app.state::<AppState>();
+    let queue = state.get_config().queue;
+    
+    // Remember the focused window now, before the overlay steals focus (wayland compositors)
+    let compositor = Compositor::detect();
+    let job = Job {
+        intent: intent.or_else(|| state.get_format_intent()),
+        profile,
+        selection,
+        audio,
+        target: InsertTarget {
+            compositor,
+            window: compositor.and_then(|c| c.focused_window()),
+            scratch: state.get_config().output.target == OutputTarget::Scratch || scratch::is_focused(&app),
+        },
+    };
+    
+    let pending = state.jobs().len();
+    if state.get_status().pipeline.is_busy() || pending > 0 {
+        match queue.busy_policy {
+            BusyPolicy::Queue if pending < queue.max_pending => {
+                info!("Pipeline busy, queueing dictation ({} waiting)", pending);
+            }
+            BusyPolicy::Replace => {
+                let dropped = state.jobs().clear();
+                info!("Pipeline busy, replacing the current dictation ({} queued dropped)", dropped);
+                state.cancel_run();
+            }
+            _ => {
+                let error = WhisperiaError::Busy(format!(
+                    "a dictation is already running ({} waiting)",
+                    pending
+                ));
+                info!("Rejecting dictation: {}", error);
+                state.publish(Event::Error(error.clone()));
+                return Err(error);
+            }
+        }
+    }
+    
+    state.jobs().push(job);
+    Ok(())
+}
+
+/// A triggered dictation waiting for the pipeline
+struct Job {
+    intent: Option<String>,
+    profile: Option<String>,
+    selection: Option<String>, // revised following the dictation instead of typing it
+    audio: Option<Vec<f32>>,   // 16khz audio decoded instead of recording (the demo clip)
+    target: InsertTarget,
+}
+
+/// Run queued dictations one after the other until shutdown
+async fn run_jobs(app: AppHandle) {
+    let state = app.state::<AppState>().inner().clone();
+    let shutdown = state.shutdown_token();
+    loop {
+        let job = tokio::select! {
+            job = state.jobs().next() => job,
+            _ = shutdown.cancelled() => break,
+        };
+        run_job(&app, &state, job).await;
+    }
+    info!("Dictation worker stopped");
+}
+
+async fn run_job(app: &AppHandle, state: &AppState, job: Job) {
+    // Claim the pipeline for this dictation until it is back to idle (or error)
+    let cancel = match state.begin_recording() {
+        Ok(cancel) => cancel,
+        Err(current) => {
+            warn!("Pipeline unexpectedly busy ({}), dropping dictation", current.name());
+            return;
+        }
+    };
+    
+    // The last draft can't be replaced once another dictation is typed after it
+    two_pass::invalidate(state);
+    idle::touch();
+    
+    // Show overlay at cursor position (or over the focused window on wayland)
+    if let Some(overlay) = app.get_webview_window("overlay") {
+        let (x, y) = overlay_anchor(job.target.compositor, job.target.window.as_ref());
+        let _ = overlay.show();
+        let _ = overlay.set_focus();
+        place_overlay(&overlay, x.saturating_sub(200), y.saturating_sub(75));
+    }
+    state.publish(Event::Status(t("status.recording")));
+    
+    // cancelling a finished run is harmless and stops the helper tasks watching it
+    let _done = cancel.clone().drop_guard();
+    
+    // run it as its own task so a panic in a stage ends up as an error below
+    // instead of leaving the pipeline stuck in recording
+    let pipeline = tokio::spawn({
+        let (app, state, cancel) = (app.clone(), state.clone(), cancel.clone());
+        async move { crash::recoverable_task(run_pipeline(&app, &state, job, &cancel)).await }
+    });
+    let result = match pipeline.await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => Err(WhisperiaError::from_panic(e.into_panic()).into()),
+        Err(e) => Err(e).context("Pipeline task failed"),
+    };
+    
+    match result {
+        Ok(()) => {}
+        Err(e) if e.is::<Cancelled>() => {
+            info!("Dictation cancelled");
+            state.finish_repro(None, "cancelled");
+            let _ = state.transition(PipelineState::Idle);
+            state.publish(Event::Status(t("status.cancelled")));
+            let _ = hide_overlay_window(app);
+        }
+        Err(e) => {
+            let error = WhisperiaError::from(e);
+            error!("Transcription pipeline failed ({}): {}", error.code(), error);
+            state.finish_repro(None, &format!("{}: {}", error.code(), error));
+            state.fail(&error.to_string());
+            state.publish(Event::Error(error));
+            let _ = hide_overlay_window(app);
+        }
+    }
+    
+    // Listeners stopped while idle come back once the microphone is free again
+    idle::resume(app);
+}
+
+/// The window the dictation is typed into, when the compositor could tell us.
+/// `scratch` sends it to the scratch note instead.
+#[derive(Clone)]
+struct InsertTarget {
+    compositor: Option<Compositor>,
+    window: Option<FocusedWindow>,
+    scratch: bool,
+}
+
+/// A message being put together from several dictations
+#[derive(Default)]
+struct Compose {
+    parts: Vec<String>,
+    target: Option<InsertTarget>,
+}
+
+impl Compose {
+    fn text(&self) -> String {
+        self.parts.join(" ")
+    }
+}
+
+/// Run blocking work (audio, whisper, input) off the async runtime, giving up
+/// on it as soon as the dictation is cancelled. A thread can't be killed, so
+/// the work goes on until it looks at the token it is handed: between its
+/// steps, and whisper and the recording while they run.
+async fn run_blocking<T, F>(cancel: &CancellationToken, work: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(CancellationToken) -> anyhow::Result<T> + Send + 'static,
+{
+    let token = cancel.clone();
+    let task = tokio::task::spawn_blocking(move || crash::recoverable(|| work(token)));
+    tokio::select! {
+        result = task => match result {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => Err(WhisperiaError::from_panic(e.into_panic()).into()),
+            Err(e) => Err(e).context("Pipeline task failed"),
+        },
+        _ = cancel.cancelled() => Err(Cancelled.into()),
+    }
+}
+
+/// `run_blocking` for one pipeline stage: past `limit` the run is cancelled, so the
+/// stage stops as soon as it can, and the dictation fails with a timeout error
+async fn run_stage<T, F>(
+    cancel: &CancellationToken,
+    stage: &str,
+    limit: Duration,
+    work: F,
+) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(CancellationToken) -> anyhow::Result<T> + Send + 'static,
+{
+    // a timeout cancels the token, the work is left to stop on its own
+    match tokio::time::timeout(limit, run_blocking(cancel, work)).await {
+        Ok(result) => result,
+        Err(_) => {
+            warn!("Pipeline stage '{}' timed out after {:?}", stage, limit);
+            cancel.cancel();
+            Err(WhisperiaError::Timeout(format!("{} took longer than {:?}", stage, limit)).into())
+        }
+    }
+}
+
+/// Record (a fixed few seconds, or on a retry until the speech ends) and decode
+/// it; `recorded` audio is decoded as it is
+async fn record_and_decode(
+    state: &AppState,
+    config: &Config,
+    cancel: &CancellationToken,
+    run: &mut RunReport,
+    retry: bool,
+    recorded: Option<&[f32]>,
+) -> anyhow::Result<(Transcript, Option<Vec<f32>>)> {
+    let timeouts = &config.timeouts;
+    
+    // Pause media players so they don't end up in the recording
+    let media_guard = media::pause_for_recording(&config.media);
+    
+    // Record for a few seconds (until stopped in toggle mode), stopping early on
+    // cancel or `stop_recording`; stopping the recording doesn't cancel the run
+    let stop = cancel.child_token();
+    // (the webview records for as long as it's told, it can't be stopped)
+    let seconds = match config.capture.mode {
+        _ if retry => config.retry.max_secs,
+        config::RecordingMode::Toggle if config.capture.source == config::CaptureSource::Device => {
+            config.capture.max_secs
+        }
+        _ => MAX_RECORDING_SECS,
+    };
+    let limit = Duration::from_secs(seconds) + timeouts.recording();
+    let recording = async {
+        if let Some(samples) = recorded {
+            run.audio = report::AudioReport {
+                device: "recorded".to_string(),
+                sample_rate: 16000,
+                channels: 1,
+                sample_format: "F32".to_string(),
+                resample: report::resample_path(16000),
+                ..Default::default()
+            };
+            return Ok((samples.to_vec(), None, Duration::ZERO, Duration::ZERO));
+        }
+        
+        // The overlay records with getUserMedia, and asks for the microphone itself
+        if config.capture.source == config::CaptureSource::Webview {
+            let started = Instant::now();
+            let (samples, from) = webview_audio::record(state, seconds, limit, cancel).await?;
+            let capture = started.elapsed();
+            run.audio = report::AudioReport {
+                device: "webview".to_string(),
+                sample_rate: from,
+                channels: 1,
+                sample_format: "F32".to_string(),
+                resample: report::resample_path(from),
+                ..Default::default()
+            };
+            let started = Instant::now();
+            let samples = AudioRecorder::resample(&samples, from, 16000);
+            return Ok((samples, None, capture, started.elapsed()));
+        }
+        
+        // not timed, the first time this waits for the user to answer the macOS prompt
+        run_blocking(cancel, |_| Ok(permissions::ensure_microphone()?)).await?;
+        let media = config.media.clone();
+        run_blocking(cancel, move |_| Ok(media::ensure_unmuted(&media)?)).await?;
+        let monitor = config.monitor.clone();
+        let recorder = run_stage(cancel, "opening the microphone", timeouts.device_open(), move |_| {
+            Ok(AudioRecorder::new().map_err(WhisperiaError::no_mic)?.with_monitor(&monitor))
+        })
+        .await?;
+        state.set_recording(Some(stop.clone()));
+        let from = recorder.sample_rate();
+        run.audio = report::AudioReport {
+            device: recorder.device_name(),
+            sample_rate: from,
+            channels: recorder.channels(),
+            sample_format: recorder.sample_format(),
+            resample: report::resample_path(from),
+            ..Default::default()
+        };
+        // the open mic's idea of speech, but one utterance of up to `seconds`
+        let vad = retry.then(|| {
+            let max_utterance_ms = seconds * 1000;
+            vad::Vad::new(&config::OpenMicConfig { max_utterance_ms, ..config.open_mic.clone() })
+        });
+        // `stop` is a child of the token, it ends the recording on a cancel too
+        run_stage(cancel, "recording", limit, move |_| {
+            let started = Instant::now();
+            let (samples, ended_by) = match vad {
+                Some(vad) => capture_utterance(&recorder, vad, seconds, &stop).map(|(s, e)| (s, Some(e))),
+                None => recorder.capture_until_stopped(seconds, &stop).map(|s| (s, None)),
+            }
+            .map_err(WhisperiaError::no_mic)?;
+            let capture = started.elapsed();
+            
+            let started = Instant::now();
+            let samples = AudioRecorder::resample(&samples, recorder.sample_rate(), 16000);
+            Ok((samples, ended_by, capture, started.elapsed()))
+        })
+        .await
+    }
+    .await;
+    state.set_recording(None);
+    drop(media_guard);
+    let (audio_data, ended_by, capture, resample) = recording?;
+    if let Some(ended_by) = ended_by {
+        run.vad.push(report::VadReport {
+            threshold: config.open_mic.threshold,
+            silence_ms: config.open_mic.silence_ms,
+            min_speech_ms: config.open_mic.min_speech_ms,
+            ended_by: ended_by.to_string(),
+        });
+    }
+    run.audio.samples = audio_data.len();
+    run.audio.peak = audio_data.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    run.metrics.capture_ms = metrics::millis(capture);
+    run.metrics.resample_ms = metrics::millis(resample);
+    run.metrics.set_audio(audio_data.len(), 16000);
+    state.with_repro(|repro| repro.audio(&audio_data));
+    info!("Audio recorded successfully: {} samples", audio_data.len());
+    
+    state.transition(PipelineState::Transcribing)?;
+    state.publish(Event::Status(t("status.transcribing")));
+    
+    // Decode on the shared service when there is one, here otherwise
+    if config.service.url.is_some() {
+        let started = Instant::now();
+        let prompt = hotwords::prompt(&config.model.hotwords);
+        let remote = tokio::select! {
+            transcript = service::transcribe(
+                &config.service,
+                &audio_data,
+                &config.language,
+                prompt.as_deref(),
+                config.model.translate,
+                timeouts.decode(),
+            ) => transcript,
+            _ = cancel.cancelled() => return Err(Cancelled.into()),
+        };
+        match remote {
+            Ok(transcript) => {
+                run.metrics.model = "service".to_string();
+                run.metrics.set_decode(started.elapsed());
+                run.decode = report::DecodeReport {
+                    decoder: "service".to_string(),
+                    language: config.language.clone(),
+                    prompt_chars: prompt.as_ref().map_or(0, |p| p.chars().count()),
+                    ..Default::default()
+                };
+                Ok((transcript, None))
+            }
+            Err(e) if config.service.fallback_local => {
+                warn!("Transcription service failed, decoding locally: {:#}", e);
+                Ok((transcribe_locally(state, config, audio_data, cancel, run).await?, None))
+            }
+            Err(e) => Err(WhisperiaError::decode_failed(e).into()),
+        }
+    } else if config.model.model_type == "api" {
+        // or on the `[api]` provider
+        let started = Instant::now();
+        let prompt = hotwords::prompt(&config.model.hotwords);
+        let prompt_chars = prompt.as_ref().map_or(0, |p| p.chars().count());
+        let transcriber = ApiTranscriber::new(&config.api)
+            .map_err(WhisperiaError::decode_failed)?
+            .with_prompt(prompt)
+            .with_translate(config.model.translate);
+        let transcript = tokio::select! {
+            transcript = transcriber.transcribe(&audio_data, &config.language, timeouts.decode()) => {
+                transcript.map_err(WhisperiaError::decode_failed)?
+            }
+            _ = cancel.cancelled() => return Err(Cancelled.into()),
+        };
+        run.metrics.model = transcriber.name();
+        run.metrics.set_decode(started.elapsed());
+        run.decode = report::DecodeReport {
+            decoder: "api".to_string(),
+            language: config.language.clone(),
+            prompt_chars,
+            ..Default::default()
+        };
+        warn_about_budget(state, config);
+        Ok((transcript, None))
+    } else if let Some(draft) = two_pass::draft_config(config) {
+        // A quick draft now, the audio is kept for the configured model
+        run.metrics.model = draft.model.local_model.clone();
+        let transcript = transcribe_locally(state, &draft, audio_data.clone(), cancel, run).await?;
+        run.decode.decoder = "draft".to_string();
+        Ok((transcript, Some(audio_data)))
+    } else {
+        Ok((transcribe_locally(state, config, audio_data, cancel, run).await?, None))
+    }
+}
+
+/// Record until the first utterance ends, or `max_seconds` without one, with
+/// what ended the recording: speech, time_limit or stopped
+fn capture_utterance(
+    recorder: &AudioRecorder,
+    mut vad: vad::Vad,
+    max_seconds: u64,
+    stop: &CancellationToken,
+) -> anyhow::Result<(Vec<f32>, &'static str)> {
+    let rate = recorder.sample_rate();
+    let deadline = Instant::now() + Duration::from_secs(max_seconds);
+    let done = CancellationToken::new();
+    let mut recorded = Vec::new();
+    let mut ended_by = "time_limit";
+    recorder.stream_until_stopped(&done, Duration::from_millis(100), |samples| {
+        let ended = !vad.push(&AudioRecorder::resample(&samples, rate, 16000)).is_empty();
+        recorded.extend(samples);
+        if ended {
+            ended_by = "speech";
+        } else if stop.is_cancelled() {
+            ended_by = "stopped";
+        }
+        if ended || Instant::now() >= deadline || stop.is_cancelled() {
+            done.cancel();
+        }
+    })?;
+    Ok((recorded, ended_by))
 }
 
-#[tauri::command]
-async fn start_interactive_recording(app: AppHandle) -> Result<String, String> {
-    info!("start_interactive_recording command invoked");
+/// Whisper's answer for silence: nothing, or only markers like "[BLANK_AUDIO]" and "(silence)"
+fn heard_nothing(text: &str) -> bool {
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = (depth - 1).max(0),
+            _ if depth == 0 && c.is_alphanumeric() => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+async fn run_pipeline(
+    app: &AppHandle,
+    state: &AppState,
+    job: Job,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    let Job { intent, profile, selection, audio, target } = job;
+    info!("Starting audio recording task");
+    let mut config = state.get_config();
     
-    // Show overlay
-    show_overlay_window(&app).map_err(|e| e.to_string())?;
+    // Per-app language rules override the global language for this dictation
+    if let Some(window) = &target.window {
+        let language = config.language_for(&window.app_id, &window.title).to_string();
+        if language != config.language {
+            info!("Dictating in '{}' for {} ({})", language, window.app_id, window.title);
+            config.language = language;
+        }
+    }
+    // and the profile picked with the hotkey overrides both
+    if let Some(profile) = profile.as_deref().and_then(|name| config.profile(name)).cloned() {
+        profile.apply(&mut config);
+    }
     
-    let state = app.state::<AppState>();
-    state.set_recording(true);
+    // Give the overlay a moment to pick another model or language for just this one
+    if config.ui.quick_pick_ms > 0 {
+        quick_pick::offer(state, &mut config, cancel).await?;
+    }
+    let mut run = RunReport::new(&config.model.local_model);
+    let app_id = target.window.as_ref().map(|w| w.app_id.as_str());
+    state.with_repro(|repro| repro.start(&config, app_id));
     
-    // Record until Ctrl+C
-    let recorder = audio::AudioRecorder::new().map_err(|e| e.to_string())?;
-    let audio_data = recorder.record_until_interrupt().map_err(|e| e.to_string())?;
+    // Nothing heard the first time: record once more until the user stops talking
+    let mut retried = false;
+    let (decoded, draft_audio) = loop {
+        let (decoded, draft_audio) =
+            record_and_decode(state, &config, cancel, &mut run, retried, audio.as_deref()).await?;
+        // recorded audio would only decode to nothing again
+        if retried || audio.is_some() || !config.retry.on_empty || !heard_nothing(&decoded.text) {
+            break (decoded, draft_audio);
+        }
+        info!("Nothing was heard, recording again for up to {}s", config.retry.max_secs);
+        retried = true;
+        run.retried = true;
+        state.transition(PipelineState::Idle)?;
+        state.transition(PipelineState::Recording)?;
+        state.publish(Event::Retrying);
+    };
+    let Transcript { text, words, confidence } = decoded;
+    run.decode.confidence = confidence;
     
-    state.set_recording(false);
-    state.set_transcribing(true);
+    info!("Transcription complete: '{}'", text);
+    state.with_repro(|repro| repro.stage("decode", &text));
     
-    // Get config and transcribe
-    let config = state.get_config();
-    let model_path = get_model_path(&config).map_err(|e| e.to_string())?;
+    let transcript = text.clone();
     
-    let transcriber = transcription::Transcriber::new(&model_path).map_err(|e| e.to_string())?;
-    let text = transcriber.transcribe(&audio_data, &config.language).map_err(|e| e.to_string())?;
+    // Words the user taught us to spell their way
+    let text = if config.glossary.enabled {
+        let text = match Glossary::load() {
+            Ok(glossary) => glossary.apply(&text),
+            Err(e) => {
+                warn!("Failed to load the glossary: {:#}", e);
+                text
+            }
+        };
+        state.with_repro(|repro| repro.stage("glossary", &text));
+        text
+    } else {
+        text
+    };
+    // and near misses of the hotwords whisper was told to expect
+    let text = hotwords::apply(&text, &config.model.hotwords);
+    state.with_repro(|repro| repro.stage("hotwords", &text));
     
-    state.set_transcribing(false);
+    // When revising a selection the dictation is the instruction for it, typed over it as is
+    let (text, spelled) = if let Some(selected) = &selection {
+        state.publish(Event::Status(t("status.revising")));
+        let revision = tokio::select! {
+            result = llm::revise(&config.llm, selected, &text) => result,
+            _ = cancel.cancelled() => return Err(Cancelled.into()),
+        };
+        let revision = revision.context("Failed to revise the selection")?;
+        state.with_repro(|repro| repro.stage("revise", &revision));
+        (revision, true)
+    } else {
+        // Spoken prefix commands ("note to self...", "search for..."), spelled text is typed as is.
+        // Chained parts ("... next ...") run one after the other, the ones to type are typed together.
+        let mut typed = Vec::new();
+        let mut spelled = false;
+        for part in routing::split_chain(&text, &config.routing) {
+            match routing::parse(part, &config.routing) {
+                routing::Route::Type(text) if config.routing.spoken_symbols => {
+                    typed.push(symbols::replace(&text, &symbols::load()))
+                }
+                routing::Route::Type(text) => typed.push(text),
+                routing::Route::Spell(text) => {
+                    typed.push(text);
+                    spelled = true;
+                }
+                routing::Route::Translate { language, text } => {
+                    let translation = tokio::select! {
+                        result = llm::translate(&config.llm, &text, &language) => result,
+                        _ = cancel.cancelled() => return Err(Cancelled.into()),
+                    };
+                    match translation {
+                        Ok(translated) => typed.push(translated),
+                        Err(e) => {
+                            warn!("Failed to translate, typing original text: {}", e);
+                            typed.push(text);
+                        }
+                    }
+                }
+                routing::Route::Note(note) => {
+                    info!("Saving dictation as a note");
+                    state.add_history(HistoryEntry::new(&note, &config.language, "note"));
+                }
+                routing::Route::Search(query) => {
+                    let url = routing::search_url(&config.routing, &query);
+                    if let Err(e) = routing::open_url(&url) {
+                        warn!("Failed to open search: {}", e);
+                    }
+                }
+            }
+        }
+        state.with_repro(|repro| repro.stage("routing", &typed.join(" ")));
+        if typed.is_empty() {
+            state.record_run(run, "routed");
+            return finish_without_typing(app, state, &transcript).await;
+        }
+        (typed.join(" "), spelled)
+    };
     
-    // Type the result
-    let mut input = input::InputSimulator::new().map_err(|e| e.to_string())?;
-    input.type_text(&text).map_err(|e| e.to_string())?;
+    // Rewrite/translate with the LLM when an intent, tone or target language is set
+    let intent = intent.as_deref().and_then(|name| config.llm.intent(name));
+    let tone = state.get_tone();
+    let tone = tone.as_deref().and_then(|name| config.llm.tone(name));
+    let rewrite = !spelled && (intent.is_some() || tone.is_some() || config.llm.translate_to.is_some());
+    // only a draft typed as dictated can be refined with the same steps
+    let draft_audio = draft_audio.filter(|_| !spelled && !rewrite);
+    let text = if rewrite {
+        state.publish(Event::Status(t("status.formatting")));
+        let text = tokio::select! {
+            text = llm::process(&config.llm, intent, tone, &text) => text,
+            _ = cancel.cancelled() => return Err(Cancelled.into()),
+        };
+        state.with_repro(|repro| repro.stage("llm", &text));
+        text
+    } else {
+        text
+    };
     
-    // Hide overlay
-    hide_overlay_window(&app).map_err(|e| e.to_string())?;
+    warn_about_budget(state, &config);
     
-    Ok(text)
-}
-
-fn get_model_path(config: &Config) -> anyhow::Result<PathBuf> {
-    let models_dir = Config::models_dir()?;
+    let text = postprocess::process(&text, &config, app_id);
+    state.with_repro(|repro| repro.stage("postprocess", &text));
+    // for the history, `record_run` takes the run
+    let measured = run.metrics.clone();
     
-    // Try quantized version first (Q5_0 has best quality/size ratio)
-    let quantized_file = models_dir.join(format!("ggml-{}-q5_0.bin", config.model.local_model));
-    if quantized_file.exists() {
-        return Ok(quantized_file);
+    // Compose mode: keep it with the previous dictations, the overlay shows them all
+    if let Some(buffer) = state.add_to_compose(&text, &target) {
+        info!("Dictation added to the compose buffer");
+        state.record_run(run, "compose");
+        state.set_result(text.clone());
+        state.transition(PipelineState::Idle)?;
+        state.add_history(
+            HistoryEntry::new(&text, &config.language, "compose")
+                .with_original(&transcript)
+                .with_confidence(confidence)
+                .with_metrics(&measured),
+        );
+        state.publish(Event::Compose(Some(buffer)));
+        return Ok(());
     }
     
-    // Fallback to standard model
-    let model_file = models_dir.join(format!("ggml-{}.bin", config.model.local_model));
-    if model_file.exists() {
-        Ok(model_file)
-    } else {
-        anyhow::bail!("Model not found. Run ./download-quantized.sh to download optimized models (40% smaller, same quality)")
+    if target.scratch {
+        info!("Adding dictation to the scratch note");
+        scratch::append(app, &text)?;
+        state.record_run(run, "scratch");
+        state.set_result(text.clone());
+        state.transition(PipelineState::Idle)?;
+        state.add_history(
+            HistoryEntry::new(&text, &config.language, "scratch")
+                .with_original(&transcript)
+                .with_confidence(confidence)
+                .with_metrics(&measured),
+        );
+        state.publish(Event::Transcript(TranscriptEvent::Final {
+            text,
+            language: config.language.clone(),
+            words,
+            confidence,
+        }));
+        let _ = hide_overlay_window(app);
+        return Ok(());
     }
-}
-
-fn get_cursor_position() -> (i32, i32) {
-    #[cfg(target_os = "linux")]
-    {
-        use x11rb::connection::Connection;
-        
-        if let Ok((conn, screen_num)) = x11rb::connect(None) {
-            let screen = &conn.setup().roots[screen_num];
-            if let Ok(cookie) = conn.query_pointer(screen.root) {
-                if let Ok(reply) = cookie.reply() {
-                    return (reply.root_x as i32, reply.root_y as i32);
-                }
+    
+    // Collecting fragments on the clipboard to paste them all at once
+    if config.output.target == OutputTarget::ClipboardAppend {
+        info!("Appending dictation to the clipboard");
+        let (appended, separator) = (text.clone(), config.output.clipboard_separator.clone());
+        run_blocking(cancel, move |token| {
+            if token.is_cancelled() {
+                return Err(Cancelled.into());
             }
-        }
+            clipboard::append(&appended, &separator).map_err(|e| WhisperiaError::input_blocked(e).into())
+        })
+        .await?;
+        state.record_run(run, "clipboard");
+        state.set_result(text.clone());
+        state.transition(PipelineState::Idle)?;
+        state.add_history(
+            HistoryEntry::new(&text, &config.language, "clipboard")
+                .with_original(&transcript)
+                .with_confidence(confidence)
+                .with_metrics(&measured),
+        );
+        state.publish(Event::Transcript(TranscriptEvent::Final {
+            text,
+            language: config.language.clone(),
+            words,
+            confidence,
+        }));
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        let _ = hide_overlay_window(app);
+        return Ok(());
     }
     
-    #[cfg(target_os = "windows")]
-    {
-        use std::mem::zeroed;
-        unsafe {
-            let mut point: windows::Win32::Foundation::POINT = zeroed();
-            if windows::Win32::UI::WindowsAndMessaging::GetCursorPos(&mut point).is_ok() {
-                return (point.x, point.y);
-            }
+    state.transition(PipelineState::Inserting)?;
+    
+    // Never type into a password field, and keep a dictated secret out of the history and events
+    if config.output.password_guard {
+        let window = target.window.clone();
+        let guarded = run_blocking(cancel, move |_| Ok(password_guard::focused_is_password(window.as_ref()))).await?;
+        if guarded {
+            info!("Focused control is a password field, copying the dictation instead of typing it");
+            clipboard::set(&text).map_err(WhisperiaError::input_blocked)?;
+            state.record_run(run, "password_guard");
+            state.transition(PipelineState::Idle)?;
+            state.publish(Event::Error(WhisperiaError::InputBlocked(
+                "the focused field takes a password, the text was copied to the clipboard instead".to_string(),
+            )));
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+            let _ = hide_overlay_window(app);
+            return Ok(());
         }
     }
     
-    #[cfg(target_os = "macos")]
-    {
-        use cocoa::appkit::NSEvent;
-        use cocoa::base::nil;
-        use cocoa::foundation::NSPoint;
-        
-        unsafe {
-            let point: NSPoint = NSEvent::mouseLocation(nil);
-            return (point.x as i32, point.y as i32);
+    // Give focus back to the window we're typing into, then type (or paste) the result
+    let insert = config.output.insert;
+    // a draft that was only copied has nothing on screen to replace
+    let refine_target = draft_audio.as_ref().filter(|_| insert != InsertMode::Clipboard).map(|_| target.clone());
+    let typed = text.clone();
+    let typing = run_stage(cancel, "typing", config.timeouts.typing(), move |token| {
+        if let (Some(compositor), Some(window)) = (target.compositor, &target.window) {
+            if let Err(e) = compositor.focus_window(window) {
+                warn!("Failed to restore focus: {}", e);
+            }
         }
-    }
+        // the text stays in history when it can't be typed
+        if let Err(e) = permissions::ensure_accessibility() {
+            return Ok((Err(e), Duration::ZERO));
+        }
+        let mut input = InputSimulator::new().map_err(WhisperiaError::input_blocked)?;
+        // the blocking task outlives the run, a cancel while focusing must still stop it here
+        if token.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        let started = Instant::now();
+        let typed = input.insert(&typed, insert).map_err(WhisperiaError::input_blocked);
+        Ok((typed, started.elapsed()))
+    })
+    .await?;
+    let inserted = match typing {
+        (Ok(()), elapsed) => {
+            run.metrics.set_typing(&text, elapsed);
+            true
+        }
+        (Err(e), _) => {
+            warn!("Failed to type text: {}", e);
+            state.publish(Event::Error(e));
+            false
+        }
+    };
+    let outcome = match insert {
+        _ if !inserted => "typing_failed",
+        InsertMode::Type => "typed",
+        InsertMode::Clipboard => "copied",
+        InsertMode::Paste => "pasted",
+    };
+    state.record_run(run, outcome);
     
-    // Default position if we can't get cursor position
-    (100, 100)
-}
-
-fn setup_tray(app: &mut tauri::App) -> anyhow::Result<()> {
-    // Create menu items
-    let transcribe_i = MenuItem::with_id(app, "transcribe", "Transcrever", true, None::<&str>)?;
-    let settings_i = MenuItem::with_id(app, "settings", "Configurações", true, None::<&str>)?;
-    let separator = PredefinedMenuItem::separator(app)?;
-    let quit_i = MenuItem::with_id(app, "quit", "Sair", true, None::<&str>)?;
+    // Update state
+    state.set_result(text.clone());
+    state.transition(PipelineState::Idle)?;
+    state.add_history(
+        HistoryEntry::new(&text, &config.language, "hotkey")
+            .with_original(&transcript)
+            .with_confidence(confidence)
+            .with_metrics(&measured),
+    );
+    let draft = text.clone();
+    state.publish(Event::Transcript(TranscriptEvent::Final {
+        text,
+        language: config.language.clone(),
+        words,
+        confidence,
+    }));
     
-    // Create menu
-    let menu = Menu::with_items(app, &[&transcribe_i, &settings_i, &separator, &quit_i])?;
+    // Hide overlay after a delay
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let _ = hide_overlay_window(app);
     
-    // Build tray icon with event handler
-    let _tray = TrayIconBuilder::new()
-        .menu(&menu)
-        .tooltip("Whisperia")
-        .icon(app.default_window_icon().unwrap().clone())
-        .on_tray_icon_event(|tray, event| {
-            if let TrayIconEvent::Click { .. } = event {
-                let app = tray.app_handle();
-                info!("Tray icon clicked - triggering transcription");
-                if let Err(e) = trigger_transcription_flow(app.clone()) {
-                    error!("Failed to trigger transcription from tray: {}", e);
-                }
-            }
-        })
-        .build(app)?;
+    // Two-pass: the configured model decodes the audio again and may replace the draft
+    if let (Some(audio), Some(target), true) = (draft_audio, refine_target, inserted) {
+        two_pass::refine(app.clone(), config, audio, draft, target);
+    }
     
     Ok(())
 }
 
-fn setup_hotkeys(app: &mut tauri::App) -> anyhow::Result<()> {
-    use global_hotkey::hotkey::{Code, HotKey, Modifiers};
-    
-    let manager = GlobalHotKeyManager::new()?;
-    
-    // Register Super+Shift+T (Cmd+Shift+T on macOS, Win+Shift+T on Windows/Linux)
-    let hotkey = HotKey::new(Some(Modifiers::SUPER | Modifiers::SHIFT), Code::KeyT);
-    manager.register(hotkey)?;
-    
-    // Store manager in app state
-    let state = app.state::<AppState>();
-    state.set_hotkey_manager(manager);
+/// Load the model (or reuse it) and decode in this process
+async fn transcribe_locally(
+    state: &AppState,
+    config: &Config,
+    audio_data: Vec<f32>,
+    cancel: &CancellationToken,
+    run: &mut RunReport,
+) -> anyhow::Result<Transcript> {
+    let timeouts = &config.timeouts;
+    let (model_path, fallback) = memory::pick_model(config)?;
+    if let Some(model) = &fallback {
+        run.metrics.model = model.clone();
+        state.publish(Event::Status(tf("status.low_memory", &[model])));
+    }
+    let language = config.language.clone();
+    let cached_models = config.model.cached_models;
+    let threads = tuning::threads(&model_path, config.model.threads);
+    let pause_ms = config.output.paragraph_pause_ms;
+    let prompt = hotwords::prompt(&config.model.hotwords);
+    let rules = config.model.decode.clone();
+    let translate = config.model.translate;
+    run.metrics.backend = transcription::backend().name().to_string();
+    let partials = state.clone();
+    let tuned = model_path.clone();
+    // Loading takes a while after the idle release (or on the first dictation)
+    let cold = !Transcriber::is_loaded(&model_path);
+    if cold {
+        state.publish(Event::Status(t("status.warming_up")));
+    }
+    run.decode = report::DecodeReport {
+        decoder: "local".to_string(),
+        language: language.clone(),
+        model_path: Some(model_path.display().to_string()),
+        low_memory_fallback: fallback,
+        cold_start: cold,
+        sampling: transcription::SAMPLING.to_string(),
+        prompt_chars: prompt.as_ref().map_or(0, |p| p.chars().count()),
+        paragraph_pause_ms: pause_ms,
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let transcriber = run_stage(cancel, "loading the model", timeouts.model_load(), move |_| {
+        info!("Loading transcriber with model: {:?}", model_path);
+        let transcriber = Transcriber::cached(&model_path, cached_models).map_err(WhisperiaError::decode_failed)?;
+        Ok(transcriber
+            .with_threads(threads)
+            .with_paragraphs(pause_ms)
+            .with_prompt(prompt)
+            .with_rules(&rules)
+            .with_translate(translate))
+    })
+    .await?;
+    run.metrics.model_load_ms = metrics::millis(started.elapsed());
+    run.decode.threads = transcriber.threads();
+    run.decode.suppressed_tokens = transcriber.suppressed();
+    if cold {
+        state.publish(Event::Status(t("status.transcribing")));
+    }
     
-    info!("Global hotkey Super+Shift+T registered successfully");
+    let started = Instant::now();
+    let transcript = run_stage(cancel, "transcription", timeouts.decode(), move |token| {
+        info!("Starting transcription with language: {}", language);
+        Ok(transcriber
+            .transcribe_scored(&audio_data, &language, &token, move |segment| {
+                partials.publish(Event::Transcript(TranscriptEvent::Partial(segment)));
+            })
+            .map_err(WhisperiaError::decode_failed)?)
+    })
+    .await?;
+    run.metrics.set_decode(started.elapsed());
     
-    Ok(())
+    // First dictation with this model: find how many threads suit it, for the next ones
+    tuning::tune_in_background(&tuned, config);
+    Ok(transcript)
 }
 
-fn trigger_transcription_flow(app: AppHandle) -> anyhow::Result<()> {
-    info!("Triggering transcription flow");
-    
-    let state = app.state::<AppState>();
-    
-    // Check if already recording
-    if state.get_status().is_recording || state.get_status().is_transcribing {
-        info!("Already recording or transcribing, skipping");
-        return Ok(());
+/// Load the model the next dictation would decode with into the cache,
+/// returning its file
+fn load_model(config: &Config) -> anyhow::Result<PathBuf> {
+    let (model_path, _) = memory::pick_model(config)?;
+    if !Transcriber::is_loaded(&model_path) {
+        let started = Instant::now();
+        Transcriber::cached(&model_path, config.model.cached_models.max(1))?;
+        info!("Preloaded {:?} in {:?}", model_path, started.elapsed());
     }
-    
-    // Show overlay at cursor position
-    if let Some(overlay) = app.get_webview_window("overlay") {
-        let (x, y) = get_cursor_position();
-        let _ = overlay.set_position(tauri::Position::Physical(tauri::PhysicalPosition { 
-            x: x.saturating_sub(200),
-            y: y.saturating_sub(75),
-        }));
-        let _ = overlay.show();
-        let _ = overlay.set_focus();
-        let _ = overlay.emit("status-update", "Recording...");
+    Ok(model_path)
+}
+
+/// `load_model` on its own thread, at startup and when the model changes;
+/// nothing to load with the shared service, an api or the cache off
+fn preload_in_background(config: Config) {
+    let remote = config.service.url.is_some() || config.model.model_type == "api";
+    if !config.model.preload || config.model.cached_models == 0 || remote {
+        return;
     }
-    
-    // Start recording
-    state.set_recording(true);
-    
-    // Clone for thread
-    let app_clone = app.clone();
-    let state_clone = Arc::new(state.inner().clone());
-    
-    // Spawn recording thread
-    thread::spawn(move || {
-        info!("Starting audio recording thread");
-        
-        // Record audio
-        let recorder = match AudioRecorder::new() {
-            Ok(r) => r,
-            Err(e) => {
-                error!("Failed to create audio recorder: {}", e);
-                let _ = app_clone.emit("status-update", format!("Error: {}", e));
-                let _ = app_clone.emit("transcription-error", format!("Audio recorder error: {}", e));
-                let _ = hide_overlay_window(&app_clone);
-                state_clone.set_recording(false);
-                return;
-            }
-        };
-        
-        // Record for 5 seconds
-        match recorder.record_for_seconds(5) {
-            Ok(data) => {
-                info!("Audio recorded successfully: {} samples", data.len());
-                state_clone.store_audio(data);
-                let _ = app_clone.emit("status-update", "Transcribing...");
-                if let Some(overlay) = app_clone.get_webview_window("overlay") {
-                    let _ = overlay.emit("status-update", "Transcribing...");
-                }
-                
-                // Process transcription
-                let state = app_clone.state::<AppState>();
-                state.set_recording(false);
-                state.set_transcribing(true);
-                
-                // Get audio data
-                let audio_data = match state.take_audio() {
-                    Some(data) => data,
-                    None => {
-                        error!("No audio data available after recording");
-                        let _ = app_clone.emit("status-update", "Error: No audio data");
-                        let _ = app_clone.emit("transcription-error", "No audio data available");
-                        let _ = hide_overlay_window(&app_clone);
-                        state.set_transcribing(false);
-                        return;
-                    }
-                };
-                
-                // Get model path
-                let config = state.get_config();
-                let model_path = match get_model_path(&config) {
-                    Ok(path) => path,
-                    Err(e) => {
-                        error!("Model not found: {}", e);
-                        let _ = app_clone.emit("status-update", format!("Error: {}", e));
-                        let _ = app_clone.emit("transcription-error", format!("Model error: {}", e));
-                        let _ = hide_overlay_window(&app_clone);
-                        state.set_transcribing(false);
-                        return;
-                    }
-                };
-                
-                info!("Loading transcriber with model: {:?}", model_path);
-                
-                // Transcribe
-                let transcriber = match Transcriber::new(&model_path) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        error!("Failed to load transcriber: {}", e);
-                        let _ = app_clone.emit("status-update", format!("Error: {}", e));
-                        let _ = app_clone.emit("transcription-error", format!("Transcriber error: {}", e));
-                        let _ = hide_overlay_window(&app_clone);
-                        state.set_transcribing(false);
-                        return;
-                    }
-                };
-                
-                info!("Starting transcription with language: {}", config.language);
-                
-                let text = match transcriber.transcribe(&audio_data, &config.language) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        error!("Transcription failed: {}", e);
-                        let _ = app_clone.emit("status-update", format!("Error: {}", e));
-                        let _ = app_clone.emit("transcription-error", format!("Transcription failed: {}", e));
-                        let _ = hide_overlay_window(&app_clone);
-                        state.set_transcribing(false);
-                        return;
-                    }
-                };
-                
-                info!("Transcription complete: '{}'", text);
-                
-                // Type the result
-                let mut input = match InputSimulator::new() {
-                    Ok(i) => i,
-                    Err(e) => {
-                        error!("Failed to create input simulator: {}", e);
-                        let _ = app_clone.emit("status-update", format!("Error: {}", e));
-                        let _ = app_clone.emit("transcription-error", format!("Input error: {}", e));
-                        let _ = hide_overlay_window(&app_clone);
-                        state.set_transcribing(false);
-                        return;
-                    }
-                };
-                
-                if let Err(e) = input.type_text(&text) {
-                    warn!("Failed to type text: {}", e);
-                    let _ = app_clone.emit("status-update", format!("Error typing: {}", e));
-                }
-                
-                // Update state
-                state.set_result(text.clone());
-                
-                // Emit to frontend
-                let _ = app_clone.emit("transcription-update", &text);
-                let _ = app_clone.emit("status-update", "Ready");
-                let _ = app_clone.emit("transcription-complete", &text);
-                if let Some(overlay) = app_clone.get_webview_window("overlay") {
-                    let _ = overlay.emit("transcription-complete", &text);
-                }
-                
-                // Hide overlay after a delay
-                thread::sleep(Duration::from_millis(1000));
-                let _ = hide_overlay_window(&app_clone);
-            }
-            Err(e) => {
-                error!("Audio recording failed: {}", e);
-                let _ = app_clone.emit("status-update", format!("Error: {}", e));
-                let _ = app_clone.emit("transcription-error", format!("Recording error: {}", e));
-                let _ = hide_overlay_window(&app_clone);
-                state_clone.set_recording(false);
-            }
+    std::thread::spawn(move || {
+        if let Err(e) = load_model(&config) {
+            warn!("Failed to preload the model: {:#}", e);
         }
     });
-    
+}
+
+/// End a dictation that was routed somewhere else instead of being typed
+async fn finish_without_typing(app: &AppHandle, state: &AppState, transcript: &str) -> anyhow::Result<()> {
+    state.set_result(transcript.to_string());
+    state.transition(PipelineState::Idle)?;
+    state.publish(Event::Routed(transcript.to_string()));
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let _ = hide_overlay_window(app);
     Ok(())
 }
 
 fn show_overlay_window(app: &AppHandle) -> anyhow::Result<()> {
     if let Some(window) = app.get_webview_window("overlay") {
         let (x, y) = get_cursor_position();
-        let _ = window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y: y - 100 }));
+        place_overlay(&window, x, y - 100);
         let _ = window.show();
         let _ = window.set_focus();
     }
@@ -522,23 +2799,137 @@ fn hide_overlay_window(app: &AppHandle) -> anyhow::Result<()> {
 }
 
 pub fn run() {
+    let args = LaunchArgs::parse();
+    if args.portable {
+        Config::set_portable();
+    }
+    match &args.command {
+        Some(LaunchCommand::Replay { bundle }) => {
+            if let Err(e) = repro::replay(bundle) {
+                eprintln!("replay failed: {:#}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(LaunchCommand::Demo) => {
+            if let Err(e) = demo::run_cli() {
+                eprintln!("demo failed: {:#}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(LaunchCommand::History { limit }) => {
+            if let Err(e) = history::print(*limit) {
+                eprintln!("history failed: {:#}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(LaunchCommand::Ctl { command }) => {
+            if let Err(e) = ipc::run_ctl(command) {
+                eprintln!("{:#}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+    if let Some(addr) = args.service {
+        return service::run(addr);
+    }
+    crash::install();
+    // The OS privacy indicators should show Whisperia, not an anonymous audio client
+    let context = tauri::generate_context!();
+    privacy::register(&context.config().identifier);
+    let state = AppState::new().expect("Failed to create app state");
+    logging::init(&state.get_config().logging);
+    if let Some(dir) = Config::portable_dir() {
+        info!("Portable mode, data in {:?}", dir);
+    }
+    if let Ok(reports) = crash::list() {
+        let crashes = reports.iter().filter(|report| !report.recovered).count();
+        if crashes > 0 {
+            warn!("Found {} crash report(s) from earlier runs", crashes);
+        }
+    }
+    
     tauri::Builder::default()
-        .manage(AppState::new().expect("Failed to create app state"))
+        .manage(state)
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             get_status,
             get_config,
             update_config,
+            get_ui_theme,
+            set_log_level,
+            get_metrics,
+            get_last_run_report,
+            record_repro,
+            get_crash_reports,
+            dismiss_crash_reports,
             get_available_models,
             get_system_info,
+            get_inference_backends,
+            set_inference_backend,
+            get_permissions_status,
+            open_permission_settings,
+            get_send_targets,
+            send_transcript,
+            learn_correction,
+            get_glossary,
+            add_glossary_entry,
+            remove_glossary_entry,
+            add_hotword,
+            remove_hotword,
+            add_hotword_from_edit,
+            dismiss_glossary_suggestion,
+            get_format_intents,
+            set_format_intent,
+            get_tones,
+            set_tone,
+            list_prompt_templates,
+            save_prompt_template,
+            delete_prompt_template,
+            get_history,
+            delete_history_entry,
+            clear_history,
+            summarize_transcript,
+            download_model,
             open_settings,
             show_overlay,
             hide_overlay,
             start_recording,
+            revise_selection,
+            speak_last_result,
+            submit_webview_audio,
+            accept_refinement,
+            dismiss_refinement,
             stop_recording,
+            cancel_recording,
+            overlay_gesture,
+            get_quick_options,
+            select_quick_option,
+            get_api_usage,
+            cycle_tray_selection,
+            try_demo,
+            preload_model,
             start_interactive_recording,
+            start_captions,
+            stop_captions,
+            process_inbox,
+            start_meeting,
+            stop_meeting,
+            set_compose_mode,
+            set_open_mic,
+            open_scratch_note,
+            get_scratch_note,
+            set_scratch_note,
+            save_scratch_note,
+            send_compose,
+            discard_compose,
+            add_meeting_bookmark,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             info!("Whisperia Tauri app starting...");
             
             // Hide main window on startup
@@ -546,22 +2937,216 @@ pub fn run() {
                 let _ = window.hide();
             }
             
+            // Without a compositor IPC the overlay can only be placed as a layer surface
+            let session = Session::detect();
+            let compositor = Compositor::detect();
+            info!("Session: {:?}, compositor: {:?}", session, compositor);
+            info!("Permissions: {:?}", permissions::status());
+            if hardware::is_low_power_arm() {
+                info!("Low-power ARM board detected, NEON: {}", hardware::has_neon());
+            }
+            #[cfg(target_os = "linux")]
+            if session == Session::Wayland && compositor.is_none() {
+                if let Some(overlay) = app.get_webview_window("overlay") {
+                    if let Err(e) = layer_shell::anchor_overlay(&overlay) {
+                        warn!("Overlay can't be placed on this compositor: {:#}", e);
+                    }
+                }
+            }
+            
             // Setup system tray
             setup_tray(app)?;
             
             // Setup global hotkeys
-            setup_hotkeys(app)?;
+            hotkeys::register(&app.state::<AppState>())?;
             
-            // Setup menu event handler
+            // One worker runs the triggered dictations in order
+            tauri::async_runtime::spawn(run_jobs(app.handle().clone()));
+            
+            if app.state::<AppState>().get_config().wake_word.enabled {
+                wake_word::start(app.handle());
+            }
+            
+            // Voice memos waiting in the inbox, on the [inbox] schedule
+            inbox::spawn(app.handle().clone());
+            
+            // Unload the models after a while without dictations, following [idle]
+            idle::spawn(app.handle().clone());
+            
+            // Commands from scripts and keybinding daemons, `whisperia ctl`
+            ipc::spawn(app.handle().clone());
+            
+            // Load the model now so the first dictation doesn't wait for it
+            preload_in_background(app.state::<AppState>().get_config());
+            
+            // Fan pipeline events out to the windows and tray (and stdout with --print)
+            let state = app.state::<AppState>();
+            events::spawn_tauri(app.handle().clone(), state.subscribe(), state.shutdown_token());
+            if args.print {
+                events::spawn_printer(state.subscribe(), state.shutdown_token());
+            }
+            let accessibility = state.get_config().accessibility;
+            if accessibility.speak_feedback {
+                events::spawn_speech(accessibility, state.subscribe(), state.shutdown_token());
+            }
+            #[cfg(target_os = "windows")]
+            if state.get_config().ui.notifications {
+                let app_id = app.config().identifier.clone();
+                events::spawn_toasts(app_id, state.subscribe(), state.shutdown_token());
+            }
+            
+            // Start the MQTT publisher if enabled
+            let config = state.get_config();
+            if config.mqtt.enabled {
+                mqtt::spawn(config.mqtt, state.subscribe(), state.shutdown_token());
+            }
+            
+            // Start the local HTTP API if requested
+            if let Some(addr) = args.serve {
+                let app_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let shutdown = app_handle.state::<AppState>().shutdown_token();
+                    if let Err(e) = server::serve(app_handle, addr, shutdown).await {
+                        error!("HTTP API server failed: {}", e);
+                    }
+                });
+            }
+            
+            // Shut down cleanly on ctrl+c / SIGTERM too
             let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let shutdown = app_handle.state::<AppState>().shutdown_token();
+                tokio::select! {
+                    _ = shutdown::wait_for_signal() => shutdown::shutdown(&app_handle),
+                    _ = shutdown.cancelled() => {}
+                }
+            });
+            
+            // Setup menu event handler
             app.on_menu_event(move |app, event| {
                 match event.id.as_ref() {
                     "transcribe" => {
                         info!("Menu 'transcribe' clicked");
-                        if let Err(e) = trigger_transcription_flow(app.clone()) {
+                        if let Err(e) = trigger_transcription_flow(app.clone(), None) {
                             error!("Failed to trigger transcription from menu: {}", e);
                         }
                     }
+                    "summarize" => {
+                        info!("Menu 'summarize' clicked");
+                        let state = app.state::<AppState>();
+                        match state.get_history().into_iter().next() {
+                            Some(latest) => {
+                                let app = app.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    let state = app.state::<AppState>();
+                                    let text = latest.original.unwrap_or(latest.text);
+                                    let _ = summarize(&state, &text, &latest.language).await;
+                                });
+                            }
+                            None => warn!("Nothing to summarize yet"),
+                        }
+                    }
+                    "captions" => {
+                        info!("Menu 'captions' clicked");
+                        if app.state::<AppState>().captions_running() {
+                            captions::stop(app);
+                        } else {
+                            // creating a window from a menu handler deadlocks on windows
+                            let app = app.clone();
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) = captions::start(&app) {
+                                    error!("Failed to start live captions: {}", e);
+                                }
+                            });
+                        }
+                    }
+                    "meeting" => {
+                        info!("Menu 'meeting' clicked");
+                        let state = app.state::<AppState>().inner().clone();
+                        if state.meeting_running() {
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) = meeting::stop(&state).await {
+                                    error!("Failed to save the meeting: {}", e);
+                                    state.publish(Event::Error(e));
+                                }
+                            });
+                        } else if let Err(e) = meeting::start(&state) {
+                            error!("Failed to start the meeting: {}", e);
+                            state.publish(Event::Error(e));
+                        }
+                    }
+                    "scratch" => {
+                        info!("Menu 'scratch' clicked");
+                        // creating a window from a menu handler deadlocks on windows
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = scratch::open(&app) {
+                                error!("Failed to open the scratch note: {}", e);
+                            }
+                        });
+                    }
+                    "compose" => {
+                        let state = app.state::<AppState>();
+                        let enabled = !state.composing();
+                        info!("Menu 'compose' clicked, composing: {}", enabled);
+                        state.set_compose_mode(enabled);
+                    }
+                    "open_mic" => {
+                        let state = app.state::<AppState>();
+                        let enabled = !state.open_mic_running();
+                        info!("Menu 'open_mic' clicked, listening: {}", enabled);
+                        if enabled {
+                            open_mic::start(app);
+                        } else {
+                            open_mic::stop(&state);
+                        }
+                    }
+                    "compose_send" => {
+                        info!("Menu 'compose_send' clicked");
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = send_composed(&app).await {
+                                error!("Failed to send the composed message: {}", e);
+                            }
+                        });
+                    }
+                    "wake_word" => {
+                        // remembered, so the microphone stays closed after a restart too
+                        let state = app.state::<AppState>();
+                        let mut config = state.get_config();
+                        config.wake_word.enabled = !state.wake_word_running();
+                        info!("Menu 'wake_word' clicked, listening: {}", config.wake_word.enabled);
+                        if config.wake_word.enabled {
+                            wake_word::start(app);
+                        } else {
+                            wake_word::stop(&state);
+                        }
+                        if let Err(e) = state.update_config(config) {
+                            error!("Failed to save the wake word setting: {}", e);
+                        }
+                    }
+                    "offline" => {
+                        let state = app.state::<AppState>();
+                        let mut config = state.get_config();
+                        config.offline = !config.offline;
+                        info!("Menu 'offline' clicked, offline: {}", config.offline);
+                        if let Err(e) = state.update_config(config) {
+                            error!("Failed to save the offline setting: {}", e);
+                        }
+                    }
+                    "translate" => {
+                        let state = app.state::<AppState>();
+                        let mut config = state.get_config();
+                        config.model.translate = !config.model.translate;
+                        info!("Menu 'translate' clicked, translate: {}", config.model.translate);
+                        if let Err(e) = state.update_config(config) {
+                            error!("Failed to save the translate setting: {}", e);
+                        }
+                    }
+                    "repro" => {
+                        info!("Menu 'repro' clicked");
+                        app.state::<AppState>().arm_repro();
+                    }
                     "settings" => {
                         info!("Menu 'settings' clicked");
                         if let Some(window) = app.get_webview_window("main") {
@@ -572,29 +3157,52 @@ pub fn run() {
                     }
                     "quit" => {
                         info!("Menu 'quit' clicked - exiting application");
-                        app.exit(0);
+                        shutdown::shutdown(app);
                     }
-                    _ => {}
-                }
-            });
-            
-            // Setup global hotkey event receiver thread
-            let app_handle = app.handle().clone();
-            thread::spawn(move || {
-                info!("Starting global hotkey event listener thread");
-                let receiver = GlobalHotKeyEvent::receiver();
-                loop {
-                    if let Ok(event) = receiver.recv() {
-                        info!("Global hotkey event received: {:?}", event);
-                        if event.state == HotKeyState::Pressed {
-                            if let Err(e) = trigger_transcription_flow(app_handle.clone()) {
-                                error!("Failed to trigger transcription from hotkey: {}", e);
+                    id if id.starts_with("intent:") => {
+                        let intent = id.trim_start_matches("intent:");
+                        let intent = (!intent.is_empty()).then(|| intent.to_string());
+                        app.state::<AppState>().set_format_intent(intent);
+                    }
+                    id if id.starts_with("insert:") => {
+                        let state = app.state::<AppState>();
+                        let mut config = state.get_config();
+                        config.output.insert = match id.trim_start_matches("insert:") {
+                            "clipboard" => InsertMode::Clipboard,
+                            "paste" => InsertMode::Paste,
+                            _ => InsertMode::Type,
+                        };
+                        info!("Menu '{}' clicked", id);
+                        if let Err(e) = state.update_config(config) {
+                            error!("Failed to save the insert setting: {}", e);
+                        }
+                    }
+                    id if id.starts_with("tone:") => {
+                        let tone = id.trim_start_matches("tone:");
+                        let tone = (!tone.is_empty()).then(|| tone.to_string());
+                        app.state::<AppState>().set_tone(tone);
+                    }
+                    id if id.starts_with("send:") => {
+                        let target = id.trim_start_matches("send:").to_string();
+                        info!("Menu 'send to {}' clicked", target);
+                        let state = app.state::<AppState>();
+                        let config = state.get_config();
+                        match state.get_status().last_result {
+                            Some(text) => {
+                                tauri::async_runtime::spawn(async move {
+                                    let _ = send_to_target(&config, &target, &text).await;
+                                });
                             }
+                            None => warn!("Nothing to send yet"),
                         }
                     }
+                    _ => {}
                 }
             });
             
+            // Listen for hotkey presses, re-registering them if the listener dies
+            hotkeys::spawn_listener(app.handle().clone());
+            
             Ok(())
         })
         .on_window_event(|window, event| {
@@ -606,6 +3214,80 @@ pub fn run() {
                 }
             }
         })
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // nothing read from or written to the data dir
+    fn state() -> AppState {
+        let mut config = Config::default();
+        config.history.persist = false;
+        AppState::with_config(config)
+    }
+    
+    #[test]
+    fn flags_follow_the_pipeline() {
+        let state = state();
+        let flags = |state: &AppState| {
+            let status = state.get_status();
+            (status.is_recording, status.is_transcribing)
+        };
+        assert_eq!(flags(&state), (false, false));
+        
+        state.transition(PipelineState::Recording).unwrap();
+        assert_eq!(flags(&state), (true, false));
+        state.transition(PipelineState::Transcribing).unwrap();
+        assert_eq!(flags(&state), (false, true));
+        state.transition(PipelineState::Inserting).unwrap();
+        assert_eq!(flags(&state), (false, false));
+        state.transition(PipelineState::Idle).unwrap();
+        assert_eq!(flags(&state), (false, false));
+        
+        state.transition(PipelineState::Recording).unwrap();
+        state.fail("no microphone");
+        assert_eq!(flags(&state), (false, false));
+        assert_eq!(state.get_status().pipeline.name(), "error");
+    }
+    
+    #[test]
+    fn refused_transitions_keep_the_flags() {
+        let state = state();
+        assert!(state.transition(PipelineState::Inserting).is_err());
+        assert!(state.transition(PipelineState::Transcribing).is_err());
+        let status = state.get_status();
+        assert_eq!(status.pipeline, PipelineState::Idle);
+        assert!(!status.is_recording && !status.is_transcribing);
+    }
+    
+    #[test]
+    fn only_one_dictation_records_at_a_time() {
+        let state = state();
+        let cancel = state.begin_recording().unwrap();
+        assert_eq!(state.begin_recording().unwrap_err(), PipelineState::Recording);
+        
+        state.cancel_run();
+        assert!(cancel.is_cancelled());
+        state.transition(PipelineState::Idle).unwrap();
+        
+        // a new dictation gets a token of its own
+        let next = state.begin_recording().unwrap();
+        assert!(!next.is_cancelled());
+    }
+    
+    #[test]
+    fn clones_share_the_state() {
+        let state = state();
+        let clone = state.clone();
+        let cancel = clone.begin_recording().unwrap();
+        assert!(state.get_status().is_recording);
+        
+        state.cancel_run();
+        assert!(cancel.is_cancelled());
+        clone.set_result("hello".to_string());
+        assert_eq!(state.get_status().last_result.as_deref(), Some("hello"));
+    }
+}
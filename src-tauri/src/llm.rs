@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::{Config, FormatIntent, LlmConfig};
+use crate::{prompts, usage};
+
+// keep each request well inside small context windows (and the local llm's)
+const SUMMARY_CHUNK_CHARS: usize = 8000;
+
+/// Single-turn chat completion with the configured backend
+pub async fn complete(config: &LlmConfig, system: &str, user: &str) -> Result<String> {
+    complete_with_temperature(config, system, user, None).await
+}
+
+/// `temperature` of `None` leaves the provider default (greedy for the local backend)
+pub async fn complete_with_temperature(
+    config: &LlmConfig,
+    system: &str,
+    user: &str,
+    temperature: Option<f32>,
+) -> Result<String> {
+    if config.backend == "local" {
+        return complete_local(config, system, user, temperature).await;
+    }
+    complete_api(config, system, user, temperature).await
+}
+
+#[cfg(feature = "local-llm")]
+async fn complete_local(
+    config: &LlmConfig,
+    system: &str,
+    user: &str,
+    temperature: Option<f32>,
+) -> Result<String> {
+    let model_path = crate::models::llm_model_path(config)?;
+    let (system, user) = (system.to_string(), user.to_string());
+    tokio::task::spawn_blocking(move || {
+        crate::local_llm::complete(&model_path, &system, &user, temperature)
+    })
+    .await
+    .context("local llm task panicked")?
+}
+
+#[cfg(not(feature = "local-llm"))]
+async fn complete_local(
+    _config: &LlmConfig,
+    _system: &str,
+    _user: &str,
+    _temperature: Option<f32>,
+) -> Result<String> {
+    anyhow::bail!("whisperia was built without the local-llm feature")
+}
+
+/// Chat completion against an openai-compatible endpoint
+async fn complete_api(
+    config: &LlmConfig,
+    system: &str,
+    user: &str,
+    temperature: Option<f32>,
+) -> Result<String> {
+    Config::ensure_online("llm post-processing")?;
+    let url = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+    let mut body = serde_json::json!({
+        "model": config.model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": user },
+        ],
+    });
+    if let Some(temperature) = temperature {
+        body["temperature"] = temperature.into();
+    }
+
+    let mut request = reqwest::Client::new()
+        .post(&url)
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .json(&body);
+    // local servers usually don't need a key
+    if !config.api_key.is_empty() {
+        request = request.bearer_auth(&config.api_key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("failed to reach {}", url))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("llm request failed ({}): {}", status, body);
+    }
+
+    let reply: Value = response.json().await.context("invalid llm response")?;
+    let content = reply["choices"][0]["message"]["content"]
+        .as_str()
+        .context("llm response has no content")?;
+
+    // servers on this machine don't cost anything
+    if let Some(host) = provider_host(&config.base_url) {
+        usage::record(host, 0.0, reply["usage"]["total_tokens"].as_u64().unwrap_or(0));
+    }
+
+    Ok(content.trim().to_string())
+}
+
+/// "api.openai.com" for "https://api.openai.com/v1", `None` for local servers
+fn provider_host(base_url: &str) -> Option<&str> {
+    let rest = base_url.split_once("://").map_or(base_url, |(_, rest)| rest);
+    let host = rest.split(['/', ':']).next()?;
+    let local = host.is_empty() || host == "localhost" || host.starts_with("127.") || rest.starts_with("[::1]");
+    (!local).then_some(host)
+}
+
+/// Run the rewrite and translation steps for a dictation. A failing step is
+/// skipped so the transcript is never lost.
+pub async fn process(
+    config: &LlmConfig,
+    intent: Option<&FormatIntent>,
+    tone: Option<&FormatIntent>,
+    text: &str,
+) -> String {
+    let mut text = text.to_string();
+
+    // the intent shapes the text (email, list...), the tone adjusts how it reads
+    for step in [intent, tone].into_iter().flatten() {
+        match reformat(config, step, &text).await {
+            Ok(formatted) => text = formatted,
+            Err(e) => warn!("reformat as '{}' failed, keeping previous text: {:#}", step.name, e),
+        }
+    }
+
+    let target = intent
+        .and_then(|i| i.translate_to.as_deref())
+        .or(config.translate_to.as_deref());
+    if let Some(target) = target {
+        match translate(config, &text, target).await {
+            Ok(translated) => text = translated,
+            Err(e) => warn!("translation failed, using untranslated text: {:#}", e),
+        }
+    }
+
+    text
+}
+
+/// Rewrite the transcript following the intent's prompt template
+pub async fn reformat(config: &LlmConfig, intent: &FormatIntent, text: &str) -> Result<String> {
+    let prompt = match &intent.template {
+        Some(name) => prompts::load(name)?,
+        None => intent.prompt.clone(),
+    };
+    if prompt.is_empty() {
+        // translation-only intent
+        return Ok(text.to_string());
+    }
+    info!("reformatting transcript as '{}'", intent.name);
+
+    if prompt.contains("{text}") {
+        let prompt = prompt.replace("{text}", text);
+        complete_with_temperature(config, "You rewrite dictated text.", &prompt, intent.temperature)
+            .await
+    } else {
+        complete_with_temperature(config, &prompt, text, intent.temperature).await
+    }
+}
+
+/// Translate the transcript to any language the model knows, unlike whisper's english-only translate
+pub async fn translate(config: &LlmConfig, text: &str, target_language: &str) -> Result<String> {
+    info!("translating transcript to {}", target_language);
+
+    let system = format!(
+        "Translate the user's text to {}. Keep the meaning, tone and formatting. \
+         Reply with the translation only.",
+        target_language
+    );
+    complete(config, &system, text).await
+}
+
+/// Apply a spoken instruction ("make it shorter", "fix the typos") to a text
+/// the user selected, returning the text to put in its place
+pub async fn revise(config: &LlmConfig, selection: &str, instruction: &str) -> Result<String> {
+    info!("revising {} selected chars", selection.len());
+
+    let system = "You edit text following the user's instruction. Keep the language of the text \
+                  unless told otherwise. Reply with the revised text only, without quotes or comments.";
+    let user = format!("Instruction: {}\n\nText:\n{}", instruction, selection);
+    complete(config, system, &user).await
+}
+
+/// Summary and action items for a long transcript. Inputs that don't fit in one
+/// request are summarized chunk by chunk, then the partial summaries are merged.
+pub async fn summarize(config: &LlmConfig, transcript: &str) -> Result<String> {
+    let chunks = split_chunks(transcript, SUMMARY_CHUNK_CHARS);
+    info!("summarizing transcript ({} chars, {} chunks)", transcript.len(), chunks.len());
+
+    if chunks.len() == 1 {
+        return complete(config, &config.summary_prompt, transcript).await;
+    }
+
+    let mut partials = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.iter().enumerate() {
+        let system = format!(
+            "{}\nThis is part {} of {} of the transcript.",
+            config.summary_prompt,
+            i + 1,
+            chunks.len()
+        );
+        partials.push(complete(config, &system, chunk).await?);
+    }
+
+    let system = format!(
+        "{}\nThe input is a list of summaries of consecutive parts of one transcript; \
+         merge them into a single summary and one deduplicated action item list.",
+        config.summary_prompt
+    );
+    complete(config, &system, &partials.join("\n\n")).await
+}
+
+/// Where the topic of a transcript changes: `lines` are its segments in order,
+/// the result is `(index of the first line, title)` per chapter. Long transcripts
+/// are segmented chunk by chunk.
+pub async fn chapters(config: &LlmConfig, lines: &[String]) -> Result<Vec<(usize, String)>> {
+    let numbered = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| format!("{}: {}", i, line.replace('\n', " ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let chunks = split_chunks(&numbered, SUMMARY_CHUNK_CHARS);
+    info!("finding chapters in {} lines ({} chunks)", lines.len(), chunks.len());
+
+    let system = "The user sends numbered lines of a meeting transcript. Split it into chapters \
+                  where the topic changes. Reply with one line per chapter, \
+                  \"<number of its first line>: <short title>\", in the transcript's language and nothing else.";
+    let mut chapters: Vec<(usize, String)> = Vec::new();
+    for chunk in chunks {
+        let reply = complete(config, system, chunk).await?;
+        for line in reply.lines() {
+            let Some((index, title)) = line.split_once(':') else {
+                continue;
+            };
+            let index = index.trim().trim_start_matches(['-', '*', ' ']).trim();
+            let (Ok(index), title) = (index.parse::<usize>(), title.trim()) else {
+                continue;
+            };
+            let after = chapters.last().map_or(true, |(last, _)| index > *last);
+            if index < lines.len() && after && !title.is_empty() {
+                chapters.push((index, title.to_string()));
+            }
+        }
+    }
+    if chapters.is_empty() {
+        anyhow::bail!("the llm found no chapters");
+    }
+    Ok(chapters)
+}
+
+/// Split on sentence ends (or whitespace) so no chunk is longer than `max_chars`
+fn split_chunks(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let window = &rest[..limit];
+        let cut = window
+            .rfind(['.', '!', '?', '\n'])
+            .map(|i| i + 1)
+            .or_else(|| window.rfind(char::is_whitespace))
+            .filter(|&i| i > 0)
+            .unwrap_or(limit);
+
+        chunks.push(rest[..cut].trim());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
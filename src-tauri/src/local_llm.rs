@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaChatTemplate, LlamaModel};
+use llama_cpp_2::sampling::LlamaSampler;
+use llama_cpp_2::token::LlamaToken;
+use llama_cpp_2::TokenToStringError;
+use parking_lot::Mutex;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tracing::info;
+
+// cleanup/translation of a dictation never needs more than this
+const CONTEXT_SIZE: u32 = 4096;
+const MAX_NEW_TOKENS: usize = 1024;
+
+static BACKEND: OnceLock<LlamaBackend> = OnceLock::new();
+// the last loaded model stays in memory, loading a gguf takes seconds
+static MODEL: Mutex<Option<(PathBuf, Arc<LlamaModel>)>> = Mutex::new(None);
+
+fn backend() -> Result<&'static LlamaBackend> {
+    if let Some(backend) = BACKEND.get() {
+        return Ok(backend);
+    }
+    let backend = LlamaBackend::init().context("failed to initialize llama.cpp")?;
+    Ok(BACKEND.get_or_init(|| backend))
+}
+
+fn load_model(path: &Path) -> Result<Arc<LlamaModel>> {
+    let mut cached = MODEL.lock();
+    if let Some((loaded_path, model)) = cached.as_ref() {
+        if loaded_path == path {
+            return Ok(model.clone());
+        }
+    }
+
+    info!("loading local llm: {:?}", path);
+    let model = LlamaModel::load_from_file(backend()?, path, &LlamaModelParams::default())
+        .with_context(|| format!("failed to load {:?}", path))?;
+    let model = Arc::new(model);
+    *cached = Some((path.to_path_buf(), model.clone()));
+    Ok(model)
+}
+
+/// Drop the loaded model, returning whether there was one
+pub fn unload() -> bool {
+    MODEL.lock().take().is_some()
+}
+
+/// Blocking single-turn chat completion with a gguf instruct model
+pub fn complete(
+    model_path: &Path,
+    system: &str,
+    user: &str,
+    temperature: Option<f32>,
+) -> Result<String> {
+    let backend = backend()?;
+    let model = load_model(model_path)?;
+
+    // fall back to chatml for models that don't ship a template
+    let template = match model.chat_template(None) {
+        Ok(template) => template,
+        Err(_) => LlamaChatTemplate::new("chatml")?,
+    };
+    let chat = [
+        LlamaChatMessage::new("system".to_string(), system.to_string())?,
+        LlamaChatMessage::new("user".to_string(), user.to_string())?,
+    ];
+    let prompt = model.apply_chat_template(&template, &chat, true)?;
+
+    let context_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(CONTEXT_SIZE));
+    let mut ctx = model
+        .new_context(backend, context_params)
+        .context("failed to create llama context")?;
+
+    let tokens = model.str_to_token(&prompt, AddBos::Always)?;
+    if tokens.len() + MAX_NEW_TOKENS > CONTEXT_SIZE as usize {
+        anyhow::bail!("text too long for the local llm ({} tokens)", tokens.len());
+    }
+
+    let mut batch = LlamaBatch::new(tokens.len().max(1), 1);
+    let last = tokens.len() as i32 - 1;
+    for (i, token) in tokens.iter().enumerate() {
+        let i = i as i32;
+        batch.add(*token, i, &[0], i == last)?;
+    }
+    ctx.decode(&mut batch).context("llama decode failed")?;
+
+    // greedy unless asked otherwise: we want the most faithful rewrite, not creativity
+    let mut sampler = match temperature {
+        Some(temperature) if temperature > 0.0 => LlamaSampler::chain_simple([
+            LlamaSampler::temp(temperature),
+            LlamaSampler::dist(rand_seed()),
+        ]),
+        _ => LlamaSampler::greedy(),
+    };
+    let mut position = batch.n_tokens();
+    let mut output = Vec::new();
+
+    for _ in 0..MAX_NEW_TOKENS {
+        let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+        sampler.accept(token);
+        if model.is_eog_token(token) {
+            break;
+        }
+        output.extend(token_bytes(&model, token)?);
+
+        batch.clear();
+        batch.add(token, position, &[0], true)?;
+        position += 1;
+        ctx.decode(&mut batch).context("llama decode failed")?;
+    }
+
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+fn rand_seed() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0)
+}
+
+fn token_bytes(model: &LlamaModel, token: LlamaToken) -> Result<Vec<u8>> {
+    match model.token_to_piece_bytes(token, 32, false, None) {
+        Err(TokenToStringError::InsufficientBufferSpace(needed)) => Ok(model
+            .token_to_piece_bytes(token, (-needed) as usize, false, None)?),
+        result => Ok(result?),
+    }
+}
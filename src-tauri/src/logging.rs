@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+use crate::config::{Config, LoggingConfig};
+
+// the day's file keeps growing, the cap is checked again this often
+const PRUNE_EVERY: Duration = Duration::from_secs(60 * 60);
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Log to stdout and to a daily rotated file in the data dir
+pub fn init(config: &LoggingConfig) {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) => EnvFilter::new(directives),
+        Err(_) => filter(&config.level).unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let file = Config::logs_dir().and_then(|dir| {
+        let max_bytes = config.max_size_mb * 1024 * 1024;
+        prune(&dir, max_bytes);
+        spawn_pruning(dir.clone(), max_bytes);
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("whisperia")
+            .filename_suffix("log")
+            .max_log_files(config.max_files.max(1))
+            .build(&dir)
+            .context("Failed to create log file")
+    });
+
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer());
+    match file {
+        Ok(file) => registry
+            .with(fmt::layer().with_ansi(false).with_writer(file))
+            .init(),
+        Err(e) => {
+            registry.init();
+            warn!("Logging to stdout only: {:#}", e);
+        }
+    }
+
+    let _ = FILTER.set(handle);
+}
+
+/// Change the log level of the running app, e.g. "debug" while chasing a bug
+pub fn set_level(level: &str) -> Result<()> {
+    let filter = filter(level)?;
+    FILTER
+        .get()
+        .context("Logging is not initialized")?
+        .reload(filter)
+        .context("Failed to change the log level")?;
+    info!("Log level set to {}", level);
+    Ok(())
+}
+
+// a plain level applies to our crates only, full directives are taken as they are
+fn filter(level: &str) -> Result<EnvFilter> {
+    let directives = if level.contains('=') || level.contains(',') {
+        level.to_string()
+    } else {
+        level
+            .parse::<tracing::Level>()
+            .map_err(|_| anyhow::anyhow!("Unknown log level: {}", level))?;
+        format!("warn,whisperia={level},whisperia_lib={level}")
+    };
+    EnvFilter::try_new(&directives).with_context(|| format!("Invalid log filter: {}", directives))
+}
+
+fn spawn_pruning(dir: PathBuf, max_bytes: u64) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(PRUNE_EVERY);
+        prune(&dir, max_bytes);
+    });
+}
+
+/// Delete the oldest log files until the rest fits in `max_bytes`
+fn prune(dir: &Path, max_bytes: u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, u64, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            metadata.is_file().then(|| (modified, metadata.len(), entry.path()))
+        })
+        .collect();
+    files.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+
+    // the newest file is always kept, it's the one being written to
+    let mut total = 0;
+    for (index, (_, size, path)) in files.iter().enumerate() {
+        total += size;
+        if index > 0 && total > max_bytes {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
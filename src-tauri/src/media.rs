@@ -0,0 +1,251 @@
+use crate::config::MediaConfig;
+use crate::WhisperiaError;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+#[cfg(target_os = "linux")]
+use tracing::{info, warn};
+
+/// Keeps media players paused (and the output ducked) while alive,
+/// restoring everything when dropped
+#[derive(Default)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct MediaGuard {
+    paused_players: Vec<String>,
+    restore_volume: Option<Volume>,
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+enum Volume {
+    PipeWire(f32),
+    Pulse(u32), // percent
+}
+
+/// Pause playing media and duck the output according to config
+pub fn pause_for_recording(config: &MediaConfig) -> MediaGuard {
+    #[allow(unused_mut)]
+    let mut guard = MediaGuard::default();
+
+    #[cfg(target_os = "linux")]
+    {
+        if config.pause_players {
+            guard.paused_players = pause_players();
+        }
+        if config.duck_volume {
+            guard.restore_volume = duck_volume(config.duck_level);
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = config;
+
+    guard
+}
+
+/// Before recording: fail when the os has the default microphone muted,
+/// instead of recording seconds of silence. Unknown counts as not muted.
+pub fn ensure_unmuted(config: &MediaConfig) -> Result<(), WhisperiaError> {
+    if config.check_mute && input_muted() == Some(true) {
+        return Err(WhisperiaError::NoMic(
+            "the microphone is muted, unmute it in the system sound settings".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the default input is muted, `None` when it can't be told
+#[cfg(target_os = "linux")]
+pub fn input_muted() -> Option<bool> {
+    // pipewire first: "Volume: 1.00 [MUTED]"
+    if let Ok(output) = Command::new("wpctl")
+        .args(["get-volume", "@DEFAULT_AUDIO_SOURCE@"])
+        .output()
+    {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if output.status.success() && stdout.starts_with("Volume:") {
+            return Some(stdout.contains("[MUTED]"));
+        }
+    }
+
+    // fallback to pulseaudio: "Mute: yes"
+    let output = Command::new("pactl")
+        .args(["get-source-mute", "@DEFAULT_SOURCE@"])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().strip_prefix("Mute:")?.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// macOS has no input mute switch, an input volume of zero is how it's muted
+#[cfg(target_os = "macos")]
+pub fn input_muted() -> Option<bool> {
+    let output = Command::new("osascript")
+        .args(["-e", "input volume of (get volume settings)"])
+        .output()
+        .ok()?;
+    let volume = String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().ok()?;
+    Some(volume == 0)
+}
+
+#[cfg(windows)]
+pub fn input_muted() -> Option<bool> {
+    crate::win32::input_muted()
+        .map_err(|e| tracing::warn!("failed to read the microphone mute state: {:#}", e))
+        .ok()
+}
+
+impl Drop for MediaGuard {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(volume) = self.restore_volume.take() {
+                restore_volume(volume);
+            }
+            for player in &self.paused_players {
+                info!("resuming media player {}", player);
+                mpris_call(player, "Play");
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn dbus_send(args: &[&str]) -> Option<String> {
+    let output = Command::new("dbus-send")
+        .arg("--session")
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Pull every `string "..."` value out of a dbus-send reply
+#[cfg(target_os = "linux")]
+fn reply_strings(reply: &str) -> Vec<String> {
+    reply
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("string \""))
+        .filter_map(|rest| rest.strip_suffix('"'))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn mpris_call(player: &str, method: &str) {
+    let dest = format!("--dest={}", player);
+    let method = format!("org.mpris.MediaPlayer2.Player.{}", method);
+    if dbus_send(&["--type=method_call", &dest, "/org/mpris/MediaPlayer2", &method]).is_none() {
+        warn!("failed to send {} to {}", method, player);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pause_players() -> Vec<String> {
+    let Some(names) = dbus_send(&[
+        "--print-reply",
+        "--dest=org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus.ListNames",
+    ]) else {
+        warn!("dbus-send unavailable, not pausing media players");
+        return Vec::new();
+    };
+
+    let mut paused = Vec::new();
+    for player in reply_strings(&names)
+        .into_iter()
+        .filter(|n| n.starts_with("org.mpris.MediaPlayer2."))
+    {
+        let dest = format!("--dest={}", player);
+        let status = dbus_send(&[
+            "--print-reply",
+            &dest,
+            "/org/mpris/MediaPlayer2",
+            "org.freedesktop.DBus.Properties.Get",
+            "string:org.mpris.MediaPlayer2.Player",
+            "string:PlaybackStatus",
+        ]);
+
+        // only touch players that are actually playing, so we don't resume
+        // something the user had paused on purpose
+        if status.map(|s| s.contains("\"Playing\"")).unwrap_or(false) {
+            info!("pausing media player {}", player);
+            mpris_call(&player, "Pause");
+            paused.push(player);
+        }
+    }
+
+    paused
+}
+
+#[cfg(target_os = "linux")]
+fn duck_volume(level: f32) -> Option<Volume> {
+    let level = level.clamp(0.0, 1.0);
+
+    // pipewire first
+    if let Ok(output) = Command::new("wpctl")
+        .args(["get-volume", "@DEFAULT_AUDIO_SINK@"])
+        .output()
+    {
+        // "Volume: 0.40" or "Volume: 0.40 [MUTED]"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(current) = stdout
+            .split_whitespace()
+            .nth(1)
+            .and_then(|v| v.parse::<f32>().ok())
+        {
+            let ducked = format!("{:.2}", current * level);
+            let _ = Command::new("wpctl")
+                .args(["set-volume", "@DEFAULT_AUDIO_SINK@", &ducked])
+                .status();
+            info!("ducked output volume {} -> {}", current, ducked);
+            return Some(Volume::PipeWire(current));
+        }
+    }
+
+    // fallback to pulseaudio
+    if let Ok(output) = Command::new("pactl")
+        .args(["get-sink-volume", "@DEFAULT_SINK@"])
+        .output()
+    {
+        // "Volume: front-left: 26214 /  40% / -23.87 dB, ..."
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(current) = stdout
+            .split('/')
+            .nth(1)
+            .and_then(|v| v.trim().trim_end_matches('%').parse::<u32>().ok())
+        {
+            let ducked = format!("{}%", (current as f32 * level) as u32);
+            let _ = Command::new("pactl")
+                .args(["set-sink-volume", "@DEFAULT_SINK@", &ducked])
+                .status();
+            info!("ducked output volume {}% -> {}", current, ducked);
+            return Some(Volume::Pulse(current));
+        }
+    }
+
+    warn!("could not read output volume (wpctl/pactl), not ducking");
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn restore_volume(volume: Volume) {
+    let result = match volume {
+        Volume::PipeWire(level) => Command::new("wpctl")
+            .args(["set-volume", "@DEFAULT_AUDIO_SINK@", &format!("{:.2}", level)])
+            .status(),
+        Volume::Pulse(percent) => Command::new("pactl")
+            .args(["set-sink-volume", "@DEFAULT_SINK@", &format!("{}%", percent)])
+            .status(),
+    };
+
+    if let Err(e) = result {
+        warn!("failed to restore output volume: {}", e);
+    }
+}
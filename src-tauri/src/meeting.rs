@@ -0,0 +1,433 @@
+//! Meeting mode: record until stopped, transcribe ~30 second chunks in the
+//! background while the recording goes on, and keep one timestamped
+//! transcript. Unlike a dictation nothing is typed; the transcript goes to
+//! the history and is exported as SRT and Markdown at the end. Bookmarks
+//! dropped while recording are highlighted in both, and the transcript can be
+//! split into titled chapters at long pauses or where the llm sees the topic change.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::{ChapterMode, Config};
+use crate::i18n::tf;
+use crate::pace::Pace;
+use crate::{
+    crash, llm, memory, tuning, AppState, AudioRecorder, Event, HistoryEntry, Segment, Transcriber,
+    WhisperiaError,
+};
+
+// how often captured audio is picked up from the device
+const POLL: Duration = Duration::from_millis(500);
+
+/// A finished meeting, timestamps relative to its start
+#[derive(Debug, Clone, Serialize)]
+pub struct Meeting {
+    pub started: u64, // unix seconds
+    pub duration_ms: i64,
+    pub segments: Vec<Segment>,
+    pub bookmarks: Vec<Bookmark>,
+    pub chapters: Vec<Chapter>,
+    pub srt_path: PathBuf,
+    pub markdown_path: PathBuf,
+}
+
+/// A moment marked while recording, e.g. "decision" or just the hotkey
+#[derive(Debug, Clone, Serialize)]
+pub struct Bookmark {
+    pub at_ms: i64,
+    pub label: Option<String>,
+}
+
+/// Where a part of the meeting about one topic starts
+#[derive(Debug, Clone, Serialize)]
+pub struct Chapter {
+    pub start_ms: i64,
+    pub title: String,
+}
+
+/// A meeting being recorded
+pub struct Session {
+    stop: CancellationToken,
+    started: Instant,
+    bookmarks: Arc<Mutex<Vec<Bookmark>>>,
+    worker: JoinHandle<Result<Meeting>>,
+}
+
+impl Session {
+    fn bookmark(&self, label: Option<String>) -> Bookmark {
+        let bookmark = Bookmark {
+            at_ms: self.started.elapsed().as_millis() as i64,
+            label: label.filter(|l| !l.trim().is_empty()),
+        };
+        self.bookmarks.lock().push(bookmark.clone());
+        bookmark
+    }
+}
+
+/// Start recording a meeting
+pub fn start(state: &AppState) -> Result<(), WhisperiaError> {
+    if state.meeting_running() {
+        return Err(WhisperiaError::Busy("a meeting is already being recorded".to_string()));
+    }
+    let config = state.get_config();
+    // load the model now, so a missing one fails here and not after an hour
+    let (model_path, _) = memory::pick_model(&config)?;
+    let transcriber = Transcriber::cached(&model_path, config.model.cached_models)
+        .map_err(WhisperiaError::decode_failed)?
+        .with_threads(tuning::threads(&model_path, config.model.threads))
+        .with_translate(config.model.translate);
+
+    info!("starting meeting recording");
+    let stop = CancellationToken::new();
+    let bookmarks = Arc::new(Mutex::new(Vec::new()));
+    let worker = {
+        let (state, stop, bookmarks) = (state.clone(), stop.clone(), bookmarks.clone());
+        std::thread::spawn(move || {
+            crash::recoverable(|| record(&state, &config, transcriber, &stop, &bookmarks))
+        })
+    };
+    state.set_meeting(Session {
+        stop,
+        started: Instant::now(),
+        bookmarks,
+        worker,
+    });
+    Ok(())
+}
+
+/// Mark the current moment of the meeting being recorded
+pub fn bookmark(state: &AppState, label: Option<String>) -> Result<Bookmark, WhisperiaError> {
+    let bookmark = state
+        .with_meeting(|session| session.bookmark(label))
+        .ok_or_else(|| WhisperiaError::Invalid("no meeting is being recorded".to_string()))?;
+    info!("meeting bookmark at {}", clock(bookmark.at_ms));
+    state.publish(Event::Status(tf("status.bookmark", &[&clock(bookmark.at_ms)])));
+    Ok(bookmark)
+}
+
+/// Stop recording, wait for the last chunk and save the transcript
+pub async fn stop(state: &AppState) -> Result<Meeting, WhisperiaError> {
+    let session = state
+        .take_meeting()
+        .ok_or_else(|| WhisperiaError::Invalid("no meeting is being recorded".to_string()))?;
+    info!("stopping meeting recording");
+    session.stop.cancel();
+
+    let meeting = tokio::task::spawn_blocking(move || session.worker.join())
+        .await
+        .map_err(|e| WhisperiaError::Internal(e.to_string()))?
+        .map_err(WhisperiaError::from_panic)??;
+    info!("meeting saved to {:?}", meeting.markdown_path);
+    state.publish(Event::Status(tf("status.meeting_saved", &[&meeting.markdown_path.display()])));
+    Ok(meeting)
+}
+
+fn record(
+    state: &AppState,
+    config: &Config,
+    transcriber: Transcriber,
+    stop: &CancellationToken,
+    bookmarks: &Mutex<Vec<Bookmark>>,
+) -> Result<Meeting> {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let recorder = AudioRecorder::with_device(config.meeting.source.as_deref())
+        .map_err(WhisperiaError::no_mic)?;
+    let rate = recorder.sample_rate();
+    // the decoder times its chunks, the recording cuts the next ones to suit
+    let pace = Arc::new(Mutex::new(Pace::new(config.meeting.chunk_secs.max(5), config.meeting.adaptive)));
+
+    // chunks are decoded on their own thread, the recording doesn't wait for whisper
+    let (chunks, pending) = mpsc::channel::<(i64, Vec<f32>)>();
+    let decoder = {
+        let (state, language, pace) = (state.clone(), config.language.clone(), pace.clone());
+        std::thread::spawn(move || {
+            crash::recoverable(|| decode(&state, &transcriber, &language, &pace, pending))
+        })
+    };
+
+    let mut audio = Vec::new();
+    let mut offset_ms = 0;
+    let recorded = recorder.stream_until_stopped(stop, POLL, |samples| {
+        audio.extend(AudioRecorder::resample(&samples, rate, 16000));
+        if audio.len() >= pace.lock().chunk() {
+            let samples = std::mem::take(&mut audio);
+            let duration_ms = samples.len() as i64 / 16;
+            let _ = chunks.send((offset_ms, samples));
+            offset_ms += duration_ms;
+        }
+    });
+    let duration_ms = offset_ms + audio.len() as i64 / 16;
+    if !audio.is_empty() {
+        let _ = chunks.send((offset_ms, audio));
+    }
+    drop(chunks);
+
+    // keep what was transcribed even when the device failed halfway
+    let segments = decoder.join().map_err(WhisperiaError::from_panic)?;
+    if let Err(e) = recorded {
+        warn!("meeting recording stopped early: {:#}", e);
+    }
+    let bookmarks = std::mem::take(&mut *bookmarks.lock());
+    save(state, config, started, duration_ms, segments, bookmarks)
+}
+
+fn decode(
+    state: &AppState,
+    transcriber: &Transcriber,
+    language: &str,
+    pace: &Mutex<Pace>,
+    chunks: mpsc::Receiver<(i64, Vec<f32>)>,
+) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for (offset_ms, samples) in chunks {
+        let decoded = Arc::new(Mutex::new(Vec::new()));
+        let collected = decoded.clone();
+        let started = Instant::now();
+        let result = transcriber.transcribe_with_callback(&samples, language, move |segment| {
+            collected.lock().push(segment);
+        });
+        pace.lock().record(samples.len(), started.elapsed());
+        if let Err(e) = result {
+            warn!("failed to transcribe meeting chunk at {}: {:#}", clock(offset_ms), e);
+            continue;
+        }
+        for mut segment in decoded.lock().drain(..).filter(|s| !s.text.is_empty()) {
+            segment.index = segments.len() as i32;
+            segment.start_ms += offset_ms;
+            segment.end_ms += offset_ms;
+            segments.push(segment);
+        }
+        let transcribed = offset_ms + samples.len() as i64 / 16;
+        state.publish(Event::Status(tf("status.meeting", &[&clock(transcribed)])));
+    }
+    segments
+}
+
+/// Write the exports and add the transcript to the history
+fn save(
+    state: &AppState,
+    config: &Config,
+    started: u64,
+    duration_ms: i64,
+    segments: Vec<Segment>,
+    bookmarks: Vec<Bookmark>,
+) -> Result<Meeting> {
+    let chapters = chapters(config, &segments);
+    let dir = Config::meetings_dir()?;
+    let srt_path = dir.join(format!("meeting-{}.srt", started));
+    let markdown_path = dir.join(format!("meeting-{}.md", started));
+    std::fs::write(&srt_path, to_srt(&segments, &bookmarks, &chapters)).context("failed to write the srt export")?;
+    let pause_ms = config.output.paragraph_pause_ms as i64;
+    let markdown = to_markdown(&segments, &bookmarks, &chapters, started, duration_ms, pause_ms);
+    std::fs::write(&markdown_path, markdown).context("failed to write the markdown export")?;
+
+    let transcript = timeline(&segments, &bookmarks, &chapters)
+        .map(|line| match line {
+            Line::Said(s) => format!("[{}] {}", clock(s.start_ms), s.text),
+            Line::Marked(b) => format!("[{}] {}", clock(b.at_ms), marker(b)),
+            Line::Chapter(c) => format!("[{}] {}", clock(c.start_ms), heading(c)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    state.add_history(HistoryEntry::new(&transcript, &config.language, "meeting"));
+
+    Ok(Meeting {
+        started,
+        duration_ms,
+        segments,
+        bookmarks,
+        chapters,
+        srt_path,
+        markdown_path,
+    })
+}
+
+/// The chapters of `[meeting].chapters`, none when it's off or the meeting is empty
+fn chapters(config: &Config, segments: &[Segment]) -> Vec<Chapter> {
+    let pause_ms = config.meeting.chapter_pause_ms.max(1) as i64;
+    match config.meeting.chapters {
+        ChapterMode::Off => Vec::new(),
+        ChapterMode::Pauses => at_pauses(segments, pause_ms),
+        ChapterMode::Llm if segments.is_empty() => Vec::new(),
+        ChapterMode::Llm => {
+            let lines: Vec<String> = segments.iter().map(|s| s.text.clone()).collect();
+            // the recording thread isn't on the runtime, it can wait for the llm here
+            match tauri::async_runtime::block_on(llm::chapters(&config.llm, &lines)) {
+                Ok(found) => found
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (index, title))| Chapter {
+                        // whatever comes before the first chapter belongs to it
+                        start_ms: segments[if i == 0 { 0 } else { index }].start_ms,
+                        title,
+                    })
+                    .collect(),
+                Err(e) => {
+                    warn!("llm chaptering failed, splitting at pauses instead: {:#}", e);
+                    at_pauses(segments, pause_ms)
+                }
+            }
+        }
+    }
+}
+
+/// A chapter at the start and after every pause of `pause_ms`, titled by its first words
+fn at_pauses(segments: &[Segment], pause_ms: i64) -> Vec<Chapter> {
+    let mut previous_end = None;
+    let mut chapters = Vec::new();
+    for segment in segments {
+        if previous_end.map_or(true, |end| segment.start_ms - end >= pause_ms) {
+            chapters.push(Chapter {
+                start_ms: segment.start_ms,
+                title: first_words(&segment.text, 6),
+            });
+        }
+        previous_end = Some(segment.end_ms);
+    }
+    chapters
+}
+
+fn first_words(text: &str, count: usize) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let title = words[..words.len().min(count)].join(" ");
+    let title = title.trim_end_matches(|c: char| c.is_ascii_punctuation());
+    if words.len() > count {
+        format!("{}…", title)
+    } else {
+        title.to_string()
+    }
+}
+
+enum Line<'a> {
+    Said(&'a Segment),
+    Marked(&'a Bookmark),
+    Chapter(&'a Chapter),
+}
+
+/// Chapters, segments and bookmarks in the order they happened
+fn timeline<'a>(
+    segments: &'a [Segment],
+    bookmarks: &'a [Bookmark],
+    chapters: &'a [Chapter],
+) -> impl Iterator<Item = Line<'a>> {
+    let mut lines: Vec<(i64, Line)> = chapters.iter().map(|c| (c.start_ms, Line::Chapter(c))).collect();
+    lines.extend(segments.iter().map(|s| (s.start_ms, Line::Said(s))));
+    lines.extend(bookmarks.iter().map(|b| (b.at_ms, Line::Marked(b))));
+    // stable, so a chapter opens before what was said at the same moment
+    // and a bookmark comes after it
+    lines.sort_by_key(|(at, _)| *at);
+    lines.into_iter().map(|(_, line)| line)
+}
+
+fn marker(bookmark: &Bookmark) -> String {
+    match &bookmark.label {
+        Some(label) => format!("★ BOOKMARK: {}", label),
+        None => "★ BOOKMARK".to_string(),
+    }
+}
+
+fn heading(chapter: &Chapter) -> String {
+    format!("▶ CHAPTER: {}", chapter.title)
+}
+
+/// Bookmarks and chapter titles are cues of their own, shown for two seconds
+pub fn to_srt(segments: &[Segment], bookmarks: &[Bookmark], chapters: &[Chapter]) -> String {
+    let timestamp = |ms: i64| format!("{},{:03}", clock(ms), ms % 1000);
+    timeline(segments, bookmarks, chapters)
+        .map(|line| match line {
+            Line::Said(s) => (s.start_ms, s.end_ms, s.text.clone()),
+            Line::Marked(b) => (b.at_ms, b.at_ms + 2000, marker(b)),
+            Line::Chapter(c) => (c.start_ms, c.start_ms + 2000, heading(c)),
+        })
+        .enumerate()
+        .map(|(i, (start, end, text))| format!("{}\n{} --> {}\n{}\n", i + 1, timestamp(start), timestamp(end), text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One timestamped paragraph per segment, or per stretch without a pause of `pause_ms` when set
+pub fn to_markdown(
+    segments: &[Segment],
+    bookmarks: &[Bookmark],
+    chapters: &[Chapter],
+    started: u64,
+    duration_ms: i64,
+    pause_ms: i64,
+) -> String {
+    let mut markdown = format!("# Meeting {}\n\nDuration: {}\n\n", date(started), clock(duration_ms));
+    if !bookmarks.is_empty() {
+        markdown.push_str("## Bookmarks\n\n");
+        for bookmark in bookmarks {
+            let label = bookmark.label.as_deref().unwrap_or("(no label)");
+            markdown.push_str(&format!("- [{}] {}\n", clock(bookmark.at_ms), label));
+        }
+        markdown.push('\n');
+    }
+    if !chapters.is_empty() {
+        markdown.push_str("## Chapters\n\n");
+        for chapter in chapters {
+            markdown.push_str(&format!("- [{}] {}\n", clock(chapter.start_ms), chapter.title));
+        }
+        markdown.push('\n');
+    }
+    if !bookmarks.is_empty() || !chapters.is_empty() {
+        markdown.push_str("## Transcript\n\n");
+    }
+    let mut previous_end = None;
+    for line in timeline(segments, bookmarks, chapters) {
+        match line {
+            Line::Said(s) => {
+                let continues = pause_ms > 0 && previous_end.is_some_and(|end| s.start_ms - end < pause_ms);
+                if continues {
+                    // back into the paragraph, before its blank line
+                    markdown.truncate(markdown.len() - 2);
+                    markdown.push_str(&format!(" {}\n\n", s.text));
+                } else {
+                    markdown.push_str(&format!("**[{}]** {}\n\n", clock(s.start_ms), s.text));
+                }
+                previous_end = Some(s.end_ms);
+            }
+            Line::Marked(b) => {
+                markdown.push_str(&format!("> **[{}] {}**\n\n", clock(b.at_ms), marker(b)));
+                previous_end = None;
+            }
+            Line::Chapter(c) => {
+                markdown.push_str(&format!("### [{}] {}\n\n", clock(c.start_ms), c.title));
+                previous_end = None;
+            }
+        }
+    }
+    markdown
+}
+
+/// hh:mm:ss
+fn clock(ms: i64) -> String {
+    let secs = ms / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// "2024-05-01 14:30 UTC" for unix seconds
+fn date(unix: u64) -> String {
+    // days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let days = (unix / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let minutes = unix % 86400 / 60;
+    format!("{}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, minutes / 60, minutes % 60)
+}
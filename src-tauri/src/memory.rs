@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::config::{Config, LowMemoryPolicy};
+use crate::{get_model_path, HardwareDetector, Transcriber, WhisperiaError};
+
+// smallest last, a downgrade walks down from the configured model
+const WHISPER_MODELS: [&str; 5] = ["large", "medium", "small", "base", "tiny"];
+
+// whisper needs the weights plus a few hundred mb of compute buffers
+const OVERHEAD_BYTES: u64 = 300 * 1024 * 1024;
+
+/// The model to load for this dictation: the configured one when it fits in the
+/// available memory, otherwise a smaller installed one (or an error, with
+/// `model.on_low_memory = "fail"`). Returns the name of the model it fell back to.
+pub fn pick_model(config: &Config) -> Result<(PathBuf, Option<String>), WhisperiaError> {
+    let path = get_model_path(config).map_err(WhisperiaError::model_missing)?;
+    if Transcriber::is_loaded(&path) {
+        return Ok((path, None));
+    }
+
+    // loaded models are unloaded to make room, so they count as free
+    let available = HardwareDetector::new()
+        .map(|detector| detector.available_memory_bytes())
+        .unwrap_or(u64::MAX)
+        .saturating_add(Transcriber::loaded_bytes());
+    if required(&path) <= available {
+        return Ok((path, None));
+    }
+
+    let message = format!(
+        "model '{}' needs about {} mb, only {} mb available",
+        config.model.local_model,
+        required(&path) / 1024 / 1024,
+        available / 1024 / 1024
+    );
+    if config.model.on_low_memory == LowMemoryPolicy::Fail {
+        return Err(WhisperiaError::LowMemory(message));
+    }
+
+    let smaller = WHISPER_MODELS
+        .iter()
+        .skip_while(|name| **name != config.model.local_model.trim_end_matches(".en"))
+        .skip(1)
+        .filter_map(|name| {
+            let mut candidate = config.clone();
+            candidate.model.local_model = name.to_string();
+            get_model_path(&candidate).ok().map(|path| (name, path))
+        })
+        .find(|(_, path)| required(path) <= available);
+
+    match smaller {
+        Some((name, path)) => {
+            warn!("{}, falling back to '{}'", message, name);
+            Ok((path, Some(name.to_string())))
+        }
+        None => {
+            info!("{} and no smaller model is installed", message);
+            Err(WhisperiaError::LowMemory(message))
+        }
+    }
+}
+
+/// Whisper models with a file in the models dir, largest first
+pub fn installed_models(config: &Config) -> Vec<&'static str> {
+    WHISPER_MODELS
+        .iter()
+        .copied()
+        .filter(|name| {
+            let mut candidate = config.clone();
+            candidate.model.local_model = name.to_string();
+            get_model_path(&candidate).is_ok()
+        })
+        .collect()
+}
+
+fn required(path: &Path) -> u64 {
+    let weights = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    weights + OVERHEAD_BYTES
+}
@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::{Config, SendTarget};
+
+/// Post the transcript to a configured messaging target
+pub async fn send(target: &SendTarget, text: &str) -> Result<()> {
+    Config::ensure_online("sending transcripts")?;
+    info!("sending transcript to {}", target.name());
+
+    let client = reqwest::Client::new();
+    let request = match target {
+        SendTarget::Telegram {
+            bot_token, chat_id, ..
+        } => client
+            .post(format!("https://api.telegram.org/bot{}/sendMessage", bot_token))
+            .json(&serde_json::json!({ "chat_id": chat_id, "text": text })),
+        SendTarget::Slack { webhook_url, .. } => client
+            .post(webhook_url)
+            .json(&serde_json::json!({ "text": text })),
+    };
+
+    let response = request
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .with_context(|| format!("failed to reach {}", target.name()))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} rejected the message ({}): {}", target.name(), status, body);
+    }
+
+    Ok(())
+}
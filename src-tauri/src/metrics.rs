@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Timings of one dictation, for comparing models on this machine
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunMetrics {
+    pub model: String,
+    pub backend: String, // cpu, cuda, vulkan or metal
+    pub audio_ms: u64,
+    pub capture_ms: u64,
+    pub resample_ms: u64,
+    pub model_load_ms: u64, // ~0 when the model was already loaded
+    pub decode_ms: u64,
+    pub real_time_factor: f64, // decode time / audio length, below 1 is faster than real time
+    pub typing_ms: u64,
+    pub words: usize,
+    pub words_per_minute: f64, // typing speed of the inserted text
+}
+
+impl RunMetrics {
+    pub fn set_audio(&mut self, samples: usize, sample_rate: u32) {
+        self.audio_ms = samples as u64 * 1000 / sample_rate.max(1) as u64;
+    }
+
+    pub fn set_decode(&mut self, decode: Duration) {
+        self.decode_ms = millis(decode);
+        if self.audio_ms > 0 {
+            self.real_time_factor = self.decode_ms as f64 / self.audio_ms as f64;
+        }
+    }
+
+    pub fn set_typing(&mut self, text: &str, typing: Duration) {
+        self.typing_ms = millis(typing);
+        self.words = text.split_whitespace().count();
+        if self.typing_ms > 0 {
+            self.words_per_minute = self.words as f64 * 60_000.0 / self.typing_ms as f64;
+        }
+    }
+}
+
+pub fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// Running totals since the app started, plus the last run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Metrics {
+    pub runs: u64,
+    pub last: Option<RunMetrics>,
+    pub audio_ms_total: u64,
+    pub capture_ms_total: u64,
+    pub resample_ms_total: u64,
+    pub model_load_ms_total: u64,
+    pub decode_ms_total: u64,
+    pub typing_ms_total: u64,
+    pub words_total: u64,
+    pub real_time_factor_avg: f64,
+}
+
+impl Metrics {
+    pub fn record(&mut self, run: RunMetrics) {
+        self.runs += 1;
+        self.audio_ms_total += run.audio_ms;
+        self.capture_ms_total += run.capture_ms;
+        self.resample_ms_total += run.resample_ms;
+        self.model_load_ms_total += run.model_load_ms;
+        self.decode_ms_total += run.decode_ms;
+        self.typing_ms_total += run.typing_ms;
+        self.words_total += run.words as u64;
+        if self.audio_ms_total > 0 {
+            self.real_time_factor_avg = self.decode_ms_total as f64 / self.audio_ms_total as f64;
+        }
+        self.last = Some(run);
+    }
+
+    /// Prometheus text exposition format, served at `/metrics`
+    pub fn prometheus(&self) -> String {
+        let seconds = |ms: u64| ms as f64 / 1000.0;
+        let mut metrics = vec![
+            ("runs_total", "counter", "Dictations finished", self.runs as f64),
+            ("audio_seconds_total", "counter", "Audio recorded", seconds(self.audio_ms_total)),
+            ("capture_seconds_total", "counter", "Time spent capturing audio", seconds(self.capture_ms_total)),
+            ("resample_seconds_total", "counter", "Time spent resampling to 16khz", seconds(self.resample_ms_total)),
+            ("model_load_seconds_total", "counter", "Time spent loading whisper models", seconds(self.model_load_ms_total)),
+            ("decode_seconds_total", "counter", "Time spent decoding with whisper", seconds(self.decode_ms_total)),
+            ("typing_seconds_total", "counter", "Time spent typing results", seconds(self.typing_ms_total)),
+            ("words_typed_total", "counter", "Words typed", self.words_total as f64),
+        ];
+        if let Some(last) = &self.last {
+            metrics.push(("last_real_time_factor", "gauge", "Decode time over audio length of the last run", last.real_time_factor));
+            metrics.push(("last_words_per_minute", "gauge", "Typing speed of the last run", last.words_per_minute));
+        }
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP whisperia_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE whisperia_{} {}", name, kind);
+            let _ = writeln!(out, "whisperia_{} {}", name, value);
+        }
+        out
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::config::{Config, LanguageModel, LlmConfig, ModelConfig};
+
+const WHISPER_MODELS_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+// sizes with an english-only build, ggml-<size>.en.bin
+pub const ENGLISH_ONLY: [&str; 4] = ["tiny", "base", "small", "medium"];
+
+// every size `download_model` fetches from upstream, besides the .en builds
+const WHISPER_SIZES: [&str; 5] = ["tiny", "base", "small", "medium", "large"];
+
+/// File name of a whisper model in the models dir, same layout as download-quantized.sh
+pub fn whisper_model_file(model: &str, quantized: bool) -> String {
+    if quantized {
+        format!("ggml-{}-q5_0.bin", model)
+    } else {
+        format!("ggml-{}.bin", model)
+    }
+}
+
+pub fn whisper_model_url(file_name: &str) -> String {
+    format!("{}/{}", WHISPER_MODELS_URL, file_name)
+}
+
+/// The model made for `language` to use instead of `model`: a configured pack
+/// for the language, or the `.en` build of the same size for english
+pub fn language_variant(config: &ModelConfig, model: &str, language: &str) -> Option<String> {
+    if let Some(pack) = config.language_models.iter().find(|pack| pack.language == language) {
+        return Some(pack.name.clone());
+    }
+    (language == "en" && ENGLISH_ONLY.contains(&model)).then(|| format!("{}.en", model))
+}
+
+pub fn language_pack<'a>(config: &'a ModelConfig, model: &str) -> Option<&'a LanguageModel> {
+    config.language_models.iter().find(|pack| pack.name == model)
+}
+
+pub fn pack_file(pack: &LanguageModel) -> &str {
+    pack.url.rsplit('/').next().unwrap_or(&pack.name)
+}
+
+/// A size upstream has, its .en build, or a configured language pack. Anything
+/// else would end up in a url and a path, so `download_model` refuses it.
+pub fn is_known_model(config: &ModelConfig, model: &str) -> bool {
+    WHISPER_SIZES.contains(&model)
+        || model.strip_suffix(".en").is_some_and(|size| ENGLISH_ONLY.contains(&size))
+        || language_pack(config, model).is_some()
+}
+
+/// Download url and file name of a whisper model, a language pack or one of ggerganov's
+pub fn whisper_model_source(config: &ModelConfig, model: &str) -> (String, String) {
+    if let Some(pack) = language_pack(config, model) {
+        return (pack.url.clone(), pack_file(pack).to_string());
+    }
+    // upstream only has a q5_0 build of medium.en, the other .en models come full size
+    let quantized = config.use_quantized && (!model.ends_with(".en") || model == "medium.en");
+    let file_name = whisper_model_file(model, quantized);
+    (whisper_model_url(&file_name), file_name)
+}
+
+/// `local_model` is either a direct .gguf url or a file already in the models dir
+pub fn llm_model_file(config: &LlmConfig) -> &str {
+    config
+        .local_model
+        .rsplit('/')
+        .next()
+        .unwrap_or(&config.local_model)
+}
+
+#[cfg_attr(not(feature = "local-llm"), allow(dead_code))]
+pub fn llm_model_path(config: &LlmConfig) -> Result<PathBuf> {
+    let path = Config::models_dir()?.join(llm_model_file(config));
+    if !path.exists() {
+        anyhow::bail!(
+            "Local LLM model {:?} not found, download it from the settings first",
+            path
+        );
+    }
+    Ok(path)
+}
+
+/// Stream `url` into the models dir. Goes through a .part file so an interrupted
+/// download is never mistaken for a model.
+pub async fn download(
+    url: &str,
+    file_name: &str,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<PathBuf> {
+    // pack and llm file names come from urls in the config, keep them in the models dir
+    if file_name.is_empty() || file_name.contains(['/', '\\']) || file_name.contains("..") {
+        anyhow::bail!("not a model file name: {:?}", file_name);
+    }
+    let models_dir = Config::models_dir()?;
+    let path = models_dir.join(file_name);
+    if path.exists() {
+        info!("model {} already downloaded", file_name);
+        return Ok(path);
+    }
+
+    Config::ensure_online("downloading models")?;
+    let url = &Config::hub_url(url);
+    info!("downloading {} from {}", file_name, url);
+    let mut response = Config::hub_client()?
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("failed to reach {}", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("download of {} failed: {}", file_name, response.status());
+    }
+
+    let total = response.content_length();
+    let part_path = models_dir.join(format!("{}.part", file_name));
+    let mut file = std::fs::File::create(&part_path)
+        .with_context(|| format!("failed to create {:?}", part_path))?;
+
+    let mut downloaded = 0u64;
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+    file.flush()?;
+    drop(file);
+
+    std::fs::rename(&part_path, &path)?;
+    info!("model {} downloaded ({} bytes)", file_name, downloaded);
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_known_models_are_downloaded() {
+        let config = Config::default().model;
+        for model in ["tiny", "base.en", "medium.en", "large"] {
+            assert!(is_known_model(&config, model), "{}", model);
+        }
+        for model in ["", "large.en", "huge", "../tiny", "tiny/../../x", "..\\tiny"] {
+            assert!(!is_known_model(&config, model), "{}", model);
+        }
+    }
+}
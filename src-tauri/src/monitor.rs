@@ -0,0 +1,133 @@
+//! Monitor mode: the microphone played back on an output device while
+//! recording, so users with hearing aids or noisy mics hear what whisper will
+//! get. Only a few milliseconds are buffered; when playback falls behind the
+//! oldest audio is dropped rather than letting the delay grow.
+
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::SampleFormat;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::MonitorConfig;
+
+/// Playback of the microphone, stops when dropped
+pub struct Monitor {
+    _stream: cpal::Stream,
+    sink: Sink,
+}
+
+/// Where the input callback pushes what it captured
+#[derive(Clone)]
+pub struct Sink {
+    state: Arc<Mutex<SinkState>>,
+    channels: usize, // of the input
+    ratio: f64,      // output samples per input sample
+    gain: f32,
+    max_buffered: usize,
+}
+
+struct SinkState {
+    buffer: VecDeque<f32>,
+    phase: f64,
+}
+
+impl Sink {
+    /// Queue interleaved input samples for playback, mono at the output rate
+    pub fn push(&self, data: &[f32]) {
+        let mut state = self.state.lock();
+        for frame in data.chunks(self.channels.max(1)) {
+            let sample = frame.iter().sum::<f32>() / frame.len() as f32 * self.gain;
+            // nearest-sample resampling is plenty for listening in
+            state.phase += self.ratio;
+            while state.phase >= 1.0 {
+                state.buffer.push_back(sample.clamp(-1.0, 1.0));
+                state.phase -= 1.0;
+            }
+        }
+        let excess = state.buffer.len().saturating_sub(self.max_buffered);
+        state.buffer.drain(..excess);
+    }
+}
+
+impl Monitor {
+    /// Start playing back an input of `input_rate` hz and `input_channels`
+    /// on `config.device` (the default output when unset)
+    pub fn start(config: &MonitorConfig, input_rate: u32, input_channels: u16) -> Result<Self> {
+        let host = cpal::default_host();
+        let device = match &config.device {
+            Some(name) => {
+                let wanted = name.to_lowercase();
+                host.output_devices()?
+                    .find(|device| {
+                        device
+                            .name()
+                            .map(|n| n.to_lowercase().contains(&wanted))
+                            .unwrap_or(false)
+                    })
+                    .with_context(|| format!("no output device matching '{}'", name))?
+            }
+            None => host
+                .default_output_device()
+                .context("no output device available")?,
+        };
+
+        let output = device.default_output_config()?;
+        let sample_format = output.sample_format();
+        let output: cpal::StreamConfig = output.config();
+        let out_channels = output.channels.max(1) as usize;
+        let out_rate = output.sample_rate.0;
+
+        let sink = Sink {
+            state: Arc::new(Mutex::new(SinkState {
+                buffer: VecDeque::new(),
+                phase: 0.0,
+            })),
+            channels: input_channels as usize,
+            ratio: out_rate as f64 / input_rate as f64,
+            gain: config.gain,
+            max_buffered: (out_rate as u64 * config.latency_ms / 1000).max(1) as usize,
+        };
+
+        let err_fn = move |err| {
+            eprintln!("monitor stream error: {}", err);
+        };
+        let state = sink.state.clone();
+        let stream = match sample_format {
+            SampleFormat::F32 => device.build_output_stream(
+                &output,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut state = state.lock();
+                    for frame in data.chunks_mut(out_channels) {
+                        frame.fill(state.buffer.pop_front().unwrap_or(0.0));
+                    }
+                },
+                err_fn,
+                None,
+            )?,
+            SampleFormat::I16 => device.build_output_stream(
+                &output,
+                move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                    let mut state = state.lock();
+                    for frame in data.chunks_mut(out_channels) {
+                        let sample = state.buffer.pop_front().unwrap_or(0.0);
+                        frame.fill((sample * i16::MAX as f32) as i16);
+                    }
+                },
+                err_fn,
+                None,
+            )?,
+            _ => anyhow::bail!("unsupported output sample format"),
+        };
+        stream.play()?;
+
+        info!("monitoring the microphone on {:?}", device.name()?);
+        Ok(Self { _stream: stream, sink })
+    }
+
+    pub fn sink(&self) -> Sink {
+        self.sink.clone()
+    }
+}
@@ -0,0 +1,95 @@
+use rumqttc::{AsyncClient, LastWill, MqttOptions, QoS};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::{Config, MqttConfig};
+use crate::events::{self, Event, TranscriptEvent};
+
+/// Connect to the broker and publish state changes and final transcripts
+pub fn spawn(
+    config: MqttConfig,
+    mut events: broadcast::Receiver<Event>,
+    shutdown: CancellationToken,
+) {
+    if let Err(e) = Config::ensure_online("the mqtt publisher") {
+        warn!("{:#}", e);
+        return;
+    }
+    info!("MQTT publisher connecting to {}:{}", config.host, config.port);
+
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        &config.state_topic,
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 16);
+
+    // the event loop has to be polled for anything to be sent; it reconnects
+    // on its own after an error. After shutdown it runs until the disconnect.
+    let poll_shutdown = shutdown.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = eventloop.poll().await {
+                if poll_shutdown.is_cancelled() {
+                    break;
+                }
+                warn!("MQTT connection error: {}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    });
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_state = "idle";
+        publish(&client, &config.state_topic, last_state, true).await;
+
+        loop {
+            tokio::select! {
+                Some(event) = events::next(&mut events) => match event {
+                    Event::State(status) => {
+                        let state = status.pipeline.name();
+                        if state != last_state {
+                            last_state = state;
+                            publish(&client, &config.state_topic, state, true).await;
+                        }
+                    }
+                    Event::Transcript(event @ TranscriptEvent::Final { .. }) => {
+                        if let Ok(payload) = serde_json::to_string(&event) {
+                            publish(&client, &config.transcript_topic, &payload, false).await;
+                        }
+                    }
+                    _ => {}
+                },
+                _ = shutdown.cancelled() => {
+                    publish(&client, &config.state_topic, "offline", true).await;
+                    let _ = client.disconnect().await;
+                    info!("MQTT publisher stopped");
+                    break;
+                }
+                else => break,
+            }
+        }
+    });
+}
+
+async fn publish(client: &AsyncClient, topic: &str, payload: &str, retain: bool) {
+    // offline mode switched on since the publisher started
+    if Config::ensure_online("the mqtt publisher").is_err() {
+        return;
+    }
+    if let Err(e) = client
+        .publish(topic, QoS::AtLeastOnce, retain, payload.as_bytes().to_vec())
+        .await
+    {
+        warn!("MQTT publish to {} failed: {}", topic, e);
+    }
+}
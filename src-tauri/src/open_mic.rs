@@ -0,0 +1,116 @@
+//! Open mic: hands-free dictation without a wake word. The microphone stays
+//! open and every utterance the VAD finds is transcribed and typed as soon as
+//! it ends, until open mic is toggled off. Silence never reaches whisper.
+
+use anyhow::Result;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::vad::Vad;
+use crate::{
+    memory, permissions, postprocess, tuning, AppState, AudioRecorder, Event, HistoryEntry,
+    InputSimulator, Transcriber, Transcript, TranscriptEvent, WhisperiaError,
+};
+
+// how often captured audio is picked up from the device
+const POLL: Duration = Duration::from_millis(100);
+
+/// Start listening, does nothing when already listening
+pub fn start(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let Some(cancel) = state.begin_open_mic() else {
+        return;
+    };
+    info!("open mic on");
+    state.publish(Event::State(state.get_status()));
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        if let Err(e) = listen(&app, &cancel) {
+            error!("open mic failed: {:#}", e);
+            state.publish(Event::Error(WhisperiaError::from(e)));
+        }
+        if !cancel.is_cancelled() {
+            stop(&state);
+        }
+    });
+}
+
+pub fn stop(state: &AppState) {
+    if state.stop_open_mic() {
+        info!("open mic off");
+        state.publish(Event::State(state.get_status()));
+    }
+}
+
+fn listen(app: &AppHandle, cancel: &CancellationToken) -> Result<()> {
+    let state = app.state::<AppState>();
+    let config = state.get_config();
+
+    let (model_path, _) = memory::pick_model(&config)?;
+    let transcriber = Transcriber::cached(&model_path, config.model.cached_models)?
+        .with_threads(tuning::threads(&model_path, config.model.threads))
+        .with_paragraphs(config.output.paragraph_pause_ms)
+        .with_translate(config.model.translate);
+    let recorder = AudioRecorder::with_device(config.open_mic.source.as_deref())?;
+    let rate = recorder.sample_rate();
+
+    let mut vad = Vad::new(&config.open_mic);
+
+    recorder.stream_until_stopped(cancel, POLL, |samples| {
+        // a hotkey dictation has the microphone for now
+        if state.get_status().pipeline.is_busy() {
+            vad.reset();
+            return;
+        }
+        for utterance in vad.push(&AudioRecorder::resample(&samples, rate, 16000)) {
+            if let Err(e) = type_utterance(&state, &transcriber, &config, &utterance, cancel) {
+                if cancel.is_cancelled() {
+                    return;
+                }
+                warn!("open mic dictation failed: {:#}", e);
+                state.publish(Event::Error(WhisperiaError::from(e)));
+            }
+        }
+    })
+}
+
+fn type_utterance(
+    state: &AppState,
+    transcriber: &Transcriber,
+    config: &Config,
+    audio: &[f32],
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let Transcript { text, words, confidence } = transcriber
+        .transcribe_scored(audio, &config.language, cancel, |_| {})
+        .map_err(WhisperiaError::decode_failed)?;
+    let text = postprocess::process(text.trim(), config, None);
+    if text.is_empty() {
+        return Ok(());
+    }
+    info!("open mic heard {} words", text.split_whitespace().count());
+
+    permissions::ensure_accessibility()?;
+    // turned off while the utterance was decoded
+    if cancel.is_cancelled() {
+        return Ok(());
+    }
+    // a space after each utterance so the next one doesn't run into it
+    InputSimulator::new()
+        .and_then(|mut input| input.insert(&format!("{} ", text), config.output.insert))
+        .map_err(WhisperiaError::input_blocked)?;
+
+    state.add_history(HistoryEntry::new(&text, &config.language, "open_mic").with_confidence(confidence));
+    state.publish(Event::Transcript(TranscriptEvent::Final {
+        text,
+        language: config.language.clone(),
+        words,
+        confidence,
+    }));
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Chunk sizes for the continuous modes (live captions, meetings) that follow
+//! how fast this machine decodes. Whisper pads every chunk to its 30 second
+//! window, so a decode takes about as long for a few seconds of audio as for
+//! the whole window: a machine too slow for short chunks keeps up with longer
+//! ones. The chunk is stretched until decoding it takes `TARGET_RTF` of its
+//! length, and shrinks back to the configured size once decoding is fast again.
+
+use std::time::Duration;
+use tracing::info;
+
+// decode time / audio time the chunk size aims for, leaving room for the recording
+const TARGET_RTF: f32 = 0.5;
+// whisper's window, longer chunks are decoded in several passes anyway
+const MAX_CHUNK_SECS: usize = 30;
+// weight of the newest decode in the average
+const SMOOTHING: f32 = 0.3;
+
+pub struct Pace {
+    base: usize,  // the configured chunk, in samples at 16khz
+    chunk: usize,
+    decode_secs: Option<f32>, // average time a decode takes
+    adaptive: bool,
+}
+
+impl Pace {
+    pub fn new(chunk_secs: u64, adaptive: bool) -> Self {
+        let base = chunk_secs as usize * 16000;
+        Self {
+            base,
+            chunk: base,
+            decode_secs: None,
+            adaptive,
+        }
+    }
+
+    /// Samples at 16khz to decode at a time
+    pub fn chunk(&self) -> usize {
+        self.chunk
+    }
+
+    /// Time between decodes of the same audio, at least `configured` and long
+    /// enough that decoding takes no more than `TARGET_RTF` of the time
+    pub fn interval(&self, configured: Duration) -> Duration {
+        match self.decode_secs.filter(|_| self.adaptive) {
+            Some(secs) => configured.max(Duration::from_secs_f32(secs / TARGET_RTF)),
+            None => configured,
+        }
+    }
+
+    /// Measure a decode of `samples` that took `took`, resizing the chunk
+    pub fn record(&mut self, samples: usize, took: Duration) {
+        if !self.adaptive || samples == 0 {
+            return;
+        }
+        let took = took.as_secs_f32();
+        let decode_secs = self.decode_secs.map_or(took, |average| average + SMOOTHING * (took - average));
+        self.decode_secs = Some(decode_secs);
+
+        // whole seconds, so the chunk doesn't change with every decode
+        let wanted = (decode_secs / TARGET_RTF).ceil() as usize * 16000;
+        let chunk = wanted.min(MAX_CHUNK_SECS * 16000).max(self.base);
+        if chunk != self.chunk {
+            let rtf = took / (samples as f32 / 16000.0);
+            info!(
+                "real-time factor {:.2}, chunks of {:.0}s instead of {:.0}s",
+                rtf,
+                chunk as f32 / 16000.0,
+                self.chunk as f32 / 16000.0
+            );
+            self.chunk = chunk;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Password guard: dictated text is never typed into a password field. Where
+//! the platform tells, the focused control itself is checked (UI Automation
+//! on Windows, the Accessibility API on macOS). Linux has no AT-SPI client
+//! here, so the focused window (hyprland/sway) is matched against the usual
+//! password prompts instead. A guarded dictation goes to the clipboard, and
+//! pasting it is the user's confirmation.
+
+use crate::compositor::FocusedWindow;
+
+// app ids / window classes of password prompts, lowercase substrings
+#[cfg_attr(any(target_os = "windows", target_os = "macos"), allow(dead_code))]
+const PROMPTS: [&str; 6] = ["pinentry", "askpass", "gcr-prompter", "polkit", "policykit", "kwalletd"];
+
+/// Whether the control that would receive the text takes a password
+pub fn focused_is_password(window: Option<&FocusedWindow>) -> bool {
+    platform::focused_is_password(window)
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::FocusedWindow;
+
+    pub fn focused_is_password(_window: Option<&FocusedWindow>) -> bool {
+        crate::win32::focused_is_password()
+            .map_err(|e| tracing::warn!("failed to check the focused control: {:#}", e))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+    use core_foundation::string::{CFString, CFStringRef};
+    use std::ffi::c_void;
+
+    use super::FocusedWindow;
+
+    type AXUIElementRef = *const c_void;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+        fn AXUIElementCopyAttributeValue(
+            element: AXUIElementRef,
+            attribute: CFStringRef,
+            value: *mut CFTypeRef,
+        ) -> i32;
+    }
+
+    /// An attribute of `element`, owned by the caller; `None` when it has none
+    unsafe fn attribute(element: AXUIElementRef, name: &str) -> Option<CFTypeRef> {
+        let name = CFString::new(name);
+        let mut value: CFTypeRef = std::ptr::null();
+        let error = AXUIElementCopyAttributeValue(element, name.as_concrete_TypeRef(), &mut value);
+        (error == 0 && !value.is_null()).then_some(value)
+    }
+
+    pub fn focused_is_password(_window: Option<&FocusedWindow>) -> bool {
+        unsafe {
+            let system = AXUIElementCreateSystemWide();
+            let focused = attribute(system, "AXFocusedUIElement");
+            CFRelease(system as CFTypeRef);
+            let Some(focused) = focused else {
+                return false;
+            };
+            let subrole = attribute(focused as AXUIElementRef, "AXSubrole");
+            CFRelease(focused);
+            subrole.is_some_and(|subrole| {
+                CFString::wrap_under_create_rule(subrole as CFStringRef).to_string() == "AXSecureTextField"
+            })
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::{FocusedWindow, PROMPTS};
+
+    pub fn focused_is_password(window: Option<&FocusedWindow>) -> bool {
+        window.is_some_and(|window| {
+            let app_id = window.app_id.to_lowercase();
+            PROMPTS.iter().any(|prompt| app_id.contains(prompt))
+        })
+    }
+}
@@ -0,0 +1,182 @@
+//! macOS privacy permissions: the microphone for recording and Accessibility for
+//! typing with enigo. Everywhere else both are always granted.
+
+use serde::Serialize;
+
+use crate::WhisperiaError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Granted,
+    Denied,
+    NotDetermined, // the system prompt hasn't been shown yet
+    NotRequired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionsStatus {
+    pub microphone: Permission,
+    pub accessibility: Permission,
+}
+
+pub fn status() -> PermissionsStatus {
+    PermissionsStatus {
+        microphone: platform::microphone(),
+        accessibility: platform::accessibility(),
+    }
+}
+
+/// Before recording: ask for the microphone the first time (blocks until the
+/// user answers the prompt), fail with a pointer to System Settings once denied
+pub fn ensure_microphone() -> Result<(), WhisperiaError> {
+    let granted = match platform::microphone() {
+        Permission::Granted | Permission::NotRequired => true,
+        Permission::NotDetermined => platform::request_microphone(),
+        Permission::Denied => false,
+    };
+    if granted {
+        Ok(())
+    } else {
+        Err(WhisperiaError::NoMic(
+            "microphone access denied, allow Whisperia in System Settings > Privacy & Security > Microphone"
+                .to_string(),
+        ))
+    }
+}
+
+/// Before typing: show the Accessibility prompt when the app isn't trusted yet
+pub fn ensure_accessibility() -> Result<(), WhisperiaError> {
+    match platform::accessibility() {
+        Permission::Granted | Permission::NotRequired => Ok(()),
+        _ if platform::request_accessibility() => Ok(()),
+        _ => Err(WhisperiaError::InputBlocked(
+            "accessibility access is needed to type, allow Whisperia in System Settings > Privacy & Security > Accessibility"
+                .to_string(),
+        )),
+    }
+}
+
+/// Open the System Settings pane for `permission` ("microphone" or "accessibility")
+pub fn open_settings(permission: &str) -> Result<(), WhisperiaError> {
+    let pane = match permission {
+        "microphone" => "Privacy_Microphone",
+        "accessibility" => "Privacy_Accessibility",
+        other => return Err(WhisperiaError::Invalid(format!("unknown permission: {}", other))),
+    };
+    platform::open_pane(pane)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use block2::RcBlock;
+    use core_foundation::base::TCFType;
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::{CFString, CFStringRef};
+    use objc2::runtime::{AnyClass, AnyObject, Bool};
+    use objc2::msg_send;
+    use std::sync::mpsc;
+    use tracing::info;
+
+    use super::Permission;
+    use crate::WhisperiaError;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVMediaTypeAudio: &'static AnyObject;
+    }
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+        fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> bool;
+        static kAXTrustedCheckOptionPrompt: CFStringRef;
+    }
+
+    fn capture_device() -> Option<&'static AnyClass> {
+        AnyClass::get(c"AVCaptureDevice")
+    }
+
+    pub fn microphone() -> Permission {
+        let Some(class) = capture_device() else {
+            return Permission::NotRequired;
+        };
+        // AVAuthorizationStatus: not determined, restricted, denied, authorized
+        let status: isize = unsafe { msg_send![class, authorizationStatusForMediaType: AVMediaTypeAudio] };
+        match status {
+            0 => Permission::NotDetermined,
+            3 => Permission::Granted,
+            _ => Permission::Denied,
+        }
+    }
+
+    pub fn request_microphone() -> bool {
+        let Some(class) = capture_device() else {
+            return true;
+        };
+        info!("requesting microphone access");
+        let (tx, rx) = mpsc::channel();
+        let handler = RcBlock::new(move |granted: Bool| {
+            let _ = tx.send(granted.as_bool());
+        });
+        unsafe {
+            let _: () = msg_send![
+                class,
+                requestAccessForMediaType: AVMediaTypeAudio,
+                completionHandler: &*handler
+            ];
+        }
+        rx.recv().unwrap_or(false)
+    }
+
+    pub fn accessibility() -> Permission {
+        if unsafe { AXIsProcessTrusted() } {
+            Permission::Granted
+        } else {
+            Permission::Denied
+        }
+    }
+
+    pub fn request_accessibility() -> bool {
+        info!("requesting accessibility access");
+        let prompt = unsafe { CFString::wrap_under_get_rule(kAXTrustedCheckOptionPrompt) };
+        let options = CFDictionary::from_CFType_pairs(&[(prompt, CFBoolean::true_value())]);
+        unsafe { AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef()) }
+    }
+
+    pub fn open_pane(pane: &str) -> Result<(), WhisperiaError> {
+        let url = format!("x-apple.systempreferences:com.apple.preference.security?{}", pane);
+        std::process::Command::new("open")
+            .arg(url)
+            .status()
+            .map_err(|e| WhisperiaError::Other(format!("failed to open System Settings: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::Permission;
+    use crate::WhisperiaError;
+
+    pub fn microphone() -> Permission {
+        Permission::NotRequired
+    }
+
+    pub fn request_microphone() -> bool {
+        true
+    }
+
+    pub fn accessibility() -> Permission {
+        Permission::NotRequired
+    }
+
+    pub fn request_accessibility() -> bool {
+        true
+    }
+
+    pub fn open_pane(_pane: &str) -> Result<(), WhisperiaError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a dictation is in the record → transcribe → insert pipeline
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum PipelineState {
+    #[default]
+    Idle,
+    Recording,
+    Transcribing,
+    Inserting,
+    Error { message: String },
+}
+
+impl PipelineState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Recording => "recording",
+            Self::Transcribing => "transcribing",
+            Self::Inserting => "inserting",
+            Self::Error { .. } => "error",
+        }
+    }
+
+    /// A dictation is in flight
+    pub fn is_busy(&self) -> bool {
+        matches!(self, Self::Recording | Self::Transcribing | Self::Inserting)
+    }
+
+    pub fn can_transition_to(&self, next: &PipelineState) -> bool {
+        use PipelineState::*;
+
+        matches!(
+            (self, next),
+            (Idle | Error { .. }, Recording)
+                | (Recording, Transcribing)
+                | (Transcribing, Inserting)
+                | (Inserting, Idle)
+                // cancelled, or routed somewhere else instead of typed
+                | (Recording | Transcribing, Idle)
+                | (Error { .. }, Idle)
+                | (_, Error { .. })
+        )
+    }
+}
+
+/// Error returned by a pipeline step that stopped because the run was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_allowed_transitions_pass() {
+        use PipelineState::*;
+        let states = [Idle, Recording, Transcribing, Inserting, Error { message: "failed".to_string() }];
+        // rows are the current state, columns the next one, in the order of `states`
+        let allowed = [
+            //   idle   rec    trans  ins    error
+            [false, true, false, false, true],  // idle
+            [true, false, true, false, true],   // recording
+            [true, false, false, true, true],   // transcribing
+            [true, false, false, false, true],  // inserting
+            [true, true, false, false, true],   // error
+        ];
+        for (from, row) in states.iter().zip(allowed) {
+            for (to, expected) in states.iter().zip(row) {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    expected,
+                    "{} -> {}",
+                    from.name(),
+                    to.name()
+                );
+            }
+        }
+    }
+}
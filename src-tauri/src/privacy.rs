@@ -0,0 +1,49 @@
+//! Who the operating system says is using the microphone. The capture goes
+//! through cpal, so whisperia names itself to the audio stack underneath: on
+//! linux PipeWire and PulseAudio (directly or behind ALSA) take the client's
+//! name and icon from the environment, and that's what the desktop's
+//! "microphone in use" indicator and per-app volume and privacy controls show.
+//! Windows attributes the capture to the process' AppUserModelID, the one the
+//! installer gave the start menu shortcut, and macOS to the bundle, whose
+//! Info.plist says what the microphone is for. Outside a flatpak there is no
+//! portal to register the capture with.
+
+const NAME: &str = "Whisperia";
+#[cfg(all(unix, not(target_os = "macos")))]
+const ICON: &str = "whisperia";
+
+/// Name the app before any audio client or window exists, `app_id` is the
+/// bundle identifier
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn register(app_id: &str) {
+    // what the user set themselves wins
+    if std::env::var_os("PULSE_PROP").is_none() {
+        let props = format!(
+            "application.name='{}' application.id='{}' application.icon_name='{}'",
+            NAME, app_id, ICON
+        );
+        std::env::set_var("PULSE_PROP", props);
+    }
+    if std::env::var_os("PIPEWIRE_PROPS").is_none() {
+        let props = format!(
+            "{{ application.name = \"{}\" application.id = \"{}\" application.icon-name = \"{}\" }}",
+            NAME, app_id, ICON
+        );
+        std::env::set_var("PIPEWIRE_PROPS", props);
+    }
+    tracing::info!("audio clients named {} ({})", NAME, app_id);
+}
+
+#[cfg(target_os = "windows")]
+pub fn register(app_id: &str) {
+    match crate::win32::set_app_user_model_id(app_id) {
+        Ok(()) => tracing::info!("running as {} ({})", NAME, app_id),
+        Err(e) => tracing::warn!("failed to set the app user model id: {:#}", e),
+    }
+}
+
+/// The bundle's Info.plist names the app, nothing to do at runtime
+#[cfg(target_os = "macos")]
+pub fn register(_app_id: &str) {
+    tracing::debug!("microphone use attributed to the {} bundle", NAME);
+}
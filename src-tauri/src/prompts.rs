@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::config::Config;
+
+/// A named LLM prompt stored as `<config dir>/prompts/<name>.txt`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub prompt: String,
+}
+
+fn template_path(name: &str) -> Result<PathBuf> {
+    // names end up in a file path, keep them boring
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!("invalid template name '{}', use letters, digits, - and _", name);
+    }
+    Ok(Config::prompts_dir()?.join(format!("{}.txt", name)))
+}
+
+pub fn list() -> Result<Vec<PromptTemplate>> {
+    let mut templates = Vec::new();
+    for entry in std::fs::read_dir(Config::prompts_dir()?)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        templates.push(PromptTemplate {
+            name: name.to_string(),
+            prompt: std::fs::read_to_string(&path)?,
+        });
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+pub fn load(name: &str) -> Result<String> {
+    let path = template_path(name)?;
+    std::fs::read_to_string(&path).with_context(|| format!("prompt template '{}' not found", name))
+}
+
+pub fn save(template: &PromptTemplate) -> Result<()> {
+    let path = template_path(&template.name)?;
+    info!("saving prompt template '{}'", template.name);
+    std::fs::write(&path, &template.prompt)
+        .with_context(|| format!("failed to write {:?}", path))
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    let path = template_path(name)?;
+    info!("deleting prompt template '{}'", name);
+    std::fs::remove_file(&path).with_context(|| format!("prompt template '{}' not found", name))
+}
@@ -0,0 +1,53 @@
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use tokio::sync::Notify;
+
+/// FIFO of pending work, drained by a single worker task
+pub struct JobQueue<T> {
+    jobs: Mutex<VecDeque<T>>,
+    ready: Notify,
+}
+
+impl<T> Default for JobQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> JobQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(VecDeque::new()),
+            ready: Notify::new(),
+        }
+    }
+
+    pub fn push(&self, job: T) {
+        self.jobs.lock().push_back(job);
+        self.ready.notify_one();
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.lock().len()
+    }
+
+    /// Drop everything still waiting, returns how many jobs were dropped
+    pub fn clear(&self) -> usize {
+        let mut jobs = self.jobs.lock();
+        let dropped = jobs.len();
+        jobs.clear();
+        dropped
+    }
+
+    /// Wait for the next job
+    pub async fn next(&self) -> T {
+        loop {
+            let job = self.jobs.lock().pop_front();
+            if let Some(job) = job {
+                return job;
+            }
+            // a push before we get here leaves a permit, so no wakeup is lost
+            self.ready.notified().await;
+        }
+    }
+}
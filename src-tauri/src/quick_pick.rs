@@ -0,0 +1,105 @@
+//! Quick pick: before a dictation starts recording, the overlay offers the
+//! installed models and a few languages for just that dictation, for the odd
+//! time the big model is needed without changing the defaults.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::config::Config;
+use crate::i18n::t;
+use crate::pipeline::Cancelled;
+use crate::{memory, AppState, Event, WhisperiaError};
+
+// offered besides the configured language
+const LANGUAGES: [&str; 3] = ["en", "pt", "auto"];
+
+/// What the overlay can offer, with the current defaults
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickOptions {
+    pub models: Vec<String>, // installed, largest first
+    pub languages: Vec<String>,
+    pub model: String,
+    pub language: String,
+    pub wait_ms: u64, // how long the overlay has before recording starts
+}
+
+/// The overlay's choice; nothing set records right away with the defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QuickPick {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl QuickPick {
+    fn apply(&self, config: &mut Config) {
+        if let Some(model) = &self.model {
+            config.model.local_model = model.clone();
+        }
+        if let Some(language) = &self.language {
+            config.language = language.clone();
+        }
+    }
+}
+
+pub fn options(config: &Config) -> QuickOptions {
+    QuickOptions {
+        models: memory::installed_models(config).into_iter().map(str::to_string).collect(),
+        languages: languages(config),
+        model: config.model.local_model.clone(),
+        language: config.language.clone(),
+        wait_ms: config.ui.quick_pick_ms,
+    }
+}
+
+/// The configured language first, then the profiles' and a few common ones
+pub fn languages(config: &Config) -> Vec<String> {
+    let mut languages = vec![config.language.clone()];
+    let others = config.profiles.iter().filter_map(|p| p.language.clone());
+    for language in others.chain(LANGUAGES.iter().map(|l| l.to_string())) {
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    languages
+}
+
+/// Check a choice from `select_quick_option` against what was offered
+pub fn validate(pick: &QuickPick, config: &Config) -> Result<(), WhisperiaError> {
+    match &pick.model {
+        Some(model) if !memory::installed_models(config).contains(&model.as_str()) => {
+            Err(WhisperiaError::Invalid(format!("model '{}' is not installed", model)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Offer the options on the overlay and wait up to `ui.quick_pick_ms` for a
+/// choice, applying it to `config`
+pub async fn offer(state: &AppState, config: &mut Config, cancel: &CancellationToken) -> anyhow::Result<()> {
+    let (tx, rx) = oneshot::channel();
+    state.set_quick_pick(Some(tx));
+    state.publish(Event::QuickOptions(options(config)));
+    state.publish(Event::Status(t("status.quick_pick")));
+
+    let wait = Duration::from_millis(config.ui.quick_pick_ms);
+    let picked = tokio::select! {
+        picked = tokio::time::timeout(wait, rx) => picked.ok().and_then(Result::ok),
+        _ = cancel.cancelled() => {
+            state.set_quick_pick(None);
+            return Err(Cancelled.into());
+        }
+    };
+    state.set_quick_pick(None);
+
+    if let Some(pick) = picked {
+        info!("quick pick: model {:?}, language {:?}", pick.model, pick.language);
+        pick.apply(config);
+    }
+    state.publish(Event::Status(t("status.recording")));
+    Ok(())
+}
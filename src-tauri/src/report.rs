@@ -0,0 +1,122 @@
+//! What happened in the last dictation, for answering "why was this one slow
+//! (or wrong)?" without reading the logs: the device and how its audio got to
+//! 16khz, what the VAD decided, which model decoded it with which settings,
+//! the timings and how it ended. Kept in `last_run.json` in the data directory
+//! so it survives a restart too.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::RunMetrics;
+
+/// The microphone and the way from its audio to whisper's
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioReport {
+    pub device: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+    pub resample: String, // "48000hz -> 16000hz", or "none" when the device records at 16khz
+    pub samples: usize,   // at 16khz
+    pub peak: f32,        // loudest sample, 0 to 1; near 0 is a muted or wrong microphone
+}
+
+/// The retry's utterance detection, see `retry.on_empty`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VadReport {
+    pub threshold: f32,
+    pub silence_ms: u64,
+    pub min_speech_ms: u64,
+    pub ended_by: String, // speech, time_limit or stopped
+}
+
+/// Who decoded and how
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DecodeReport {
+    pub decoder: String, // local, service or draft (of a two-pass run)
+    pub language: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_memory_fallback: Option<String>, // the model used instead of the configured one
+    pub cold_start: bool,                      // the model had to be loaded first
+    pub threads: usize,
+    pub sampling: String,
+    pub prompt_chars: usize, // hotwords handed to whisper as the initial prompt
+    #[serde(default)]
+    pub suppressed_tokens: usize, // ruled out by `model.decode` or the profile's rules
+    pub paragraph_pause_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+/// One dictation from the hotkey to the text, for `get_last_run_report`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunReport {
+    pub finished_at: u64, // unix seconds
+    pub audio: AudioReport,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vad: Vec<VadReport>,
+    pub retried: bool, // nothing was heard the first time
+    pub decode: DecodeReport,
+    pub metrics: RunMetrics,
+    pub outcome: String, // typed, routed, copied, nothing_heard...
+}
+
+impl RunReport {
+    pub fn new(model: &str) -> Self {
+        Self {
+            metrics: RunMetrics {
+                model: model.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn finish(&mut self, outcome: &str) {
+        self.outcome = outcome.to_string();
+        self.finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+    }
+}
+
+/// How audio recorded at `from` hz gets to whisper's 16khz
+pub fn resample_path(from: u32) -> String {
+    if from == 16000 {
+        "none".to_string()
+    } else {
+        format!("{}hz -> 16000hz", from)
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    Config::last_run_path()
+}
+
+/// Keep `report` as the last run, overwriting the one before
+pub fn save(report: &RunReport) {
+    let saved = path().and_then(|path| {
+        std::fs::write(&path, serde_json::to_string_pretty(report)?)
+            .with_context(|| format!("failed to write {:?}", path))
+    });
+    if let Err(e) = saved {
+        warn!("failed to save the run report: {:#}", e);
+    }
+}
+
+/// The report saved by the last run, from this session or an earlier one
+pub fn load() -> Result<Option<RunReport>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+    Ok(Some(serde_json::from_str(&content).with_context(|| format!("failed to parse {:?}", path))?))
+}
@@ -0,0 +1,224 @@
+//! Repro bundles for bug reports: `record_repro` (or the tray) keeps the next
+//! dictation in `<data dir>/repros/repro-<unix time>/`, with its 16khz audio,
+//! the config it ran with, which model decoded it and what every stage made of
+//! the text. `whisperia replay <bundle>` runs the stages again on this machine
+//! and shows where the output differs from the recording.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::config::Config;
+use crate::{audio, get_model_path, hotwords, i18n, postprocess, tuning, Glossary, RunReport, Transcriber};
+
+const BUNDLE_FILE: &str = "repro.json";
+const AUDIO_FILE: &str = "audio.wav";
+
+/// The model file that decoded the dictation; the size tells a re-download or
+/// another quantization apart from the same file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelIdentity {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>, // unset when the service decoded it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+/// What one step of the pipeline made of the text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stage {
+    pub name: String, // decode, glossary, hotwords, revise, routing, llm, postprocess
+    pub output: String,
+}
+
+/// `repro.json`, next to `audio.wav`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: String, // of the whisperia that recorded it
+    pub recorded_at: u64, // unix seconds
+    pub config: Config,   // as the dictation ran, profile and language rules applied, without secrets
+    pub model: ModelIdentity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>, // the focused app, for its output rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary: Option<Glossary>,
+    pub stages: Vec<Stage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<RunReport>, // unset when the dictation failed
+    pub outcome: String,
+}
+
+/// The dictation being recorded; armed by `record_repro`, started by the next
+/// dictation once its config is settled
+#[derive(Default)]
+pub struct Capture {
+    config: Option<Config>,
+    app_id: Option<String>,
+    glossary: Option<Glossary>,
+    audio: Vec<f32>,
+    stages: Vec<Stage>,
+}
+
+impl Capture {
+    pub fn start(&mut self, config: &Config, app_id: Option<&str>) {
+        let config = config.redacted();
+        *self = Self {
+            glossary: config.glossary.enabled.then(|| Glossary::load().unwrap_or_default()),
+            config: Some(config),
+            app_id: app_id.map(str::to_string),
+            ..Default::default()
+        };
+    }
+
+    /// False while armed for a dictation that hasn't started yet
+    pub fn started(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// The 16khz audio whisper gets; a retry replaces the first recording
+    pub fn audio(&mut self, samples: &[f32]) {
+        if self.started() {
+            self.audio = samples.to_vec();
+        }
+    }
+
+    pub fn stage(&mut self, name: &str, output: &str) {
+        if self.started() {
+            self.stages.push(Stage {
+                name: name.to_string(),
+                output: output.to_string(),
+            });
+        }
+    }
+}
+
+/// Write a started capture as a bundle, returning its directory
+pub fn save(capture: Capture, report: Option<&RunReport>, outcome: &str) -> Result<PathBuf> {
+    let config = capture.config.context("the repro capture never started")?;
+    let recorded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let dir = Config::repros_dir()?.join(format!("repro-{}", recorded_at));
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {:?}", dir))?;
+
+    let model_path = report.and_then(|r| r.decode.model_path.clone());
+    let bundle = Bundle {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        recorded_at,
+        model: ModelIdentity {
+            name: report.map_or_else(|| config.model.local_model.clone(), |r| r.metrics.model.clone()),
+            bytes: model_path.as_ref().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len()),
+            path: model_path,
+        },
+        config,
+        app_id: capture.app_id,
+        glossary: capture.glossary,
+        stages: capture.stages,
+        report: report.cloned(),
+        outcome: outcome.to_string(),
+    };
+    write_wav(&dir.join(AUDIO_FILE), &capture.audio)?;
+    let path = dir.join(BUNDLE_FILE);
+    std::fs::write(&path, serde_json::to_string_pretty(&bundle)?).with_context(|| format!("failed to write {:?}", path))?;
+    info!("repro bundle saved to {:?} ({} stages)", dir, bundle.stages.len());
+    Ok(dir)
+}
+
+// f32 samples, so the replay decodes exactly what whisper got
+fn write_wav(path: &Path, samples: &[f32]) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).with_context(|| format!("failed to create {:?}", path))?;
+    for sample in samples {
+        writer.write_sample(*sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+pub fn load(dir: &Path) -> Result<Bundle> {
+    let path = dir.join(BUNDLE_FILE);
+    let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("failed to parse {:?}", path))
+}
+
+/// `whisperia replay <bundle>`: run the recorded stages again, each on what the
+/// stage before it recorded, and print both outputs. Routing, revisions and the
+/// llm aren't run again (they open urls and call apis), only shown.
+pub fn replay(dir: &Path) -> Result<()> {
+    let bundle = load(dir)?;
+    let config = &bundle.config;
+    i18n::set_locale(&config.ui.locale);
+    println!(
+        "repro recorded by whisperia {} at {} ({}), replayed by {}",
+        bundle.version,
+        bundle.recorded_at,
+        bundle.outcome,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let mut input = String::new();
+    let mut differences = 0;
+    for stage in &bundle.stages {
+        let replayed = match stage.name.as_str() {
+            "decode" => Some(decode(dir, &bundle)?),
+            "glossary" => bundle.glossary.as_ref().map(|glossary| glossary.apply(&input)),
+            "hotwords" => Some(hotwords::apply(&input, &config.model.hotwords)),
+            "postprocess" => Some(postprocess::process(&input, config, bundle.app_id.as_deref())),
+            _ => None,
+        };
+        println!("\n[{}]\n  recorded: {}", stage.name, stage.output);
+        match replayed {
+            Some(replayed) if replayed == stage.output => println!("  replayed: same"),
+            Some(replayed) => {
+                differences += 1;
+                println!("  replayed: {}", replayed);
+            }
+            None => println!("  replayed: not run again"),
+        }
+        input = stage.output.clone();
+    }
+    println!("\n{} of {} stages differ", differences, bundle.stages.len());
+    Ok(())
+}
+
+/// Decode the bundle's audio with the recorded model file when it's here, the
+/// configured one otherwise
+fn decode(dir: &Path, bundle: &Bundle) -> Result<String> {
+    let config = &bundle.config;
+    let samples = audio::decode_wav(&std::fs::read(dir.join(AUDIO_FILE)).context("the bundle has no audio")?)?;
+    let recorded = bundle.model.path.as_ref().map(PathBuf::from).filter(|path| path.exists());
+    let model_path = match recorded {
+        Some(path) => path,
+        None => {
+            let path = get_model_path(config)?;
+            println!("model {:?} isn't here, decoding with {:?}", bundle.model.path, path);
+            path
+        }
+    };
+    let bytes = std::fs::metadata(&model_path).map(|m| m.len()).ok();
+    if bundle.model.bytes.is_some() && bytes != bundle.model.bytes {
+        println!("model {:?} isn't the recorded file ({:?} bytes, recorded {:?})", model_path, bytes, bundle.model.bytes);
+    }
+    let threads = bundle
+        .report
+        .as_ref()
+        .map(|r| r.decode.threads)
+        .filter(|&threads| threads > 0)
+        .unwrap_or_else(|| tuning::threads(&model_path, config.model.threads));
+    let transcriber = Transcriber::new(&model_path)?
+        .with_threads(threads)
+        .with_paragraphs(config.output.paragraph_pause_ms)
+        .with_prompt(hotwords::prompt(&config.model.hotwords))
+        .with_rules(&config.model.decode)
+        .with_translate(config.model.translate);
+    Ok(transcriber.transcribe(&samples, &config.language)?)
+}
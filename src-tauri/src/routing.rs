@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+use tracing::info;
+
+use crate::config::RoutingConfig;
+
+/// What to do with a dictation, decided from the words it starts with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    Type(String),
+    Note(String),
+    Translate { language: String, text: String },
+    Search(String),
+    Spell(String),
+}
+
+/// Recognize "note to self ...", "translate to <language> ...", "search for ..."
+/// and "spell ..." at the start of a dictation. Anything else is typed as usual.
+pub fn parse(text: &str, config: &RoutingConfig) -> Route {
+    if !config.enabled {
+        return Route::Type(text.to_string());
+    }
+
+    if let Some(rest) = strip_any_prefix(text, &config.note_prefixes) {
+        return Route::Note(rest.to_string());
+    }
+
+    if let Some(rest) = strip_any_prefix(text, &config.spell_prefixes) {
+        return Route::Spell(spell(rest));
+    }
+
+    if let Some(rest) = strip_any_prefix(text, &config.translate_prefixes) {
+        // the first word after the prefix is the target language
+        let (language, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let language = language.trim_matches(|c: char| !c.is_alphanumeric());
+        let remainder = trim_separators(remainder);
+        if !language.is_empty() && !remainder.is_empty() {
+            return Route::Translate {
+                language: language.to_string(),
+                text: remainder.to_string(),
+            };
+        }
+    }
+
+    if let Some(rest) = strip_any_prefix(text, &config.search_prefixes) {
+        // whisper usually ends the query with a period
+        return Route::Search(rest.trim_end_matches(['.', '!', '?']).to_string());
+    }
+
+    Route::Type(text.to_string())
+}
+
+/// Split a dictation at the spoken chain separators ("... next ..."), so
+/// every part is routed on its own. One part when chaining is off.
+pub fn split_chain<'a>(text: &'a str, config: &RoutingConfig) -> Vec<&'a str> {
+    if !config.enabled || config.chain_separators.is_empty() {
+        return vec![text];
+    }
+    let separators: Vec<Vec<String>> = config
+        .chain_separators
+        .iter()
+        .map(|s| s.split_whitespace().map(str::to_lowercase).collect())
+        .filter(|words: &Vec<String>| !words.is_empty())
+        .collect();
+    // whisper punctuates around the separator, "Next," or "next."
+    let word = |w: &str| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    let mut words: Vec<(usize, &str)> = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                words.push((s, &text[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+
+    let mut parts = Vec::new();
+    let (mut part_start, mut i) = (0, 0);
+    while i < words.len() {
+        let matched = separators.iter().find(|separator| {
+            words.len() - i >= separator.len()
+                && separator.iter().zip(&words[i..]).all(|(s, (_, w))| word(w) == *s)
+        });
+        match matched {
+            Some(separator) => {
+                parts.push(&text[part_start..words[i].0]);
+                let (offset, last) = words[i + separator.len() - 1];
+                part_start = offset + last.len();
+                i += separator.len();
+            }
+            None => i += 1,
+        }
+    }
+    parts.push(&text[part_start..]);
+    parts
+        .into_iter()
+        .map(|part| trim_separators(part).trim_end_matches([',', ';']))
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn strip_any_prefix<'a>(text: &'a str, prefixes: &[String]) -> Option<&'a str> {
+    let text = text.trim_start();
+    prefixes.iter().find_map(|prefix| {
+        let head = text.get(..prefix.len())?;
+        if !head.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        // whole words only, "notes" must not match "note"
+        let rest = &text[prefix.len()..];
+        if rest.starts_with(char::is_alphanumeric) {
+            return None;
+        }
+        let rest = trim_separators(rest);
+        (!rest.is_empty()).then_some(rest)
+    })
+}
+
+/// Spelled words to the characters: NATO alphabet, single letters, digits and
+/// a few symbols, "capital" before a letter uppercases it. Other words are kept,
+/// so "john at gmail dot com" is "john@gmail.com".
+pub fn spell(text: &str) -> String {
+    let mut spelled = String::new();
+    let mut capital = false;
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        let lower = word.to_lowercase();
+        if matches!(lower.as_str(), "capital" | "uppercase" | "maiúsculo" | "maiúscula") {
+            capital = true;
+            continue;
+        }
+        let piece = if let Some(&(_, letter)) = NATO.iter().find(|(name, _)| *name == lower) {
+            letter.to_string()
+        } else if let Some(&(_, digit)) = DIGITS.iter().find(|(name, _)| *name == lower) {
+            digit.to_string()
+        } else if let Some(&(_, symbol)) = SYMBOLS.iter().find(|(name, _)| *name == lower) {
+            symbol.to_string()
+        } else {
+            lower
+        };
+        if std::mem::take(&mut capital) {
+            spelled.push_str(&piece.to_uppercase());
+        } else {
+            spelled.push_str(&piece);
+        }
+    }
+    spelled
+}
+
+const NATO: &[(&str, char)] = &[
+    ("alpha", 'a'), ("alfa", 'a'), ("bravo", 'b'), ("charlie", 'c'), ("delta", 'd'),
+    ("echo", 'e'), ("foxtrot", 'f'), ("golf", 'g'), ("hotel", 'h'), ("india", 'i'),
+    ("juliet", 'j'), ("juliett", 'j'), ("kilo", 'k'), ("lima", 'l'), ("mike", 'm'),
+    ("november", 'n'), ("oscar", 'o'), ("papa", 'p'), ("quebec", 'q'), ("romeo", 'r'),
+    ("sierra", 's'), ("tango", 't'), ("uniform", 'u'), ("victor", 'v'), ("whiskey", 'w'),
+    ("xray", 'x'), ("yankee", 'y'), ("zulu", 'z'),
+];
+
+const DIGITS: &[(&str, char)] = &[
+    ("zero", '0'), ("one", '1'), ("two", '2'), ("three", '3'), ("four", '4'),
+    ("five", '5'), ("six", '6'), ("seven", '7'), ("eight", '8'), ("nine", '9'),
+    ("um", '1'), ("uma", '1'), ("dois", '2'), ("duas", '2'), ("três", '3'), ("tres", '3'),
+    ("quatro", '4'), ("cinco", '5'), ("seis", '6'), ("meia", '6'), ("sete", '7'), ("oito", '8'),
+    ("nove", '9'),
+];
+
+const SYMBOLS: &[(&str, char)] = &[
+    ("dash", '-'), ("hyphen", '-'), ("minus", '-'), ("hífen", '-'), ("traço", '-'),
+    ("underscore", '_'), ("dot", '.'), ("period", '.'), ("ponto", '.'), ("at", '@'),
+    ("arroba", '@'), ("slash", '/'), ("barra", '/'), ("plus", '+'), ("mais", '+'),
+    ("hash", '#'), ("space", ' '), ("espaço", ' '),
+];
+
+fn trim_separators(text: &str) -> &str {
+    text.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ':' | ';' | '-'))
+        .trim_end()
+}
+
+pub fn search_url(config: &RoutingConfig, query: &str) -> String {
+    config.search_url.replace("{query}", &encode_query(query))
+}
+
+fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+    for byte in query.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Open a url in the default browser
+pub fn open_url(url: &str) -> Result<()> {
+    info!("opening {}", url);
+
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        let mut c = Command::new("xdg-open");
+        c.arg(url);
+        c
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut c = Command::new("open");
+        c.arg(url);
+        c
+    };
+    // not `cmd /C start`, cmd splits the url at every & of its query
+    #[cfg(windows)]
+    let mut command = {
+        let mut c = Command::new("explorer");
+        c.arg(url);
+        c
+    };
+
+    command.spawn().context("failed to open browser")?;
+    Ok(())
+}
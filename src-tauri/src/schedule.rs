@@ -0,0 +1,70 @@
+//! Cron-like schedules: "minute hour day-of-month month day-of-week", where
+//! each field is `*`, a number, a range `a-b`, a list `a,b` or a step `*/n`
+//! (ranges take steps too). Sunday is 0 or 7.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Local, Timelike};
+
+// the values each field can take
+const FIELDS: [(u32, u32); 5] = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 7)];
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    fields: [Vec<u32>; 5],
+}
+
+impl Schedule {
+    pub fn parse(entry: &str) -> Result<Self> {
+        let parts: Vec<&str> = entry.split_whitespace().collect();
+        if parts.len() != 5 {
+            anyhow::bail!("'{}' should have 5 fields: minute hour day month weekday", entry);
+        }
+        let mut fields: [Vec<u32>; 5] = Default::default();
+        for (i, part) in parts.iter().enumerate() {
+            fields[i] = parse_field(part, FIELDS[i]).with_context(|| format!("invalid schedule '{}'", entry))?;
+        }
+        // 7 is sunday too
+        if fields[4].contains(&7) {
+            fields[4].push(0);
+        }
+        Ok(Self { fields })
+    }
+
+    /// Whether the schedule fires in the minute of `at`
+    pub fn matches(&self, at: &DateTime<Local>) -> bool {
+        let values = [
+            at.minute(),
+            at.hour(),
+            at.day(),
+            at.month(),
+            at.weekday().num_days_from_sunday(),
+        ];
+        self.fields.iter().zip(values).all(|(allowed, value)| allowed.contains(&value))
+    }
+}
+
+fn parse_field(field: &str, (min, max): (u32, u32)) -> Result<Vec<u32>> {
+    let mut values = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().context("invalid step")?.max(1)),
+            None => (item, 1),
+        };
+        let (from, to) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((from, to)) => (from.parse()?, to.parse()?),
+                None => {
+                    let value: u32 = range.parse()?;
+                    // "5/10" runs from 5 to the end in steps of 10
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if from < min || to > max || from > to {
+            anyhow::bail!("{} is outside {}-{}", item, min, max);
+        }
+        values.extend((from..=to).step_by(step as usize));
+    }
+    Ok(values)
+}
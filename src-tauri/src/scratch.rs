@@ -0,0 +1,58 @@
+//! Scratch note: a small window of our own that dictations go into when
+//! there's no app to type into, with copy and save buttons. The note lives
+//! in the app state, so it survives closing the window until it's saved.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tracing::info;
+
+use crate::config::Config;
+use crate::{AppState, Event, WhisperiaError};
+
+pub const WINDOW: &str = "scratch";
+
+/// Show the scratch note, opening its window when needed
+pub fn open(app: &AppHandle) -> Result<(), WhisperiaError> {
+    if let Some(window) = app.get_webview_window(WINDOW) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+    info!("opening the scratch note");
+    WebviewWindowBuilder::new(app, WINDOW, WebviewUrl::App("scratch.html".into()))
+        .title("Whisperia Scratch Note")
+        .inner_size(420.0, 320.0)
+        .always_on_top(true)
+        .build()
+        .map_err(|e| WhisperiaError::Internal(format!("could not open the scratch note: {}", e)))?;
+    Ok(())
+}
+
+/// Whether dictations should go to the scratch note: it's the focused window
+pub fn is_focused(app: &AppHandle) -> bool {
+    app.get_webview_window(WINDOW)
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+/// Add a dictation at the end of the note and show it
+pub fn append(app: &AppHandle, text: &str) -> Result<(), WhisperiaError> {
+    let state = app.state::<AppState>();
+    let note = state.append_scratch(text);
+    state.publish(Event::Scratch(note));
+    open(app)
+}
+
+/// Write the note to `<data dir>/notes/note-<unix time>.md`
+pub fn save(note: &str) -> Result<PathBuf> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = Config::notes_dir()?.join(format!("note-{}.md", now));
+    std::fs::write(&path, note).with_context(|| format!("failed to write {:?}", path))?;
+    info!("scratch note saved to {:?}", path);
+    Ok(path)
+}
@@ -0,0 +1,38 @@
+//! The text selected in the focused app, read by pressing the copy shortcut
+//! and picking it up from the clipboard. The text that was on the clipboard
+//! before is put back afterwards.
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::{clipboard, permissions, InputSimulator};
+
+// how long the app has to put the selection on the clipboard
+const COPY_TIMEOUT: Duration = Duration::from_millis(500);
+const POLL: Duration = Duration::from_millis(25);
+
+/// The selected text, `None` when nothing is selected
+pub fn copy() -> Result<Option<String>> {
+    permissions::ensure_accessibility()?;
+    let previous = clipboard::get().unwrap_or_default();
+    // cleared first, so an app that copies nothing isn't mistaken for one that copied the old contents
+    clipboard::set("")?;
+
+    InputSimulator::new()?.copy()?;
+    let started = Instant::now();
+    let mut selected = String::new();
+    while selected.is_empty() && started.elapsed() < COPY_TIMEOUT {
+        std::thread::sleep(POLL);
+        selected = clipboard::get().unwrap_or_default();
+    }
+
+    if let Err(e) = clipboard::set(&previous) {
+        warn!("failed to restore the clipboard: {:#}", e);
+    }
+    if selected.trim().is_empty() {
+        return Ok(None);
+    }
+    info!("copied {} selected chars", selected.len());
+    Ok(Some(selected))
+}
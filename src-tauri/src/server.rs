@@ -0,0 +1,347 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, Multipart, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::history::HistoryEntry;
+use crate::events::{self, Event, TranscriptEvent};
+use crate::{
+    audio, memory, tray_cycle, trigger_transcription_flow, tuning, AppState, Transcriber, WhisperiaError, Word,
+};
+
+// minimal page for OBS browser sources and other caption displays
+const CAPTIONS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="UTF-8">
+<style>
+  body { margin: 0; background: transparent; font-family: sans-serif; }
+  #captions { position: fixed; bottom: 24px; width: 100%; text-align: center;
+    font-size: 32px; color: #fff; text-shadow: 0 0 6px #000; }
+</style>
+</head>
+<body>
+<div id="captions"></div>
+<script>
+  const el = document.getElementById("captions");
+  let partial = [];
+  function connect() {
+    const ws = new WebSocket(`ws://${location.host}/ws`);
+    ws.onmessage = (msg) => {
+      const event = JSON.parse(msg.data);
+      if (event.type === "partial") {
+        partial.push(event.text);
+        el.textContent = partial.join(" ");
+      } else if (event.type === "final") {
+        partial = [];
+        el.textContent = event.text;
+      }
+    };
+    ws.onclose = () => setTimeout(connect, 1000);
+  }
+  connect();
+</script>
+</body>
+</html>
+"#;
+
+#[derive(Debug, Serialize)]
+struct TranscribeResponse {
+    text: String,
+    language: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    words: Vec<Word>,
+    confidence: Option<f32>,
+}
+
+// an upload the size of the longest recording at 48khz stereo 16 bit, the
+// 2mb axum allows by default is about 10 seconds of that
+const UPLOAD_BYTES_PER_SEC: usize = 48_000 * 2 * 2;
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.1 }));
+        (self.0, body).into_response()
+    }
+}
+
+/// Serve the local REST API until `shutdown` is cancelled
+pub async fn serve(app: AppHandle, addr: SocketAddr, shutdown: CancellationToken) -> anyhow::Result<()> {
+    let state = app.state::<AppState>().inner().clone();
+    let limit = body_limit(&state.get_config());
+    let router = Router::new()
+        .route("/transcribe", post(transcribe))
+        .route("/status", get(status))
+        .route("/history", get(history))
+        .route("/metrics", get(metrics))
+        .route("/ws", get(websocket))
+        .route("/captions", get(captions))
+        .route("/cycle", post(cycle))
+        .with_state(state)
+        .merge(Router::new().route("/trigger", post(trigger)).with_state(app))
+        .layer(DefaultBodyLimit::max(limit));
+
+    listen(router, addr, false, shutdown).await
+}
+
+/// The shared transcription service. No history, live transcripts or triggers,
+/// so the users of the machine don't see each other's dictations. With
+/// `service.token` set every request has to bring it as a bearer token.
+pub async fn serve_service(state: AppState, addr: SocketAddr, shutdown: CancellationToken) -> anyhow::Result<()> {
+    let config = state.get_config();
+    let limit = body_limit(&config);
+    let token = config.service.token.filter(|t| !t.is_empty());
+    let authenticated = token.is_some();
+    let mut router = Router::new()
+        .route("/transcribe", post(transcribe_only))
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+        .layer(DefaultBodyLimit::max(limit));
+    if let Some(token) = token {
+        router = router.layer(middleware::from_fn_with_state(Arc::new(token), require_token));
+    }
+
+    listen(router, addr, authenticated, shutdown).await
+}
+
+/// Room for a wav of `capture.max_secs` and the other form fields
+fn body_limit(config: &Config) -> usize {
+    config.capture.max_secs as usize * UPLOAD_BYTES_PER_SEC + 1024 * 1024
+}
+
+async fn require_token(
+    State(token): State<Arc<String>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let sent = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if sent != Some(token.as_str()) {
+        return Err(ApiError(StatusCode::UNAUTHORIZED, "missing or wrong service token".to_string()));
+    }
+    Ok(next.run(request).await)
+}
+
+async fn listen(router: Router, addr: SocketAddr, authenticated: bool, shutdown: CancellationToken) -> anyhow::Result<()> {
+    if !addr.ip().is_loopback() && !authenticated {
+        warn!("HTTP API bound to non-loopback address {} - it has no authentication", addr);
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("HTTP API listening on http://{}", addr);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await?;
+    info!("HTTP API stopped");
+    Ok(())
+}
+
+async fn transcribe(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Json<TranscribeResponse>, ApiError> {
+    let response = decode(&state, multipart).await?;
+
+    state.add_history(
+        HistoryEntry::new(&response.text, &response.language, "api").with_confidence(response.confidence),
+    );
+    state.publish(Event::Transcript(TranscriptEvent::Final {
+        text: response.text.clone(),
+        language: response.language.clone(),
+        words: response.words.clone(),
+        confidence: response.confidence,
+    }));
+
+    Ok(Json(response))
+}
+
+async fn transcribe_only(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Json<TranscribeResponse>, ApiError> {
+    Ok(Json(decode(&state, multipart).await?))
+}
+
+async fn decode(state: &AppState, mut multipart: Multipart) -> Result<TranscribeResponse, ApiError> {
+    let config = state.get_config();
+
+    let mut audio_bytes = None;
+    let mut language = config.language.clone();
+    let mut prompt = None;
+    let mut translate = config.model.translate;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        match field.name() {
+            Some("language") => {
+                language = field
+                    .text()
+                    .await
+                    .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+            }
+            // hotwords of the app that sent the audio
+            Some("prompt") => {
+                prompt = Some(
+                    field
+                        .text()
+                        .await
+                        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?,
+                );
+            }
+            // "true" for english text whatever was spoken, `model.translate` when left out
+            Some("translate") => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+                translate = value.trim().eq_ignore_ascii_case("true") || value.trim() == "1";
+            }
+            // any other field is treated as the audio file
+            _ => {
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+                audio_bytes = Some(bytes);
+            }
+        }
+    }
+
+    let audio_bytes = audio_bytes.ok_or_else(|| {
+        ApiError(StatusCode::BAD_REQUEST, "missing audio file field".to_string())
+    })?;
+    let samples = audio::decode_wav(&audio_bytes)
+        .map_err(|e| ApiError(StatusCode::UNSUPPORTED_MEDIA_TYPE, e.to_string()))?;
+
+    info!("HTTP API transcription request: {} samples", samples.len());
+
+    let (model_path, _) = memory::pick_model(&config)
+        .map_err(|e| ApiError(StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+    let lang = language.clone();
+    let cached_models = config.model.cached_models;
+    let threads = tuning::threads(&model_path, config.model.threads);
+    let pause_ms = config.output.paragraph_pause_ms;
+    let partials = state.clone();
+    let transcript = tokio::task::spawn_blocking(move || {
+        let transcriber = Transcriber::cached(&model_path, cached_models)?
+            .with_threads(threads)
+            .with_paragraphs(pause_ms)
+            .with_prompt(prompt)
+            .with_translate(translate);
+        transcriber.transcribe_scored(&samples, &lang, &CancellationToken::new(), move |segment| {
+            partials.publish(Event::Transcript(TranscriptEvent::Partial(segment)));
+        })
+    })
+    .await
+    .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| {
+        error!("HTTP API transcription failed: {}", e);
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+
+    Ok(TranscribeResponse {
+        text: transcript.text,
+        language,
+        words: transcript.words,
+        confidence: transcript.confidence,
+    })
+}
+
+async fn trigger(State(app): State<AppHandle>) -> Result<impl IntoResponse, ApiError> {
+    info!("HTTP API trigger request");
+    trigger_transcription_flow(app.clone(), None).map_err(|e| match e {
+        WhisperiaError::Busy(_) => ApiError(StatusCode::CONFLICT, e.to_string()),
+        _ => ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    let status = app.state::<AppState>().get_status();
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+#[derive(Deserialize)]
+struct CycleQuery {
+    #[serde(default = "forward")]
+    step: i32,
+}
+
+fn forward() -> i32 {
+    1
+}
+
+/// The tray's model/language switch for bars that bind it to the scroll wheel
+async fn cycle(State(state): State<AppState>, Query(query): Query<CycleQuery>) -> Result<impl IntoResponse, ApiError> {
+    let selection = tray_cycle::cycle(&state, query.step).map_err(|e| match e {
+        WhisperiaError::Invalid(_) => ApiError(StatusCode::BAD_REQUEST, e.to_string()),
+        _ => ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+    Ok(Json(serde_json::json!({ "selection": selection })))
+}
+
+async fn status(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.get_status())
+}
+
+async fn history(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.get_history())
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let body = state.get_metrics().prometheus();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+async fn websocket(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    let events = state.subscribe();
+    ws.on_upgrade(move |socket| stream_transcripts(socket, events))
+}
+
+async fn stream_transcripts(
+    mut socket: WebSocket,
+    mut events: tokio::sync::broadcast::Receiver<Event>,
+) {
+    info!("WebSocket client connected");
+
+    while let Some(event) = events::next(&mut events).await {
+        let Event::Transcript(event) = event else {
+            continue;
+        };
+
+        let payload = match serde_json::to_string(&event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("failed to serialize transcript event: {}", e);
+                continue;
+            }
+        };
+
+        if socket.send(Message::Text(payload)).await.is_err() {
+            break;
+        }
+    }
+
+    info!("WebSocket client disconnected");
+}
+
+async fn captions() -> impl IntoResponse {
+    Html(CAPTIONS_PAGE)
+}
@@ -0,0 +1,88 @@
+//! Service mode for shared machines: one process (usually a system service) owns
+//! the model and decodes for everyone, the per-user apps only record and type and
+//! send their audio to it over the HTTP API. The service can also be a desktop
+//! with a GPU on the LAN decoding for a thin laptop, then `service.token` keeps
+//! the rest of the network out.
+
+use anyhow::{Context, Result};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::config::{Config, ServiceConfig};
+use crate::{audio, crash, logging, server, shutdown, AppState, Transcript, Word};
+
+/// Run the transcription service until ctrl+c or SIGTERM, without any window
+pub fn run(addr: SocketAddr) {
+    crash::install();
+    let state = AppState::new().expect("Failed to create app state");
+    logging::init(&state.get_config().logging);
+    info!("Whisperia transcription service starting on {}", addr);
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+    runtime.block_on(async move {
+        let shutdown = state.shutdown_token();
+        let signal_state = state.clone();
+        tokio::spawn(async move {
+            shutdown::wait_for_signal().await;
+            signal_state.begin_shutdown();
+        });
+
+        if let Err(e) = server::serve_service(state, addr, shutdown).await {
+            error!("Transcription service failed: {:#}", e);
+        }
+    });
+    info!("Transcription service stopped");
+}
+
+#[derive(Deserialize)]
+struct TranscribeResponse {
+    text: String,
+    #[serde(default)]
+    words: Vec<Word>,
+    #[serde(default)]
+    confidence: Option<f32>,
+}
+
+/// Decode `samples` (16khz mono) on the service at `config.url`, with the
+/// hotwords `prompt` when there is one, into english with `translate`
+pub async fn transcribe(
+    config: &ServiceConfig,
+    samples: &[f32],
+    language: &str,
+    prompt: Option<&str>,
+    translate: bool,
+    timeout: Duration,
+) -> Result<Transcript> {
+    let url = config.url.as_deref().context("No transcription service configured")?;
+    Config::ensure_online("the transcription service")?;
+    let wav = Part::bytes(audio::encode_wav(samples)?).file_name("audio.wav").mime_str("audio/wav")?;
+    let mut form = Form::new()
+        .text("language", language.to_string())
+        .text("translate", translate.to_string())
+        .part("audio", wav);
+    if let Some(prompt) = prompt {
+        form = form.text("prompt", prompt.to_string());
+    }
+
+    let mut request = reqwest::Client::new().post(format!("{}/transcribe", url.trim_end_matches('/')));
+    if let Some(token) = &config.token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .multipart(form)
+        .timeout(timeout)
+        .send()
+        .await
+        .with_context(|| format!("Transcription service at {} is unreachable", url))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        anyhow::bail!("Transcription service returned {}: {}", status, message);
+    }
+    let TranscribeResponse { text, words, confidence } = response.json().await?;
+    Ok(Transcript { text, words, confidence })
+}
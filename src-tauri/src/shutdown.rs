@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::{captions, hide_overlay_window, meeting, open_mic, wake_word, AppState, TRAY_ID};
+
+// long enough for whisper to notice the abort and mqtt to send "offline"
+const GRACE_PERIOD: Duration = Duration::from_secs(2);
+// the last chunk of a meeting still has to be transcribed
+const MEETING_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Stop everything in order and exit. Safe to call more than once.
+pub fn shutdown(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if !state.begin_shutdown() {
+        return;
+    }
+    info!("Shutting down");
+
+    // the shutdown token stops the hotkey listener, mqtt and the http api;
+    // the run token aborts an in-flight recording/transcription
+    state.jobs().clear();
+    state.cancel_run();
+    state.unregister_hotkeys();
+    captions::stop(app);
+    wake_word::stop(&state);
+    open_mic::stop(&state);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+
+        let deadline = Instant::now() + GRACE_PERIOD;
+        while state.get_status().pipeline.is_busy() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // save the meeting being recorded instead of losing it
+        if state.meeting_running() {
+            match tokio::time::timeout(MEETING_GRACE_PERIOD, meeting::stop(&state)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("Failed to save the meeting on shutdown: {}", e),
+                Err(_) => warn!("Gave up saving the meeting after {:?}", MEETING_GRACE_PERIOD),
+            }
+        }
+
+        if let Err(e) = state.flush() {
+            warn!("Failed to save state on shutdown: {}", e);
+        }
+
+        let _ = hide_overlay_window(&app);
+        app.remove_tray_by_id(TRAY_ID);
+
+        info!("Shutdown complete");
+        app.exit(0);
+    });
+}
+
+/// Resolve on ctrl+c, or SIGTERM on unix (systemd, logout)
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+
+    info!("Received termination signal");
+}
@@ -0,0 +1,95 @@
+//! Short spoken confirmations for blind and low-vision users, through the
+//! engine the platform ships: speech-dispatcher or espeak on linux, `say` on
+//! macos and System.Speech on windows.
+
+use anyhow::{Context, Result};
+use std::process::Command;
+use tracing::debug;
+
+/// Speak `text` and wait until it's done, so confirmations don't talk over each other
+pub fn speak(text: &str, voice: Option<&str>) -> Result<()> {
+    speak_in(text, voice, None)
+}
+
+/// Speak `text` in `language` (a whisper code like "pt"), the ui.locale one when `None`
+pub fn speak_in(text: &str, voice: Option<&str>, language: Option<&str>) -> Result<()> {
+    debug!("speaking '{}'", text);
+    #[cfg(not(target_os = "macos"))]
+    let language = language.unwrap_or(locale_language());
+    let status = command(text, voice, language)?.status().context("failed to run the speech engine")?;
+    if !status.success() {
+        anyhow::bail!("speech engine exited with {}", status);
+    }
+    Ok(())
+}
+
+// the say voice already follows the system language on macos
+#[cfg(not(target_os = "macos"))]
+fn locale_language() -> &'static str {
+    use crate::i18n::{locale, Locale};
+    match locale() {
+        Locale::En => "en",
+        Locale::Pt => "pt",
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn command(text: &str, voice: Option<&str>, language: &str) -> Result<Command> {
+    if which("spd-say") {
+        let mut c = Command::new("spd-say");
+        // -w waits for the message to be spoken
+        c.args(["-w", "-l", language]);
+        if let Some(voice) = voice {
+            c.args(["-y", voice]);
+        }
+        c.arg("--").arg(text);
+        return Ok(c);
+    }
+    for espeak in ["espeak-ng", "espeak"] {
+        if which(espeak) {
+            let mut c = Command::new(espeak);
+            c.args(["-v", voice.unwrap_or(language)]);
+            c.arg("--").arg(text);
+            return Ok(c);
+        }
+    }
+    anyhow::bail!("no speech engine found, install speech-dispatcher or espeak-ng")
+}
+
+#[cfg(target_os = "linux")]
+fn which(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "macos")]
+fn command(text: &str, voice: Option<&str>, _language: Option<&str>) -> Result<Command> {
+    // without a voice `say` uses the system one, which follows the system language
+    let mut c = Command::new("say");
+    if let Some(voice) = voice {
+        c.args(["-v", voice]);
+    }
+    c.arg("--").arg(text);
+    Ok(c)
+}
+
+#[cfg(windows)]
+fn command(text: &str, voice: Option<&str>, language: &str) -> Result<Command> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // text and voice go through the environment, nothing to quote
+    let script = "Add-Type -AssemblyName System.Speech; \
+        $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+        if ($env:WHISPERIA_VOICE) { $s.SelectVoice($env:WHISPERIA_VOICE) } \
+        else { $s.SelectVoiceByHints('NotSet', 'NotSet', 0, [Globalization.CultureInfo]::new($env:WHISPERIA_LANGUAGE)) }; \
+        $s.Speak($env:WHISPERIA_SAY)";
+    let mut c = Command::new("powershell");
+    c.args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("WHISPERIA_SAY", text)
+        .env("WHISPERIA_VOICE", voice.unwrap_or(""))
+        .env("WHISPERIA_LANGUAGE", language)
+        .creation_flags(CREATE_NO_WINDOW);
+    Ok(c)
+}
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::config::Config;
+
+/// Spoken names and what they are typed as. `symbols.toml` in the config
+/// directory adds more and overrides these, e.g. `"shrug emoji" = "🤷"`.
+const BUILTIN: &[(&str, &str)] = &[
+    ("thumbs up emoji", "👍"),
+    ("thumbs down emoji", "👎"),
+    ("smiley emoji", "🙂"),
+    ("smile emoji", "🙂"),
+    ("wink emoji", "😉"),
+    ("laughing emoji", "😂"),
+    ("heart emoji", "❤️"),
+    ("fire emoji", "🔥"),
+    ("party emoji", "🎉"),
+    ("check mark", "✓"),
+    ("degree sign", "°"),
+    ("em dash", "—"),
+    ("en dash", "–"),
+    ("ellipsis", "…"),
+    ("bullet point", "•"),
+    ("right arrow", "→"),
+    ("left arrow", "←"),
+    ("plus minus sign", "±"),
+    ("copyright sign", "©"),
+    ("registered sign", "®"),
+    ("trademark sign", "™"),
+    ("euro sign", "€"),
+    ("pound sign", "£"),
+    ("section sign", "§"),
+    ("emoji joinha", "👍"),
+    ("emoji sorriso", "🙂"),
+    ("emoji piscadinha", "😉"),
+    ("emoji risada", "😂"),
+    ("emoji coração", "❤️"),
+    ("emoji fogo", "🔥"),
+    ("emoji festa", "🎉"),
+    ("sinal de grau", "°"),
+    ("travessão", "—"),
+    ("reticências", "…"),
+    ("marcador", "•"),
+    ("seta para a direita", "→"),
+    ("seta para a esquerda", "←"),
+    ("sinal de euro", "€"),
+];
+
+// typed right after the previous word, "20 degree sign" is "20°"
+const ATTACHED: &[&str] = &["°", "…", "©", "®", "™"];
+
+/// The built-in names plus the user's `symbols.toml`, longest names first so
+/// "thumbs up emoji" wins over a shorter "up emoji"
+pub fn load() -> Vec<(Vec<String>, String)> {
+    let mut symbols: HashMap<String, String> = BUILTIN
+        .iter()
+        .map(|(name, symbol)| (name.to_string(), symbol.to_string()))
+        .collect();
+    match load_user() {
+        Ok(user) => symbols.extend(user.into_iter().map(|(name, symbol)| (name.to_lowercase(), symbol))),
+        Err(e) => warn!("ignoring symbols.toml: {:#}", e),
+    }
+
+    let mut symbols: Vec<_> = symbols
+        .into_iter()
+        .map(|(name, symbol)| (words(&name).map(str::to_string).collect::<Vec<_>>(), symbol))
+        .filter(|(name, _)| !name.is_empty())
+        .collect();
+    symbols.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    symbols
+}
+
+fn load_user() -> Result<HashMap<String, String>> {
+    let path = Config::symbols_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+    toml::from_str(&content).with_context(|| format!("failed to parse {:?}", path))
+}
+
+/// Replace spoken symbol names with the symbols, matching whole words
+/// case-insensitively; "Em-dash" is "em dash" too
+pub fn replace(text: &str, symbols: &[(Vec<String>, String)]) -> String {
+    let spans = word_spans(text);
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < spans.len() {
+        let found = symbols.iter().find(|(name, _)| {
+            spans.len() - i >= name.len()
+                && name
+                    .iter()
+                    .zip(&spans[i..])
+                    .all(|(word, &(start, end))| text[start..end].to_lowercase() == *word)
+        });
+        let Some((name, symbol)) = found else {
+            i += 1;
+            continue;
+        };
+
+        out.push_str(&text[copied..spans[i].0]);
+        if ATTACHED.contains(&symbol.as_str()) {
+            out.truncate(out.trim_end().len());
+        }
+        out.push_str(symbol);
+        copied = spans[i + name.len() - 1].1;
+        i += name.len();
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    word_spans(text).into_iter().map(move |(start, end)| &text[start..end])
+}
+
+/// Byte ranges of the words in `text`
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (at, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(at),
+            (false, Some(from)) => {
+                spans.push((from, at));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        spans.push((from, text.len()));
+    }
+    spans
+}
@@ -0,0 +1,54 @@
+use serde::Serialize;
+
+use crate::config::UiConfig;
+
+const THEMES: &[&str] = &["glass", "minimal", "dark"];
+
+/// Appearance for the overlay and settings windows, resolved once here so
+/// both webviews look the same and follow the config
+#[derive(Debug, Clone, Serialize)]
+pub struct UiTheme {
+    pub theme: String,
+    pub opacity: f32,
+    pub font_scale: f32,
+    pub reduced_motion: bool,
+}
+
+pub fn resolve(ui: &UiConfig) -> UiTheme {
+    let theme = if THEMES.contains(&ui.theme.as_str()) {
+        ui.theme.clone()
+    } else {
+        THEMES[0].to_string()
+    };
+    UiTheme {
+        theme,
+        opacity: ui.opacity.clamp(0.2, 1.0),
+        font_scale: ui.font_scale.clamp(0.5, 3.0),
+        reduced_motion: ui.reduced_motion.unwrap_or_else(os_reduced_motion),
+    }
+}
+
+/// The desktop's "reduce motion" / "animations off" accessibility setting
+#[cfg(target_os = "linux")]
+fn os_reduced_motion() -> bool {
+    // gnome, and most gtk desktops that read the same key
+    std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "enable-animations"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "false")
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "macos")]
+fn os_reduced_motion() -> bool {
+    std::process::Command::new("defaults")
+        .args(["read", "com.apple.universalaccess", "reduceMotion"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "1")
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn os_reduced_motion() -> bool {
+    !crate::win32::animations_enabled()
+}
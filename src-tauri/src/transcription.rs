@@ -1,21 +1,153 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
-use tracing::info;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_int, c_void};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use whisper_rs::whisper_rs_sys::{whisper_context, whisper_state, whisper_token_data};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
+use crate::config::DecodeRules;
+use crate::HardwareDetector;
+
+// recently used models on each backend, least recently used first
+static CONTEXTS: Mutex<Vec<(PathBuf, Backend, Arc<WhisperContext>)>> = Mutex::new(Vec::new());
+// what new transcribers decode on, switched with `set_backend`
+static BACKEND: Mutex<Backend> = Mutex::new(Backend::Cpu);
+
+/// Where whisper.cpp runs: the cpu, or a gpu backend this build was compiled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Cpu,
+    Cuda,
+    Vulkan,
+    Metal,
+}
+
+impl Backend {
+    /// The cpu, then the gpu backends enabled with the cargo features of the same name
+    pub fn available() -> Vec<Backend> {
+        let gpus = [
+            (Self::Cuda, cfg!(feature = "cuda")),
+            (Self::Vulkan, cfg!(feature = "vulkan")),
+            (Self::Metal, cfg!(feature = "metal")),
+        ];
+        let mut backends = vec![Self::Cpu];
+        backends.extend(gpus.into_iter().filter(|(_, built)| *built).map(|(backend, _)| backend));
+        backends
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Cuda => "cuda",
+            Self::Vulkan => "vulkan",
+            Self::Metal => "metal",
+        }
+    }
+}
+
+/// The backend new transcribers decode on
+pub fn backend() -> Backend {
+    *BACKEND.lock()
+}
+
+/// Decode on `backend` from now on. Models already loaded on it are reused,
+/// the ones on the previous backend stay cached for switching back.
+pub fn set_backend(backend: Backend) -> Result<()> {
+    if !Backend::available().contains(&backend) {
+        anyhow::bail!("whisperia was built without the {} backend", backend.name());
+    }
+    info!("inference backend set to {}", backend.name());
+    *BACKEND.lock() = backend;
+    Ok(())
+}
+
+/// `model.use_gpu`: the first gpu backend of this build, or the cpu
+pub fn set_gpu(enabled: bool) {
+    let gpu = Backend::available().into_iter().find(|backend| *backend != Backend::Cpu);
+    let backend = match (enabled, gpu) {
+        (true, Some(gpu)) => gpu,
+        (true, None) => {
+            warn!("model.use_gpu is on, but whisperia was built without cuda, vulkan or metal");
+            Backend::Cpu
+        }
+        (false, _) => Backend::Cpu,
+    };
+    info!("inference backend set to {}", backend.name());
+    *BACKEND.lock() = backend;
+}
+
 pub struct Transcriber {
-    context: WhisperContext,
+    context: Arc<WhisperContext>,
+    threads: i32,
+    paragraph_pause_ms: i64,
+    translate: bool,
+    prompt: Option<String>,
+    suppressed: Arc<Vec<i32>>, // token ids the logits filter rules out
+    suppress_non_speech: bool,
+}
+
+// decode threads unless the config says otherwise
+const DEFAULT_THREADS: i32 = 4;
+// how whisper picks tokens, for the run report
+pub const SAMPLING: &str = "greedy, best of 1";
+
+/// A decoded segment, reported as soon as whisper finishes it
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    pub index: i32,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+/// A word and how sure whisper was of it, from 0 to 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub confidence: f32,
+}
+
+/// The text with per-word confidence for highlighting the unsure words;
+/// `confidence` is the average, `None` when the decoder didn't report any
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Transcript {
+    pub text: String,
+    pub words: Vec<Word>,
+    pub confidence: Option<f32>,
 }
 
 impl Transcriber {
     pub fn new(model_path: &PathBuf) -> Result<Self> {
-        info!("loading whisper model from: {:?}", model_path);
+        Self::load(model_path, backend()).map(|(transcriber, _)| transcriber)
+    }
+
+    // on `backend`, or the cpu from now on when the gpu can't take the model
+    // (no device, not enough vram)
+    fn load(model_path: &PathBuf, backend: Backend) -> Result<(Self, Backend)> {
+        match Self::on_backend(model_path, backend) {
+            Err(e) if backend != Backend::Cpu && model_path.exists() => {
+                warn!("failed to load the model on {} ({:#}), decoding on the cpu", backend.name(), e);
+                *BACKEND.lock() = Backend::Cpu;
+                Ok((Self::on_backend(model_path, Backend::Cpu)?, Backend::Cpu))
+            }
+            loaded => loaded.map(|transcriber| (transcriber, backend)),
+        }
+    }
+
+    fn on_backend(model_path: &PathBuf, backend: Backend) -> Result<Self> {
+        info!("loading whisper model from: {:?} ({})", model_path, backend.name());
 
         if !model_path.exists() {
             anyhow::bail!("model file not found: {:?}", model_path);
         }
 
-        let context_params = WhisperContextParameters::default();
+        let mut context_params = WhisperContextParameters::default();
+        context_params.use_gpu(backend != Backend::Cpu);
         let context = WhisperContext::new_with_params(
             model_path.to_str().context("invalid model path")?,
             context_params,
@@ -24,10 +156,147 @@ impl Transcriber {
 
         info!("whisper model loaded successfully");
 
-        Ok(Self { context })
+        Ok(Self::with_context(Arc::new(context)))
+    }
+
+    // default settings on a loaded model
+    fn with_context(context: Arc<WhisperContext>) -> Self {
+        Self {
+            context,
+            threads: DEFAULT_THREADS,
+            paragraph_pause_ms: 0,
+            translate: false,
+            prompt: None,
+            suppressed: Arc::default(),
+            suppress_non_speech: false,
+        }
+    }
+
+    /// Like `new`, but reuses the context of a recently used model. Up to `capacity`
+    /// models stay loaded; older ones are unloaded first, and so many more as needed
+    /// for the new model to fit in the available memory.
+    pub fn cached(model_path: &PathBuf, capacity: usize) -> Result<Self> {
+        let backend = backend();
+        {
+            let mut cache = CONTEXTS.lock();
+            if let Some(position) = cache.iter().position(|(path, on, _)| path == model_path && *on == backend) {
+                let entry = cache.remove(position);
+                let context = entry.2.clone();
+                cache.push(entry);
+                return Ok(Self::with_context(context));
+            }
+
+            // a loaded model takes about its file size in memory
+            let needed = model_size(model_path);
+            let mut available = HardwareDetector::new()
+                .map(|detector| detector.available_memory_bytes())
+                .unwrap_or(u64::MAX);
+            while !cache.is_empty() && (cache.len() >= capacity || available < needed) {
+                let (path, on, _) = cache.remove(0);
+                info!("unloading whisper model {:?} ({})", path, on.name());
+                available = available.saturating_add(model_size(&path));
+            }
+        }
+
+        // loading takes seconds, the cached models stay usable meanwhile
+        let (transcriber, backend) = Self::load(model_path, backend)?;
+        if capacity > 0 {
+            let mut cache = CONTEXTS.lock();
+            // loaded twice at once, the later one replaces the first
+            cache.retain(|(path, on, _)| !(path == model_path && *on == backend));
+            cache.push((model_path.clone(), backend, transcriber.context.clone()));
+            let over = cache.len().saturating_sub(capacity);
+            cache.drain(..over);
+        }
+        Ok(transcriber)
+    }
+
+    /// Threads a decode runs on
+    pub fn threads(&self) -> usize {
+        self.threads as usize
+    }
+
+    /// Decode with `threads` threads, 0 keeps the default
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        if threads > 0 {
+            self.threads = threads as i32;
+        }
+        self
+    }
+
+    /// Start a new paragraph where the speaker paused this long, 0 keeps one paragraph
+    pub fn with_paragraphs(mut self, pause_ms: u64) -> Self {
+        self.paragraph_pause_ms = pause_ms as i64;
+        self
+    }
+
+    /// Write the speech in english, translated from the language spoken
+    pub fn with_translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    /// Start the decoder from `prompt`, e.g. the hotwords it should expect
+    pub fn with_prompt(mut self, prompt: Option<String>) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Keep whisper from writing what `rules` rule out, see `model.decode`
+    pub fn with_rules(mut self, rules: &DecodeRules) -> Self {
+        self.suppress_non_speech = rules.suppress_non_speech;
+        self.suppressed = Arc::new(if rules.is_empty() { Vec::new() } else { suppressed_tokens(&self.context, rules) });
+        self
+    }
+
+    /// Text tokens the decode rules out
+    pub fn suppressed(&self) -> usize {
+        self.suppressed.len()
     }
 
     pub fn transcribe(&self, audio_data: &[f32], language: &str) -> Result<String> {
+        self.transcribe_with_callback(audio_data, language, |_| {})
+    }
+
+    /// Like `transcribe`, but calls `on_segment` for every segment as it is decoded
+    pub fn transcribe_with_callback<F>(
+        &self,
+        audio_data: &[f32],
+        language: &str,
+        on_segment: F,
+    ) -> Result<String>
+    where
+        F: FnMut(Segment) + 'static,
+    {
+        self.transcribe_abortable(audio_data, language, &CancellationToken::new(), on_segment)
+    }
+
+    /// Like `transcribe_with_callback`, stopping early once `abort` is cancelled
+    pub fn transcribe_abortable<F>(
+        &self,
+        audio_data: &[f32],
+        language: &str,
+        abort: &CancellationToken,
+        on_segment: F,
+    ) -> Result<String>
+    where
+        F: FnMut(Segment) + 'static,
+    {
+        self.transcribe_scored(audio_data, language, abort, on_segment)
+            .map(|transcript| transcript.text)
+    }
+
+    /// Like `transcribe_abortable`, with the confidence of every word
+    pub fn transcribe_scored<F>(
+        &self,
+        audio_data: &[f32],
+        language: &str,
+        abort: &CancellationToken,
+        mut on_segment: F,
+    ) -> Result<Transcript>
+    where
+        F: FnMut(Segment) + 'static,
+    {
         info!("transcribing {} samples", audio_data.len());
 
         // create a state for this transcription
@@ -38,29 +307,171 @@ impl Transcriber {
 
         let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
         params.set_language(Some(language));
-        params.set_translate(false);
+        params.set_translate(self.translate);
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
-        params.set_n_threads(4);
+        params.set_n_threads(self.threads);
+        if let Some(prompt) = &self.prompt {
+            params.set_initial_prompt(prompt);
+        }
+        params.set_suppress_nst(self.suppress_non_speech);
+        // kept alive until the decode is done, the filter reads it through the pointer
+        let suppressed = self.suppressed.clone();
+        if !suppressed.is_empty() {
+            // SAFETY: the callback only reads the vec, which outlives `state.full` below
+            unsafe {
+                params.set_filter_logits_callback(Some(suppress_logits));
+                params.set_filter_logits_callback_user_data(Arc::as_ptr(&suppressed) as *mut c_void);
+            }
+        }
+
+        // whisper timestamps are in centiseconds
+        params.set_segment_callback_safe_lossy(move |data: whisper_rs::SegmentCallbackData| {
+            on_segment(Segment {
+                index: data.segment,
+                start_ms: data.start_timestamp * 10,
+                end_ms: data.end_timestamp * 10,
+                text: data.text.trim().to_string(),
+            });
+        });
+
+        let aborted = abort.clone();
+        params.set_abort_callback_safe(move || aborted.is_cancelled());
 
         // whisper aceita f32 diretamente agora
-        state
-            .full(params, audio_data)
-            .context("transcription failed")?;
+        let result = state.full(params, audio_data);
+        if abort.is_cancelled() {
+            anyhow::bail!("transcription aborted");
+        }
+        result.context("transcription failed")?;
 
         // iterar pelos segmentos usando o novo metodo as_iter
         let mut text = String::new();
+        let mut words: Vec<Word> = Vec::new();
+        let mut previous_end = None;
 
         for segment in state.as_iter() {
-            text.push_str(&segment.to_string());
-            text.push(' ');
+            // tokens are word pieces, a leading space starts the next word; a word
+            // is only as sure as its least sure piece
+            let mut word_start = true;
+            for token in (0..segment.n_tokens()).filter_map(|i| segment.get_token(i)) {
+                let Ok(piece) = token.to_str_lossy() else {
+                    continue;
+                };
+                // [_BEG_], [_TT_150], <|endoftext|> and the like
+                if piece.starts_with("[_") || piece.starts_with("<|") || piece.trim().is_empty() {
+                    continue;
+                }
+                let confidence = token.token_probability();
+                match words.last_mut() {
+                    Some(word) if !word_start && !piece.starts_with(' ') => {
+                        word.text.push_str(&piece);
+                        word.confidence = word.confidence.min(confidence);
+                    }
+                    _ => words.push(Word {
+                        text: piece.trim_start().to_string(),
+                        confidence,
+                    }),
+                }
+                word_start = false;
+            }
+
+            // whisper timestamps are in centiseconds
+            let start_ms = segment.start_timestamp() * 10;
+            if let Some(end_ms) = previous_end {
+                let paused = self.paragraph_pause_ms > 0 && start_ms - end_ms >= self.paragraph_pause_ms;
+                text.push_str(if paused { "\n\n" } else { " " });
+            }
+            text.push_str(segment.to_string().trim());
+            previous_end = Some(segment.end_timestamp() * 10);
         }
 
         let text = text.trim().to_string();
         info!("transcription complete: {} chars", text.len());
 
-        Ok(text)
+        let confidence = (!words.is_empty())
+            .then(|| words.iter().map(|w| w.confidence).sum::<f32>() / words.len() as f32);
+        Ok(Transcript {
+            text,
+            words,
+            confidence,
+        })
+    }
+}
+
+impl Transcriber {
+    /// Whether `cached` would reuse an already loaded context for this model
+    pub fn is_loaded(model_path: &Path) -> bool {
+        let backend = backend();
+        CONTEXTS.lock().iter().any(|(path, on, _)| path == model_path && *on == backend)
+    }
+
+    /// Drop every cached context, returning how many were loaded. Transcribers
+    /// still in use keep theirs until they're done.
+    pub fn unload_all() -> usize {
+        let unloaded = std::mem::take(&mut *CONTEXTS.lock());
+        for (path, on, _) in &unloaded {
+            info!("unloading whisper model {:?} ({})", path, on.name());
+        }
+        unloaded.len()
+    }
+
+    /// Approximate memory held by the cached contexts
+    pub fn loaded_bytes() -> u64 {
+        CONTEXTS.lock().iter().map(|(path, _, _)| model_size(path)).sum()
+    }
+}
+
+fn model_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+// signs that go with numbers in a digits-only decode
+const NUMBER_SIGNS: &str = ".,-+%:/";
+// punctuation outside ascii that whisper writes
+const PUNCTUATION: &str = "¿¡«»…“”„–—·";
+
+/// Text tokens of the vocabulary that `rules` rule out; special ones (end of
+/// text, timestamps) always stay
+fn suppressed_tokens(context: &WhisperContext, rules: &DecodeRules) -> Vec<i32> {
+    let suppress: Vec<String> = rules.suppress.iter().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+    let suppressed: Vec<i32> = (0..context.token_eot())
+        .filter(|&id| {
+            let Ok(bytes) = context.token_to_bytes(id) else {
+                return false;
+            };
+            let piece = String::from_utf8_lossy(bytes);
+            let piece = piece.trim();
+            if piece.is_empty() {
+                return false;
+            }
+            if rules.digits_only && !piece.chars().all(|c| c.is_ascii_digit() || NUMBER_SIGNS.contains(c)) {
+                return true;
+            }
+            let punctuation = |c: char| (c.is_ascii_punctuation() && c != '\'' && c != '-') || PUNCTUATION.contains(c);
+            if rules.no_punctuation && piece.chars().any(punctuation) {
+                return true;
+            }
+            suppress.iter().any(|s| piece.to_lowercase() == *s)
+        })
+        .collect();
+    info!("decode rules suppress {} tokens", suppressed.len());
+    suppressed
+}
+
+// whisper.cpp's logits filter: the suppressed tokens can never be picked
+unsafe extern "C" fn suppress_logits(
+    _context: *mut whisper_context,
+    _state: *mut whisper_state,
+    _tokens: *const whisper_token_data,
+    _n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    let suppressed = &*(user_data as *const Vec<i32>);
+    for &id in suppressed {
+        *logits.add(id as usize) = f32::NEG_INFINITY;
     }
 }
@@ -0,0 +1,54 @@
+//! Switching the model or the language from the tray with the mouse alone,
+//! following `ui.tray_cycle`; the tooltip shows the current one. Tauri's tray
+//! reports no wheel events, so the steps come from a middle click on the icon,
+//! the `cycle_tray_selection` command or `POST /cycle` of the local API, which
+//! bars like waybar can bind to scrolling over their own module.
+
+use tracing::info;
+
+use crate::config::{Config, TrayCycle};
+use crate::{memory, quick_pick, AppState, Event, WhisperiaError};
+
+/// What the tray switches now, `None` with `ui.tray_cycle` off
+pub fn selection(config: &Config) -> Option<String> {
+    match config.ui.tray_cycle {
+        TrayCycle::Off => None,
+        TrayCycle::Model => Some(config.model.local_model.clone()),
+        TrayCycle::Language => Some(config.language.clone()),
+    }
+}
+
+/// Move `step` places through the models or languages (negative goes back,
+/// wrapping around) and keep the new one in the config, returning it
+pub fn cycle(state: &AppState, step: i32) -> Result<String, WhisperiaError> {
+    let mut config = state.get_config();
+    let options: Vec<String> = match config.ui.tray_cycle {
+        TrayCycle::Off => return Err(WhisperiaError::Invalid("ui.tray_cycle is off".to_string())),
+        TrayCycle::Model => memory::installed_models(&config).into_iter().map(str::to_string).collect(),
+        TrayCycle::Language => quick_pick::languages(&config),
+    };
+    let current = selection(&config).unwrap_or_default();
+    let next = step_through(&options, &current, step)
+        .ok_or_else(|| WhisperiaError::ModelMissing("no whisper model is installed".to_string()))?;
+    match config.ui.tray_cycle {
+        TrayCycle::Model => config.model.local_model = next.clone(),
+        _ => config.language = next.clone(),
+    }
+    info!("Tray cycled to {}", next);
+    state.update_config(config)?;
+    // the tooltip follows the state events
+    state.publish(Event::State(state.get_status()));
+    Ok(next)
+}
+
+/// `step` places after `current`; from one not in `options` the first step
+/// lands on the first option, or the last going back
+fn step_through(options: &[String], current: &str, step: i32) -> Option<String> {
+    if options.is_empty() {
+        return None;
+    }
+    let len = options.len() as i64;
+    let outside = if step < 0 { len } else { -1 };
+    let position = options.iter().position(|o| o == current).map_or(outside, |p| p as i64);
+    Some(options[(position + step as i64).rem_euclid(len) as usize].clone())
+}
@@ -0,0 +1,155 @@
+//! Decode threads tuned to the machine: the first time a model is used with
+//! `model.threads = 0`, a few thread counts are timed on a short clip in the
+//! background and the fastest is kept in `threads.json` for that model, cpu
+//! and backend. Until then the default of 4 is used.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::{transcription, HardwareDetector, Transcriber};
+
+// seconds of audio timed for every thread count
+const CLIP_SECS: usize = 2;
+// slower counts in a row before giving up on fewer threads
+const PATIENCE: usize = 2;
+
+// one read-modify-write of the file at a time
+static FILE: Mutex<()> = Mutex::new(());
+// models being tuned right now
+static TUNING: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+static CPU: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Tuned {
+    model: String, // file name
+    cpu: String,   // brand and logical cores
+    backend: String,
+    threads: usize,
+}
+
+fn path() -> Result<PathBuf> {
+    Config::tuning_path()
+}
+
+fn load() -> Result<Vec<Tuned>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("failed to parse {:?}", path))
+}
+
+fn cpu() -> &'static str {
+    CPU.get_or_init(|| {
+        let name = HardwareDetector::new()
+            .map(|detector| detector.get_system_info().cpu_name)
+            .unwrap_or_default();
+        format!("{} ({} threads)", name.trim(), logical_cores())
+    })
+}
+
+fn logical_cores() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+fn model_name(model_path: &Path) -> String {
+    model_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn lookup(model_path: &Path) -> Option<usize> {
+    let (model, backend) = (model_name(model_path), transcription::backend().name());
+    load()
+        .ok()?
+        .into_iter()
+        .find(|t| t.model == model && t.cpu == cpu() && t.backend == backend)
+        .map(|t| t.threads)
+}
+
+/// The threads to decode `model_path` with: `configured` when set, else the
+/// tuned count, else 0 for the transcriber's default
+pub fn threads(model_path: &Path, configured: usize) -> usize {
+    if configured > 0 {
+        return configured;
+    }
+    lookup(model_path).unwrap_or(0)
+}
+
+/// Time the thread counts for `model_path` on a background thread, unless the
+/// config sets them, they're known already or a run is on its way
+pub fn tune_in_background(model_path: &Path, config: &Config) {
+    if config.model.threads > 0 || lookup(model_path).is_some() {
+        return;
+    }
+    if !TUNING.lock().get_or_insert_with(HashSet::new).insert(model_path.to_path_buf()) {
+        return;
+    }
+    let (model_path, cached_models) = (model_path.to_path_buf(), config.model.cached_models);
+    std::thread::spawn(move || {
+        match tune(&model_path, cached_models) {
+            Ok(threads) => info!("decoding {} with {} threads from now on", model_name(&model_path), threads),
+            Err(e) => warn!("failed to tune decode threads: {:#}", e),
+        }
+        if let Some(tuning) = TUNING.lock().as_mut() {
+            tuning.remove(&model_path);
+        }
+    });
+}
+
+fn tune(model_path: &Path, cached_models: usize) -> Result<usize> {
+    let model_path = model_path.to_path_buf();
+    // kept loaded between the runs even when the cache is off
+    let transcriber = || Transcriber::cached(&model_path, cached_models.max(1));
+    // quiet noise rather than digital silence, so the decoder doesn't stop right away
+    let clip: Vec<f32> = (0..CLIP_SECS * 16000).map(|i| ((i * 7919 % 200) as f32 - 100.0) / 20000.0).collect();
+    // from all of them down, the few-thread runs of a big model are the slow ones to time
+    let cores = logical_cores();
+    let mut candidates = vec![cores];
+    let mut threads = cores.next_power_of_two() / 2;
+    while threads > 0 {
+        candidates.push(threads);
+        threads /= 2;
+    }
+    info!("tuning decode threads for {:?}: {:?}", model_path, candidates);
+
+    // once to warm up, so loading isn't timed for the first count
+    transcriber()?.transcribe(&clip, "en")?;
+    let mut best = (0, Duration::MAX);
+    let mut slower = 0;
+    for threads in candidates {
+        let transcriber = transcriber()?.with_threads(threads);
+        let started = Instant::now();
+        transcriber.transcribe(&clip, "en")?;
+        let took = started.elapsed();
+        info!("{} threads: {:?}", threads, took);
+        if took < best.1 {
+            best = (threads, took);
+            slower = 0;
+        } else {
+            slower += 1;
+            if slower >= PATIENCE {
+                break;
+            }
+        }
+    }
+
+    let tuned = Tuned {
+        model: model_name(&model_path),
+        cpu: cpu().to_string(),
+        backend: transcription::backend().name().to_string(),
+        threads: best.0,
+    };
+    let _file = FILE.lock();
+    let mut all = load()?;
+    all.retain(|t| !(t.model == tuned.model && t.cpu == tuned.cpu && t.backend == tuned.backend));
+    all.push(tuned);
+    std::fs::write(path()?, serde_json::to_string_pretty(&all)?).context("failed to write threads.json")?;
+    Ok(best.0)
+}
@@ -0,0 +1,183 @@
+//! Two-pass dictation: the draft model's transcript is typed right away, then
+//! the configured model decodes the same audio in the background. When it
+//! heard something else the draft is erased and the refined text typed in its
+//! place, at once or when the overlay's offer is accepted. Erasing is done
+//! with backspaces, so a new dictation makes an offer stale.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::config::{Config, RefineReplace};
+use crate::{
+    get_model_path, hotwords, memory, permissions, postprocess, routing, symbols, tuning, AppState, Event, Glossary,
+    HistoryEntry, InputSimulator, InsertTarget, Transcriber, WhisperiaError,
+};
+
+// bumped by every dictation, a refinement of an older one is dropped
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+
+/// The draft typed and what the configured model made of the same audio
+#[derive(Debug, Clone, Serialize)]
+pub struct Refinement {
+    pub draft: String,
+    pub text: String,
+}
+
+struct Pending {
+    refinement: Refinement,
+    target: InsertTarget,
+    language: String,
+}
+
+/// The config to decode the draft with, `None` when two-pass is off or the
+/// draft model isn't installed
+pub fn draft_config(config: &Config) -> Option<Config> {
+    let two_pass = &config.two_pass;
+    if !two_pass.enabled || two_pass.draft_model == config.model.local_model {
+        return None;
+    }
+    let mut draft = config.clone();
+    draft.model.local_model = two_pass.draft_model.clone();
+    get_model_path(&draft).ok()?;
+    Some(draft)
+}
+
+/// A new dictation started, the cursor isn't after the last draft anymore
+pub fn invalidate(state: &AppState) {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    if PENDING.lock().take().is_some() {
+        state.publish(Event::Refinement(None));
+    }
+}
+
+/// Decode `audio` with the configured model in the background and replace
+/// or offer to replace the `draft` typed into `target`
+pub fn refine(app: AppHandle, config: Config, audio: Vec<f32>, draft: String, target: InsertTarget) {
+    let generation = GENERATION.load(Ordering::SeqCst);
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let text = match decode(&config, audio, target.window.as_ref().map(|w| w.app_id.clone())).await {
+            Ok(Some(text)) if text != draft => text,
+            Ok(_) => {
+                info!("the refined transcript matches the draft");
+                return;
+            }
+            Err(e) => {
+                warn!("failed to refine the draft: {:#}", e);
+                return;
+            }
+        };
+        if GENERATION.load(Ordering::SeqCst) != generation {
+            info!("another dictation started, dropping the refined transcript");
+            return;
+        }
+
+        let pending = Pending {
+            refinement: Refinement { draft, text },
+            target,
+            language: config.language.clone(),
+        };
+        match config.two_pass.replace {
+            RefineReplace::Auto => {
+                if let Err(e) = replace(&state, pending).await {
+                    warn!("failed to replace the draft: {:#}", e);
+                    state.publish(Event::Error(WhisperiaError::from(e)));
+                }
+            }
+            RefineReplace::Offer => {
+                info!("offering the refined transcript");
+                state.publish(Event::Refinement(Some(pending.refinement.clone())));
+                *PENDING.lock() = Some(pending);
+                if let Some(overlay) = app.get_webview_window("overlay") {
+                    let _ = overlay.show();
+                }
+            }
+        }
+    });
+}
+
+/// Type the offered refinement over its draft, see `accept_refinement`
+pub async fn accept(state: &AppState) -> Result<(), WhisperiaError> {
+    let pending = PENDING
+        .lock()
+        .take()
+        .ok_or_else(|| WhisperiaError::Invalid("no refined transcript to accept".to_string()))?;
+    state.publish(Event::Refinement(None));
+    Ok(replace(state, pending).await?)
+}
+
+/// Keep the draft, see `dismiss_refinement`
+pub fn dismiss(state: &AppState) {
+    if PENDING.lock().take().is_some() {
+        state.publish(Event::Refinement(None));
+    }
+}
+
+async fn decode(config: &Config, audio: Vec<f32>, app_id: Option<String>) -> Result<Option<String>> {
+    let (model_path, _) = memory::pick_model(config)?;
+    let threads = tuning::threads(&model_path, config.model.threads);
+    let (cached_models, pause_ms) = (config.model.cached_models, config.output.paragraph_pause_ms);
+    let (language, prompt) = (config.language.clone(), hotwords::prompt(&config.model.hotwords));
+    let (rules, translate) = (config.model.decode.clone(), config.model.translate);
+    let text = tauri::async_runtime::spawn_blocking(move || {
+        let transcriber = Transcriber::cached(&model_path, cached_models)?
+            .with_threads(threads)
+            .with_paragraphs(pause_ms)
+            .with_prompt(prompt)
+            .with_rules(&rules)
+            .with_translate(translate);
+        transcriber.transcribe(&audio, &language)
+    })
+    .await
+    .context("refinement task failed")??;
+
+    // the same steps the draft went through, a routed, chained or spelled one isn't refined
+    let text = if config.glossary.enabled {
+        Glossary::load().map(|glossary| glossary.apply(&text)).unwrap_or(text)
+    } else {
+        text
+    };
+    let text = hotwords::apply(&text, &config.model.hotwords);
+    if routing::split_chain(&text, &config.routing).len() > 1 {
+        return Ok(None);
+    }
+    let text = match routing::parse(&text, &config.routing) {
+        routing::Route::Type(text) if config.routing.spoken_symbols => symbols::replace(&text, &symbols::load()),
+        routing::Route::Type(text) => text,
+        _ => return Ok(None),
+    };
+    Ok(Some(postprocess::process(&text, config, app_id.as_deref())))
+}
+
+async fn replace(state: &AppState, pending: Pending) -> Result<()> {
+    let Pending { refinement, target, language } = pending;
+    let Refinement { draft, text } = refinement.clone();
+    info!("replacing the draft with the refined transcript");
+    tauri::async_runtime::spawn_blocking(move || -> Result<()> {
+        // backspaces into any other window would delete its text, so nothing is
+        // erased unless the draft's window is focused again
+        if let (Some(compositor), Some(window)) = (target.compositor, &target.window) {
+            compositor.focus_window(window).context("failed to restore focus, the draft was left as typed")?;
+            let focused = compositor.focused_window().map(|focused| focused.id);
+            if focused.as_deref() != Some(window.id.as_str()) {
+                anyhow::bail!("{} isn't focused, the draft was left as typed", window.app_id);
+            }
+        }
+        permissions::ensure_accessibility()?;
+        let mut input = InputSimulator::new().map_err(WhisperiaError::input_blocked)?;
+        input.erase(draft.chars().count()).map_err(WhisperiaError::input_blocked)?;
+        input.type_text(&text).map_err(WhisperiaError::input_blocked)?;
+        Ok(())
+    })
+    .await
+    .context("replacing the draft failed")??;
+
+    state.set_result(refinement.text.clone());
+    state.add_history(HistoryEntry::new(&refinement.text, &language, "refined").with_original(&refinement.draft));
+    Ok(())
+}
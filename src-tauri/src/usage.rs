@@ -0,0 +1,120 @@
+//! What the cloud providers were used for this month: requests, seconds of
+//! audio and tokens, kept in `usage.json` in the data directory. The cost is
+//! estimated from the `[usage]` prices, so changing them re-prices the month.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::config::{Config, UsageConfig};
+
+// one read-modify-write of the file at a time
+static FILE: Mutex<()> = Mutex::new(());
+// the month a budget warning was already given for
+static WARNED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Usage of one provider in one month
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    pub provider: String,
+    pub month: String, // "2024-05"
+    pub requests: u64,
+    pub audio_secs: f64,
+    pub tokens: u64,
+    #[serde(default)]
+    pub cost: f64, // estimated, filled in when reported
+}
+
+/// This month's usage, for `get_api_usage`
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiUsage {
+    pub month: String,
+    pub providers: Vec<ProviderUsage>,
+    pub cost: f64,
+    pub budget: Option<f64>,
+}
+
+fn path() -> Result<PathBuf> {
+    Config::usage_path()
+}
+
+fn month() -> String {
+    chrono::Local::now().format("%Y-%m").to_string()
+}
+
+fn load() -> Result<Vec<ProviderUsage>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("failed to parse {:?}", path))
+}
+
+/// Add one request to this month's usage of `provider`. Failing to write it
+/// never fails the request itself.
+pub fn record(provider: &str, audio_secs: f64, tokens: u64) {
+    let _file = FILE.lock();
+    let result = load().and_then(|mut usage| {
+        let month = month();
+        let index = match usage.iter().position(|u| u.provider == provider && u.month == month) {
+            Some(index) => index,
+            None => {
+                usage.push(ProviderUsage {
+                    provider: provider.to_string(),
+                    month,
+                    ..Default::default()
+                });
+                usage.len() - 1
+            }
+        };
+        let entry = &mut usage[index];
+        entry.requests += 1;
+        entry.audio_secs += audio_secs;
+        entry.tokens += tokens;
+        std::fs::write(path()?, serde_json::to_string_pretty(&usage)?).context("failed to write usage.json")
+    });
+    if let Err(e) = result {
+        warn!("failed to record api usage: {:#}", e);
+    }
+}
+
+fn estimate(usage: &ProviderUsage, config: &UsageConfig) -> f64 {
+    usage.audio_secs / 60.0 * config.audio_cost_per_minute + usage.tokens as f64 / 1000.0 * config.llm_cost_per_1k_tokens
+}
+
+pub fn this_month(config: &UsageConfig) -> Result<ApiUsage> {
+    let month = month();
+    let providers: Vec<ProviderUsage> = load()?
+        .into_iter()
+        .filter(|u| u.month == month)
+        .map(|mut u| {
+            u.cost = estimate(&u, config);
+            u
+        })
+        .collect();
+    Ok(ApiUsage {
+        cost: providers.iter().map(|u| u.cost).sum(),
+        month,
+        providers,
+        budget: config.monthly_budget,
+    })
+}
+
+/// `(spent, budget)` the first time this month's estimate passes `warn_at` of the budget
+pub fn budget_warning(config: &UsageConfig) -> Option<(f64, f64)> {
+    let budget = config.monthly_budget.filter(|b| *b > 0.0)?;
+    let usage = this_month(config).ok()?;
+    if usage.cost < budget * config.warn_at as f64 {
+        return None;
+    }
+    let mut warned = WARNED.lock();
+    if warned.as_deref() == Some(usage.month.as_str()) {
+        return None;
+    }
+    info!("api usage at {:.2} of a {:.2} monthly budget", usage.cost, budget);
+    *warned = Some(usage.month);
+    Some((usage.cost, budget))
+}
@@ -0,0 +1,101 @@
+//! Voice activity detection by loudness: 30 ms frames louder than the
+//! threshold are speech. An utterance starts at the first speech frame, keeps
+//! a little audio from before it so the first syllable isn't cut, and ends
+//! after `silence_ms` without speech.
+
+use std::collections::VecDeque;
+
+use crate::config::OpenMicConfig;
+
+// 30 ms at 16khz
+const FRAME: usize = 480;
+// audio kept from before the speech started
+const PREROLL_FRAMES: usize = 10;
+
+pub struct Vad {
+    threshold: f32,
+    silence_frames: usize,
+    min_speech_frames: usize,
+    max_frames: usize,
+    pending: Vec<f32>,
+    preroll: VecDeque<Vec<f32>>,
+    utterance: Vec<f32>,
+    speech: usize, // speech frames in the utterance
+    silent: usize, // frames since the last speech frame
+}
+
+impl Vad {
+    pub fn new(config: &OpenMicConfig) -> Self {
+        let frames = |ms: u64| (ms as usize * 16).div_ceil(FRAME).max(1);
+        Self {
+            threshold: config.threshold,
+            silence_frames: frames(config.silence_ms),
+            min_speech_frames: frames(config.min_speech_ms),
+            max_frames: frames(config.max_utterance_ms),
+            pending: Vec::new(),
+            preroll: VecDeque::new(),
+            utterance: Vec::new(),
+            speech: 0,
+            silent: 0,
+        }
+    }
+
+    /// Forget the utterance in progress, e.g. while a dictation has the microphone
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.preroll.clear();
+        self.utterance.clear();
+        self.speech = 0;
+        self.silent = 0;
+    }
+
+    /// Feed 16khz mono audio, returning the utterances that ended in it.
+    /// Ones with less than `min_speech_ms` of speech are dropped as noise.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        self.pending.extend_from_slice(samples);
+        let mut finished = Vec::new();
+
+        let frames: Vec<Vec<f32>> = self.pending.chunks_exact(FRAME).map(<[f32]>::to_vec).collect();
+        self.pending.drain(..frames.len() * FRAME);
+
+        for frame in frames {
+            let loud = rms(&frame) >= self.threshold;
+            if self.utterance.is_empty() {
+                if !loud {
+                    self.preroll.push_back(frame);
+                    if self.preroll.len() > PREROLL_FRAMES {
+                        self.preroll.pop_front();
+                    }
+                    continue;
+                }
+                self.utterance.extend(self.preroll.drain(..).flatten());
+            }
+
+            self.utterance.extend_from_slice(&frame);
+            if loud {
+                self.speech += 1;
+                self.silent = 0;
+            } else {
+                self.silent += 1;
+            }
+
+            let frames = self.utterance.len() / FRAME;
+            if self.silent >= self.silence_frames || frames >= self.max_frames {
+                let utterance = std::mem::take(&mut self.utterance);
+                if self.speech >= self.min_speech_frames {
+                    finished.push(utterance);
+                }
+                self.speech = 0;
+                self.silent = 0;
+            }
+        }
+        finished
+    }
+}
+
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
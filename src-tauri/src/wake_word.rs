@@ -0,0 +1,116 @@
+//! Hands-free activation: listen for a phrase ("hey whisperia") and start the
+//! normal dictation when it's heard. Cheap by design: whisper only runs on
+//! short windows that are loud enough to hold speech, with the tiny model.
+//! Audio is never stored, only the last window is kept in memory.
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::vad::rms;
+use crate::{
+    get_model_path, trigger_transcription_flow, AppState, AudioRecorder, Event, Transcriber,
+    WhisperiaError,
+};
+
+// how often captured audio is picked up from the device
+const POLL: Duration = Duration::from_millis(250);
+// how often the window is checked for the phrase
+const STEP: Duration = Duration::from_millis(1000);
+
+/// Start listening, does nothing when already listening
+pub fn start(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let Some(cancel) = state.begin_wake_word() else {
+        return;
+    };
+    info!("listening for the wake word '{}'", state.get_config().wake_word.phrase);
+    state.publish(Event::State(state.get_status()));
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        if let Err(e) = listen(&app, &cancel) {
+            error!("wake word listener failed: {:#}", e);
+            state.publish(Event::Error(WhisperiaError::from(e)));
+        }
+        if !cancel.is_cancelled() {
+            stop(&state);
+        }
+    });
+}
+
+pub fn stop(state: &AppState) {
+    if state.stop_wake_word() {
+        info!("stopped listening for the wake word");
+        state.publish(Event::State(state.get_status()));
+    }
+}
+
+fn listen(app: &AppHandle, cancel: &CancellationToken) -> Result<()> {
+    let state = app.state::<AppState>();
+    let config = state.get_config();
+    let wake = &config.wake_word;
+
+    let mut small = config.clone();
+    small.model.local_model = wake.model.clone();
+    let transcriber = Transcriber::new(&get_model_path(&small)?)?.with_threads(config.model.threads);
+    let recorder = AudioRecorder::with_device(wake.source.as_deref())?;
+    let rate = recorder.sample_rate();
+
+    let phrase = normalize(&wake.phrase);
+    let window = wake.window_ms.max(500) as usize * 16;
+    let mut audio: Vec<f32> = Vec::with_capacity(window * 2);
+    let mut checked_at = Instant::now();
+
+    recorder.stream_until_stopped(cancel, POLL, |samples| {
+        // the dictation has the microphone, and its audio isn't ours to look at
+        if state.get_status().pipeline.is_busy() {
+            audio.clear();
+            return;
+        }
+        audio.extend(AudioRecorder::resample(&samples, rate, 16000));
+        let old = audio.len().saturating_sub(window);
+        audio.drain(..old);
+        if checked_at.elapsed() < STEP || rms(&audio) < wake.threshold {
+            return;
+        }
+        checked_at = Instant::now();
+
+        let heard = transcriber.transcribe_abortable(&audio, &config.language, cancel, |_| {});
+        let heard = match heard {
+            Ok(text) => text,
+            Err(_) if cancel.is_cancelled() => return,
+            Err(e) => {
+                warn!("wake word check failed: {:#}", e);
+                return;
+            }
+        };
+        if matches(&normalize(&heard), &phrase) {
+            info!("wake word heard");
+            audio.clear();
+            if let Err(e) = trigger_transcription_flow(app.clone(), None) {
+                error!("failed to trigger transcription from the wake word: {}", e);
+            }
+        }
+    })
+}
+
+/// Lowercase words without punctuation, whisper writes "Hey, Whisperia!"
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn matches(heard: &str, phrase: &str) -> bool {
+    // "hey whisper ia" is still the phrase
+    let squash = |s: &str| s.replace(' ', "");
+    !phrase.is_empty() && (heard.contains(phrase) || squash(heard).contains(&squash(phrase)))
+}
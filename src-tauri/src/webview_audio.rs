@@ -0,0 +1,54 @@
+//! Recording in the webview instead of through cpal, for machines where cpal
+//! can't open the microphone (sandboxed packages, unusual audio stacks): the
+//! overlay records with getUserMedia for as long as it's told and hands the
+//! audio back through `submit_webview_audio`. From there it's resampled and
+//! decoded like audio from the input device.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::pipeline::Cancelled;
+use crate::{audio, AppState, Event};
+
+/// Samples at the rate they were recorded
+pub type Recorded = (Vec<f32>, u32);
+
+/// Ask the overlay to record for `seconds` and wait up to `limit` for the
+/// audio, at the rate the webview recorded it
+pub async fn record(state: &AppState, seconds: u64, limit: Duration, cancel: &CancellationToken) -> Result<Recorded> {
+    let (tx, rx) = oneshot::channel();
+    state.set_webview_audio(Some(tx));
+    state.publish(Event::WebviewRecord(seconds * 1000));
+    info!("recording {}s in the webview", seconds);
+
+    let recorded = tokio::select! {
+        recorded = tokio::time::timeout(limit, rx) => recorded,
+        _ = cancel.cancelled() => {
+            state.set_webview_audio(None);
+            return Err(Cancelled.into());
+        }
+    };
+    state.set_webview_audio(None);
+    recorded
+        .context("the webview sent no audio in time")?
+        .context("the webview recording was abandoned")
+}
+
+/// Samples from the body of `submit_webview_audio`: 32 bit float mono at
+/// `sample_rate` when it's given, else a wav file
+pub fn decode(body: &[u8], sample_rate: Option<u32>) -> Result<Recorded> {
+    let Some(rate) = sample_rate else {
+        return Ok((audio::decode_wav(body)?, 16000));
+    };
+    if body.len() % 4 != 0 {
+        anyhow::bail!("webview audio is {} bytes, not a whole number of f32 samples", body.len());
+    }
+    let samples = body
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Ok((samples, rate))
+}
@@ -0,0 +1,216 @@
+//! Windows specifics: toast notifications, unicode typing through SendInput,
+//! the clipboard, detecting elevated windows we aren't allowed to type into,
+//! the animation accessibility setting, the microphone mute state, whether
+//! the focused control is a password field and the AppUserModelID the process
+//! runs as.
+
+use anyhow::{Context, Result};
+use std::ffi::c_void;
+use windows::core::{BOOL, HSTRING};
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HGLOBAL};
+use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+use windows::Win32::Media::Audio::{eCapture, eConsole, IMMDeviceEnumerator, MMDeviceEnumerator};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_ALL, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED,
+};
+use windows::Win32::UI::Accessibility::{CUIAutomation, IUIAutomation};
+use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Shell::SetCurrentProcessExplicitAppUserModelID;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, GetWindowThreadProcessId, SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION,
+    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+};
+
+const CF_UNICODETEXT: u32 = 13;
+
+/// Show a toast from the app registered under `app_id` by the installer
+pub fn show_toast(app_id: &str, title: &str, body: &str) -> Result<()> {
+    let xml = format!(
+        "<toast><visual><binding template=\"ToastGeneric\"><text>{}</text><text>{}</text></binding></visual></toast>",
+        escape_xml(title),
+        escape_xml(body)
+    );
+    let document = XmlDocument::new()?;
+    document.LoadXml(&HSTRING::from(xml))?;
+    let toast = ToastNotification::CreateToastNotification(&document)?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_id))?.Show(&toast)?;
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Type `text` as unicode key events, one per UTF-16 unit so accents and
+/// characters outside the BMP arrive intact whatever the keyboard layout.
+/// Fails when Windows accepted fewer events than were sent.
+pub fn send_unicode(text: &str) -> Result<()> {
+    let key = |unit: u16, up: bool| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: unit,
+                dwFlags: if up { KEYEVENTF_UNICODE | KEYEVENTF_KEYUP } else { KEYEVENTF_UNICODE },
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    let inputs: Vec<INPUT> = text
+        .encode_utf16()
+        .flat_map(|unit| [key(unit, false), key(unit, true)])
+        .collect();
+
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    if (sent as usize) < inputs.len() {
+        anyhow::bail!("SendInput accepted {} of {} key events", sent, inputs.len());
+    }
+    Ok(())
+}
+
+pub fn set_clipboard(text: &str) -> Result<()> {
+    let units: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        OpenClipboard(None).context("Failed to open the clipboard")?;
+        let result = (|| -> Result<()> {
+            EmptyClipboard()?;
+            let memory = GlobalAlloc(GMEM_MOVEABLE, units.len() * 2)?;
+            let target = GlobalLock(memory) as *mut u16;
+            if target.is_null() {
+                anyhow::bail!("Failed to lock clipboard memory");
+            }
+            std::ptr::copy_nonoverlapping(units.as_ptr(), target, units.len());
+            let _ = GlobalUnlock(memory);
+            // the clipboard owns the memory from here on
+            SetClipboardData(CF_UNICODETEXT, Some(HANDLE(memory.0)))?;
+            Ok(())
+        })();
+        let _ = CloseClipboard();
+        result
+    }
+}
+
+/// The unicode text on the clipboard, empty when it holds something else
+pub fn get_clipboard() -> Result<String> {
+    unsafe {
+        if IsClipboardFormatAvailable(CF_UNICODETEXT).is_err() {
+            return Ok(String::new());
+        }
+        OpenClipboard(None).context("Failed to open the clipboard")?;
+        let result = (|| -> Result<String> {
+            let data = GetClipboardData(CF_UNICODETEXT)?;
+            let memory = HGLOBAL(data.0);
+            let source = GlobalLock(memory) as *const u16;
+            if source.is_null() {
+                anyhow::bail!("Failed to lock clipboard memory");
+            }
+            let mut len = 0;
+            while *source.add(len) != 0 {
+                len += 1;
+            }
+            let text = String::from_utf16_lossy(std::slice::from_raw_parts(source, len));
+            let _ = GlobalUnlock(memory);
+            Ok(text)
+        })();
+        let _ = CloseClipboard();
+        result
+    }
+}
+
+/// File the process under `app_id`, the taskbar, notifications and the
+/// privacy settings then show it as the installed app
+pub fn set_app_user_model_id(app_id: &str) -> Result<()> {
+    unsafe { SetCurrentProcessExplicitAppUserModelID(&HSTRING::from(app_id)) }.context("Failed to set the AppUserModelID")
+}
+
+/// Windows silently drops input we send to a window running as administrator
+/// unless we are elevated too (UIPI)
+pub fn foreground_is_elevated() -> bool {
+    unsafe {
+        let mut pid = 0;
+        GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid as *mut u32));
+        if pid == 0 {
+            return false;
+        }
+        let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+            // not even allowed to query it, so it's elevated
+            return !is_elevated(GetCurrentProcess()).unwrap_or(false);
+        };
+        let elevated = is_elevated(process).unwrap_or(false);
+        let _ = CloseHandle(process);
+        elevated && !is_elevated(GetCurrentProcess()).unwrap_or(false)
+    }
+}
+
+unsafe fn is_elevated(process: HANDLE) -> Result<bool> {
+    let mut token = HANDLE::default();
+    OpenProcessToken(process, TOKEN_QUERY, &mut token)?;
+    let mut elevation = TOKEN_ELEVATION::default();
+    let mut size = 0;
+    let result = GetTokenInformation(
+        token,
+        TokenElevation,
+        Some(&mut elevation as *mut _ as *mut c_void),
+        std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+        &mut size,
+    );
+    let _ = CloseHandle(token);
+    result?;
+    Ok(elevation.TokenIsElevated != 0)
+}
+
+/// "Show animations in Windows" under accessibility, on unless the user turned it off
+pub fn animations_enabled() -> bool {
+    let mut enabled = BOOL(1);
+    let result = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut enabled as *mut BOOL as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    result.is_err() || enabled.as_bool()
+}
+
+/// Mute switch of the default capture endpoint, over WASAPI
+pub fn input_muted() -> Result<bool> {
+    unsafe {
+        // already initialized on this thread is fine too
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).context("no audio device enumerator")?;
+        let device = enumerator
+            .GetDefaultAudioEndpoint(eCapture, eConsole)
+            .context("no default input device")?;
+        let volume: IAudioEndpointVolume = device.Activate(CLSCTX_ALL, None)?;
+        Ok(volume.GetMute()?.as_bool())
+    }
+}
+
+/// UI Automation's password flag of the control with the keyboard focus
+pub fn focused_is_password() -> Result<bool> {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let automation: IUIAutomation =
+            CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER).context("no ui automation")?;
+        let focused = automation.GetFocusedElement().context("no focused control")?;
+        Ok(focused.CurrentIsPassword()?.as_bool())
+    }
+}
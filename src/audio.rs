@@ -1,12 +1,132 @@
 use anyhow::{Context, Result};
+#[cfg(feature = "capture")]
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+#[cfg(feature = "capture")]
 use cpal::{SampleFormat, StreamConfig};
+#[cfg(feature = "capture")]
 use std::io::Write;
+#[cfg(feature = "capture")]
 use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "capture")]
 use std::sync::{Arc, Mutex};
+#[cfg(feature = "capture")]
 use std::time::Instant;
+use std::path::Path;
+use std::process::Command;
 use tracing::info;
 
+#[cfg(feature = "capture")]
+use crate::i18n::{t, tf};
+
+/// Decode a wav file to 16khz mono samples
+pub fn decode_wav(reader: impl std::io::Read) -> Result<Vec<f32>> {
+    let reader = hound::WavReader::new(reader).context("invalid wav file")?;
+    let spec = reader.spec();
+    // a header claiming 0 or 200 bits would overflow the scale below
+    if !(1..=32).contains(&spec.bits_per_sample) {
+        anyhow::bail!("unsupported wav bit depth {}", spec.bits_per_sample);
+    }
+    if spec.sample_rate == 0 {
+        anyhow::bail!("invalid wav sample rate 0");
+    }
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<Result<_, _>>()
+            .context("failed to read wav samples")?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|v| v as f32 / scale))
+                .collect::<Result<_, _>>()
+                .context("failed to read wav samples")?
+        }
+    };
+
+    // downmix to mono
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    info!(
+        "decoded wav: {} samples, {} channels, {}hz",
+        mono.len(),
+        spec.channels,
+        spec.sample_rate
+    );
+
+    Ok(resample(&mono, spec.sample_rate, 16000))
+}
+
+fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || input.is_empty() {
+        return input.to_vec();
+    }
+
+    let ratio = to_rate as f64 / from_rate as f64;
+    let output_len = (input.len() as f64 * ratio) as usize;
+    let mut output = Vec::with_capacity(output_len);
+
+    for i in 0..output_len {
+        let src_idx = i as f64 / ratio;
+        let src_idx_floor = src_idx.floor() as usize;
+        let src_idx_ceil = (src_idx_floor + 1).min(input.len() - 1);
+        let t = src_idx - src_idx_floor as f64;
+
+        let sample = input[src_idx_floor] * (1.0 - t as f32) + input[src_idx_ceil] * t as f32;
+        output.push(sample);
+    }
+
+    output
+}
+
+// containers hound can't read, their audio track goes through ffmpeg
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "mov", "avi", "m4v"];
+
+/// Whether `path` is a video whose audio `extract_audio` can decode
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Demux and decode the first audio track of a video (screen recordings and
+/// the like) with ffmpeg, straight to 16khz mono
+pub fn extract_audio(path: &Path) -> Result<Vec<f32>> {
+    let output = Command::new("ffmpeg")
+        .args(["-nostdin", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args(["-vn", "-ac", "1", "-ar", "16000", "-f", "s16le", "-"])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("ffmpeg is needed to read video files, install it first"),
+            _ => anyhow::Error::from(e).context("failed to run ffmpeg"),
+        })?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ffmpeg could not read the audio of {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let samples: Vec<f32> = output
+        .stdout
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+        .collect();
+    if samples.is_empty() {
+        anyhow::bail!("{} has no audio track", path.display());
+    }
+    info!("extracted {} samples of audio from {}", samples.len(), path.display());
+    Ok(samples)
+}
+
+#[cfg(feature = "capture")]
 pub struct AudioRecorder {
     host: cpal::Host,
     device: cpal::Device,
@@ -14,6 +134,7 @@ pub struct AudioRecorder {
     sample_format: SampleFormat,
 }
 
+#[cfg(feature = "capture")]
 impl AudioRecorder {
     pub fn new() -> Result<Self> {
         let host = cpal::default_host();
@@ -98,45 +219,79 @@ impl AudioRecorder {
 
         // resample to 16khz if needed
         if self.config.sample_rate.0 != 16000 {
-            let resampled = Self::resample(&samples, self.config.sample_rate.0, 16000);
+            let resampled = resample(&samples, self.config.sample_rate.0, 16000);
             Ok(resampled)
         } else {
             Ok(samples)
         }
     }
 
-    fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-        if from_rate == to_rate {
-            return input.to_vec();
-        }
+    /// Record until `stopped` says so (asked every 50ms) or `max_seconds` have
+    /// passed, resampled to 16khz
+    pub fn record_until(&self, max_seconds: u64, mut stopped: impl FnMut() -> bool) -> Result<Vec<f32>> {
+        info!("recording for up to {} seconds...", max_seconds);
+
+        let samples_needed = (self.config.sample_rate.0 as u64 * max_seconds) as usize;
+        let recorded_samples = Arc::new(Mutex::new(Vec::new()));
+
+        let err_fn = move |err| {
+            eprintln!("audio stream error: {}", err);
+        };
 
-        let ratio = to_rate as f64 / from_rate as f64;
-        let output_len = (input.len() as f64 * ratio) as usize;
-        let mut output = Vec::with_capacity(output_len);
+        let stream = match self.sample_format {
+            SampleFormat::F32 => {
+                let samples = recorded_samples.clone();
+                self.device.build_input_stream(
+                    &self.config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                        let mut vec = samples.lock().unwrap();
+                        let room = samples_needed.saturating_sub(vec.len());
+                        vec.extend(data.iter().take(room));
+                    },
+                    err_fn,
+                    None,
+                )?
+            }
+            SampleFormat::I16 => {
+                let samples = recorded_samples.clone();
+                self.device.build_input_stream(
+                    &self.config,
+                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                        let mut vec = samples.lock().unwrap();
+                        let room = samples_needed.saturating_sub(vec.len());
+                        vec.extend(data.iter().take(room).map(|&sample| sample as f32 / 32768.0));
+                    },
+                    err_fn,
+                    None,
+                )?
+            }
+            _ => anyhow::bail!("unsupported sample format"),
+        };
 
-        for i in 0..output_len {
-            let src_idx = i as f64 / ratio;
-            let src_idx_floor = src_idx.floor() as usize;
-            let src_idx_ceil = (src_idx_floor + 1).min(input.len() - 1);
-            let t = src_idx - src_idx_floor as f64;
+        stream.play()?;
 
-            let sample = input[src_idx_floor] * (1.0 - t as f32) + input[src_idx_ceil] * t as f32;
-            output.push(sample);
+        let deadline = Instant::now() + std::time::Duration::from_secs(max_seconds);
+        while Instant::now() < deadline && !stopped() {
+            std::thread::sleep(std::time::Duration::from_millis(50));
         }
 
-        output
+        drop(stream);
+
+        let samples = recorded_samples.lock().unwrap().clone();
+        info!("recorded {} samples", samples.len());
+        Ok(resample(&samples, self.config.sample_rate.0, 16000))
     }
 
     pub fn record_until_interrupt(&self) -> Result<Vec<f32>> {
         info!("recording until ctrl+c...");
-        println!("gravando... pressione ctrl+c para parar");
+        println!("{}", t("cli.press_ctrl_c"));
 
         let should_stop = Arc::new(AtomicBool::new(false));
         let should_stop_clone = should_stop.clone();
 
         // setup ctrl+c handler
         ctrlc::set_handler(move || {
-            println!("\nparando gravacao...");
+            println!("\n{}", t("cli.stopping"));
             should_stop_clone.store(true, Ordering::SeqCst);
         })
         .expect("error setting ctrl+c handler");
@@ -198,10 +353,8 @@ impl AudioRecorder {
             if elapsed > 0 && elapsed % 5 == 0 {
                 let mins = elapsed / 60;
                 let secs = elapsed % 60;
-                print!(
-                    "\rgravando: {:02}:{:02} - pressione ctrl+c para parar",
-                    mins, secs
-                );
+                let elapsed = format!("{:02}:{:02}", mins, secs);
+                print!("\r{}", tf("cli.elapsed", &[&elapsed]));
                 std::io::stdout().flush().unwrap();
             }
         }
@@ -214,14 +367,12 @@ impl AudioRecorder {
             samples.len(),
             samples.len() / self.config.sample_rate.0 as usize
         );
-        println!(
-            "\ngravacao finalizada: {} segundos",
-            samples.len() / self.config.sample_rate.0 as usize
-        );
+        let seconds = samples.len() / self.config.sample_rate.0 as usize;
+        println!("\n{}", tf("cli.recorded", &[&seconds]));
 
         // resample to 16khz if needed
         if self.config.sample_rate.0 != 16000 {
-            let resampled = Self::resample(&samples, self.config.sample_rate.0, 16000);
+            let resampled = resample(&samples, self.config.sample_rate.0, 16000);
             Ok(resampled)
         } else {
             Ok(samples)
@@ -3,6 +3,8 @@ use serde::Deserialize;
 use sysinfo::System;
 use tracing::{info, warn};
 
+use crate::config::Config;
+
 pub struct HardwareDetector {
     sys: System,
 }
@@ -15,6 +17,9 @@ pub struct SystemInfo {
     pub cpu_name: String,
     pub has_gpu: bool,
     pub gpu_vram_gb: Option<u64>,
+    pub arch: String,
+    pub neon: bool,      // arm simd, whisper.cpp is several times slower without it
+    pub low_power: bool, // raspberry pi class arm board, see `is_low_power_arm`
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +55,9 @@ impl HardwareDetector {
                 .unwrap_or_default(),
             has_gpu: false, // will be updated if gpu detection is enabled
             gpu_vram_gb: None,
+            arch: std::env::consts::ARCH.to_string(),
+            neon: has_neon(),
+            low_power: is_low_power_arm(),
         }
     }
 
@@ -104,6 +112,12 @@ impl HardwareDetector {
         if let Some(vram) = info.gpu_vram_gb {
             info!("  gpu vram: {} gb", vram);
         }
+        if info.arch.starts_with("arm") || info.arch == "aarch64" {
+            info!("  arch: {}, neon: {}", info.arch, if info.neon { "yes" } else { "no" });
+        }
+        if info.low_power {
+            info!("  low-power arm board: tiny/base quantized models recommended");
+        }
 
         info!("available whisper models:");
         for model in self.get_available_models() {
@@ -116,10 +130,11 @@ impl HardwareDetector {
     }
 
     pub async fn check_huggingface_model(&self, model_id: &str) ->  Result<HuggingFaceCompatibility> {
-        let client = reqwest::Client::new();
+        Config::ensure_online("the hugging face model check")?;
+        let client = Config::hub_client()?;
         
-        // try to fetch model info from huggingface api
-        let api_url = format!("https://huggingface.co/api/models/{}", model_id);
+        // try to fetch model info from huggingface api (or the configured mirror)
+        let api_url = Config::hub_url(&format!("https://huggingface.co/api/models/{}", model_id));
         
         let response = client.get(&api_url)
             .timeout(std::time::Duration::from_secs(10))
@@ -219,6 +234,48 @@ impl HardwareDetector {
     }
 }
 
+/// NEON support of the cpu, always there on aarch64 but optional on 32-bit arm
+pub fn has_neon() -> bool {
+    #[cfg(target_arch = "aarch64")]
+    {
+        std::arch::is_aarch64_feature_detected!("neon")
+    }
+    #[cfg(target_arch = "arm")]
+    {
+        std::fs::read_to_string("/proc/cpuinfo")
+            .map(|cpuinfo| {
+                cpuinfo
+                    .lines()
+                    .filter(|line| line.starts_with("Features"))
+                    .any(|line| line.split_whitespace().any(|flag| flag == "neon"))
+            })
+            .unwrap_or(false)
+    }
+    #[cfg(not(any(target_arch = "aarch64", target_arch = "arm")))]
+    {
+        false
+    }
+}
+
+/// An arm single board computer like a raspberry pi: a known board model in the
+/// device tree, or few cores and little memory
+pub fn is_low_power_arm() -> bool {
+    if !cfg!(any(target_arch = "aarch64", target_arch = "arm")) {
+        return false;
+    }
+    let board = std::fs::read_to_string("/proc/device-tree/model").unwrap_or_default();
+    if ["Raspberry Pi", "Orange Pi", "Rock Pi", "Pine64"]
+        .iter()
+        .any(|name| board.contains(name))
+    {
+        return true;
+    }
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let memory_gb = System::new_all().total_memory() / 1024 / 1024 / 1024;
+    cores <= 4 && memory_gb <= 8
+}
+
 #[derive(Debug, Clone)]
 pub struct HuggingFaceCompatibility {
     pub model_id: String,
@@ -0,0 +1,69 @@
+//! Dictations of the daemon and of --transcribe/--interactive in the app's
+//! `history.jsonl`, so they can be found in its history like its own. The
+//! lines are kept as they are, the app writes fields this doesn't know.
+//! Failing to write the file never fails the dictation, it's only logged.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::config::{Config, HistoryConfig};
+use crate::postprocess;
+
+/// Add a transcription, with `history.persist`, masked like the app's
+pub fn add(config: &Config, text: &str, source: &str) {
+    if !config.history.persist || text.is_empty() {
+        return;
+    }
+    let text = if config.redaction.scope.history() {
+        postprocess::redact(text, &config.redaction)
+    } else {
+        text.to_string()
+    };
+    if let Err(e) = push(&config.history, &text, &config.language, source) {
+        warn!("failed to save the history entry: {:#}", e);
+    }
+}
+
+fn push(config: &HistoryConfig, text: &str, language: &str, source: &str) -> Result<()> {
+    let path = Config::history_path()?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {:?}", path)),
+    };
+    let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+
+    // the app's ids: unix milliseconds, past the last one given
+    let last_id = lines
+        .iter()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok()?.get("id")?.as_u64())
+        .max()
+        .unwrap_or(0);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let entry = serde_json::json!({
+        "id": (now.as_millis() as u64).max(last_id + 1),
+        "text": text,
+        "language": language,
+        "timestamp": now.as_secs(),
+        "source": source,
+    })
+    .to_string();
+
+    // dropping the oldest rewrites the file, otherwise the entry is only appended
+    let max_entries = config.max_entries.max(1);
+    if lines.len() >= max_entries {
+        let mut kept = lines[lines.len() + 1 - max_entries..].to_vec();
+        kept.push(&entry);
+        std::fs::write(&path, kept.join("\n") + "\n").with_context(|| format!("failed to write {:?}", path))?;
+    } else {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {:?}", path))?;
+        writeln!(file, "{}", entry)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! `whisperia ctl`: scripts and the keybinding daemons of tiling window
+//! managers drive the running daemon through a unix socket (a named pipe on
+//! windows) instead of the global hotkeys, the same one the app listens on.
+//! A connection sends one command on a line and reads the answer until the
+//! daemon hangs up; a failure comes back as `error: ...`.
+
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+
+use crate::config::Config;
+
+/// What `status` and `last-result` answer, kept up to date by the daemon loop
+#[cfg(feature = "desktop")]
+#[derive(Debug)]
+pub struct DaemonStatus {
+    pub state: &'static str, // idle, recording, transcribing, inserting
+    pub last_result: Option<String>,
+}
+
+#[cfg(feature = "desktop")]
+impl Default for DaemonStatus {
+    fn default() -> Self {
+        Self {
+            state: "idle",
+            last_result: None,
+        }
+    }
+}
+
+#[cfg(feature = "desktop")]
+pub type SharedStatus = std::sync::Arc<std::sync::Mutex<DaemonStatus>>;
+
+// a client that connects and never sends its line is dropped after this
+#[cfg(feature = "desktop")]
+const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Listen for `whisperia ctl` on its own thread; dictations go to the daemon
+/// loop as if the hotkey was pressed
+#[cfg(all(feature = "desktop", unix))]
+pub fn spawn(events: std::sync::mpsc::Sender<crate::tray::AppEvent>, status: SharedStatus) -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use tracing::{info, warn};
+
+    let path = Config::ipc_path()?;
+    // a socket left by a crash answers nobody, one that answers is another instance's
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("another whisperia is listening on {:?}", path);
+        }
+        std::fs::remove_file(&path).with_context(|| format!("failed to remove the stale socket {:?}", path))?;
+    }
+    let listener = UnixListener::bind(&path).with_context(|| format!("failed to listen on {:?}", path))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    info!("ipc listening on {:?}", path);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("ipc connection failed: {}", e);
+                    continue;
+                }
+            };
+            // one client waiting to send doesn't hold up the next
+            let (events, status) = (events.clone(), status.clone());
+            std::thread::spawn(move || {
+                let mut command = String::new();
+                let read = stream
+                    .set_read_timeout(Some(READ_TIMEOUT))
+                    .and_then(|()| BufReader::new(&stream).read_line(&mut command));
+                if let Err(e) = read {
+                    warn!("whisperia ctl sent no command: {}", e);
+                    return;
+                }
+                let reply = answer(command.trim(), &events, &status);
+                if let Err(e) = writeln!(stream, "{}", reply) {
+                    warn!("failed to answer whisperia ctl: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Listen for `whisperia ctl` on a named pipe, on its own thread
+#[cfg(all(feature = "desktop", windows))]
+pub fn spawn(events: std::sync::mpsc::Sender<crate::tray::AppEvent>, status: SharedStatus) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::windows::named_pipe::ServerOptions;
+    use tracing::{info, warn};
+
+    let path = Config::ipc_path()?;
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let mut pipe = {
+        let _guard = runtime.enter();
+        ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&path)
+            .with_context(|| format!("failed to listen on {:?}, is another whisperia running?", path))?
+    };
+    info!("ipc listening on {:?}", path);
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            loop {
+                let connected = pipe.connect().await;
+                // every client gets its own instance of the pipe
+                let next = match ServerOptions::new().create(&path) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        warn!("whisperia ctl stopped, failed to reopen the pipe: {}", e);
+                        return;
+                    }
+                };
+                let client = std::mem::replace(&mut pipe, next);
+                if let Err(e) = connected {
+                    warn!("ipc connection failed: {}", e);
+                    continue;
+                }
+                let (events, status) = (events.clone(), status.clone());
+                tokio::spawn(async move {
+                    let mut client = BufReader::new(client);
+                    let mut command = String::new();
+                    let read = tokio::time::timeout(READ_TIMEOUT, client.read_line(&mut command)).await;
+                    if !matches!(read, Ok(Ok(_))) {
+                        warn!("whisperia ctl sent no command in {:?}", READ_TIMEOUT);
+                        return;
+                    }
+                    let reply = answer(command.trim(), &events, &status);
+                    let client = client.get_mut();
+                    if let Err(e) = client.write_all(format!("{}\n", reply).as_bytes()).await {
+                        warn!("failed to answer whisperia ctl: {}", e);
+                    }
+                    let _ = client.shutdown().await;
+                });
+            }
+        });
+    });
+    Ok(())
+}
+
+#[cfg(feature = "desktop")]
+fn answer(command: &str, events: &std::sync::mpsc::Sender<crate::tray::AppEvent>, status: &SharedStatus) -> String {
+    tracing::info!("ipc command '{}'", command);
+    let send = |event| match events.send(event) {
+        Ok(()) => "ok".to_string(),
+        Err(_) => "error: the daemon is shutting down".to_string(),
+    };
+    match command {
+        // like the hotkey: in toggle mode the second one ends the recording
+        "transcribe" | "toggle" => send(crate::tray::AppEvent::StartRecording),
+        "cancel" => send(crate::tray::AppEvent::CancelRecording),
+        "status" => status.lock().map(|status| status.state).unwrap_or("idle").to_string(),
+        "last-result" => status.lock().ok().and_then(|status| status.last_result.clone()).unwrap_or_default(),
+        _ => format!("error: unknown command '{}', use transcribe, toggle, cancel, status or last-result", command),
+    }
+}
+
+/// `whisperia ctl <command>`: send it to the running daemon or app and print the answer
+pub fn run_ctl(command: &str) -> Result<()> {
+    let path = Config::ipc_path()?;
+    let unreachable = || format!("whisperia isn't running, nothing listens on {:?}", path);
+    #[cfg(unix)]
+    let mut stream = std::os::unix::net::UnixStream::connect(&path).with_context(unreachable)?;
+    #[cfg(not(unix))]
+    let mut stream = std::fs::OpenOptions::new().read(true).write(true).open(&path).with_context(unreachable)?;
+
+    writeln!(stream, "{}", command)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).context("whisperia hung up without an answer")?;
+    let reply = reply.trim_end();
+    match reply.strip_prefix("error: ") {
+        Some(error) => anyhow::bail!("{}", error),
+        None => {
+            if !reply.is_empty() {
+                println!("{}", reply);
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,27 +1,55 @@
 use anyhow::Result;
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "desktop")]
 use std::sync::mpsc::channel;
+#[cfg(feature = "desktop")]
 use std::thread;
 use tracing::info;
 
 mod audio;
 mod config;
 mod hardware;
+#[cfg(feature = "capture")]
+mod history;
+#[cfg(feature = "desktop")]
 mod hotkeys;
+// tf only formats recording and daemon output
+#[cfg_attr(not(feature = "capture"), allow(dead_code))]
+mod i18n;
+#[cfg(feature = "desktop")]
 mod input;
+mod ipc;
+mod logging;
+#[cfg(feature = "capture")]
+mod media;
+#[cfg(feature = "capture")]
+mod native_messaging;
+#[cfg(feature = "desktop")]
 mod overlay;
+mod postprocess;
+mod subtitles;
+#[cfg(feature = "desktop")]
 mod tray;
 mod transcription;
 
+#[cfg(feature = "capture")]
 use audio::AudioRecorder;
 use config::Config;
 use hardware::HardwareDetector;
+use i18n::t;
+#[cfg(feature = "capture")]
+use i18n::tf;
+#[cfg(feature = "desktop")]
 use input::InputSimulator;
+#[cfg(feature = "desktop")]
 use overlay::{OverlayCommand, OverlayState};
+#[cfg(feature = "desktop")]
 use tray::{setup_tray, AppEvent};
+#[cfg(feature = "desktop")]
 use hotkeys::setup_hotkeys;
-use transcription::Transcriber;
+use subtitles::OutputFormat;
+use transcription::{Segment, Transcriber};
 
 #[derive(Parser)]
 #[command(name = "whisperia")]
@@ -58,19 +86,83 @@ struct Cli {
     /// record until ctrl+c is pressed (interactive mode)
     #[arg(long)]
     interactive: bool,
+    
+    /// transcribe a wav or video file (mp4/mkv/webm, needs ffmpeg), "-" reads a wav from stdin
+    #[arg(long, value_name = "path")]
+    file: Option<String>,
+    
+    /// write the transcript as txt, srt or vtt subtitles, or json with the timed segments
+    #[arg(long, value_enum, value_name = "format", default_value_t = OutputFormat::Txt)]
+    output_format: OutputFormat,
+    
+    /// write the transcript to this file instead of the terminal
+    #[arg(long, value_name = "path")]
+    output_file: Option<PathBuf>,
+    
+    /// write the speech in english, whatever the language spoken
+    #[arg(long)]
+    translate: bool,
+    
+    /// keep config and models next to the executable
+    #[arg(long)]
+    portable: bool,
+    
+    /// run as a chrome/firefox native messaging host
+    #[arg(long)]
+    native_messaging: bool,
+    
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// control the running daemon or app: transcribe, toggle, cancel, status or last-result
+    Ctl {
+        command: String,
+    },
 }
 
 fn main() -> Result<()> {
-    // initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter("whisperia=info")
-        .init();
+    // browsers launch the host with their own arguments, so check before parsing
+    #[cfg(feature = "capture")]
+    let cli = if native_messaging::launched_by_browser() {
+        Cli::parse_from(["whisperia", "--native-messaging"])
+    } else {
+        Cli::parse()
+    };
+    #[cfg(not(feature = "capture"))]
+    let cli = Cli::parse();
+    
+    if cli.portable {
+        Config::set_portable();
+    }
+    
+    // initialize logging (stdout belongs to the browser in native messaging mode,
+    // the daemon keeps log files like the app)
+    if cli.daemon && cli.command.is_none() && !cli.native_messaging {
+        logging::init(&Config::load_or_create()?.logging);
+    } else if cli.native_messaging {
+        tracing_subscriber::fmt()
+            .with_env_filter("whisperia=info")
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt()
+            .with_env_filter("whisperia=info")
+            .init();
+    }
 
     info!("starting whisperia v{}", env!("CARGO_PKG_VERSION"));
-
-    let cli = Cli::parse();
+    if let Some(dir) = Config::portable_dir() {
+        info!("portable mode, data in {:?}", dir);
+    }
     
-    if cli.daemon {
+    if let Some(Command::Ctl { command }) = &cli.command {
+        ipc::run_ctl(command)?;
+    } else if cli.native_messaging {
+        run_native_messaging()?;
+    } else if cli.daemon {
         run_daemon()?;
     } else {
         run_cli(cli)?;
@@ -79,11 +171,33 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "capture")]
+fn run_native_messaging() -> Result<()> {
+    let config = Config::load_or_create()?;
+    transcription::set_gpu(config.model.use_gpu);
+    native_messaging::run(&config)
+}
+
+#[cfg(not(feature = "capture"))]
+fn run_native_messaging() -> Result<()> {
+    anyhow::bail!("built without the capture feature, native messaging needs the microphone")
+}
+
+#[cfg(not(feature = "desktop"))]
+fn run_daemon() -> Result<()> {
+    anyhow::bail!("built without the desktop feature, only file transcription is available (--file)")
+}
+
+#[cfg(feature = "desktop")]
 fn run_daemon() -> Result<()> {
-    println!("whisperia daemon starting...");
-    println!("use tray icon or hotkey to transcribe");
-    
     let config = Config::load_or_create()?;
+    i18n::set_locale(&config.ui.locale);
+    Config::set_offline(config.offline);
+    Config::set_network(&config.network);
+    transcription::set_gpu(config.model.use_gpu);
+    
+    println!("{}", t("cli.daemon_starting"));
+    println!("{}", t("cli.daemon_hint"));
     let (event_tx, event_rx) = channel::<AppEvent>();
     
     // setup system tray
@@ -92,6 +206,17 @@ fn run_daemon() -> Result<()> {
     // setup global hotkeys
     setup_hotkeys(event_tx.clone(), &config.shortcut)?;
     
+    // scripts and keybinding daemons start dictations with `whisperia ctl`
+    let status = ipc::SharedStatus::default();
+    if let Err(e) = ipc::spawn(event_tx.clone(), status.clone()) {
+        tracing::warn!("whisperia ctl can't reach this daemon: {:#}", e);
+    }
+    let set_state = |state: &'static str| {
+        if let Ok(mut status) = status.lock() {
+            status.state = state;
+        }
+    };
+    
     // setup overlay
     let (overlay_tx, _overlay_rx) = channel::<OverlayCommand>();
     
@@ -101,8 +226,8 @@ fn run_daemon() -> Result<()> {
     // get model path
     let model_path = get_model_path(&config)?;
     
-    println!("daemon ready!");
-    println!("hotkey: {}", config.shortcut);
+    println!("{}", t("cli.daemon_ready"));
+    println!("{}", tf("cli.hotkey", &[&config.shortcut]));
     
     // main event loop
     loop {
@@ -113,37 +238,78 @@ fn run_daemon() -> Result<()> {
                     let _ = overlay_tx.send(OverlayCommand::Show(OverlayState::Listening));
                     
                     // record audio
-                    println!("gravando...");
+                    set_state("recording");
+                    println!("{}", t("cli.recording"));
+                    let media_guard = media::pause_for_recording(&config.media);
                     let recorder = AudioRecorder::new()?;
-                    let audio_data = recorder.record_for_seconds(5)?;
+                    let (mut cancelled, mut quit) = (false, false);
+                    let audio_data = match config.capture.mode {
+                        config::RecordingMode::Fixed => recorder.record_for_seconds(5)?,
+                        // until the hotkey or `whisperia ctl toggle` again
+                        config::RecordingMode::Toggle => recorder.record_until(config.capture.max_secs, || {
+                            match event_rx.try_recv() {
+                                Ok(AppEvent::StartRecording | AppEvent::StopRecording) => true,
+                                Ok(AppEvent::CancelRecording) => {
+                                    cancelled = true;
+                                    true
+                                }
+                                Ok(AppEvent::Quit) => {
+                                    quit = true;
+                                    true
+                                }
+                                Ok(AppEvent::OpenSettings) | Err(_) => false,
+                            }
+                        })?,
+                    };
+                    drop(media_guard);
+                    if quit {
+                        println!("{}", t("cli.quitting"));
+                        break;
+                    }
+                    if cancelled {
+                        set_state("idle");
+                        let _ = overlay_tx.send(OverlayCommand::Hide);
+                        continue;
+                    }
                     
                     // update overlay
                     let _ = overlay_tx.send(OverlayCommand::Update(OverlayState::Transcribing));
                     
                     // transcribe
-                    println!("transcrevendo...");
-                    let transcriber = Transcriber::new(&model_path)?;
+                    set_state("transcribing");
+                    println!("{}", t("cli.transcribing"));
+                    let transcriber = Transcriber::new(&model_path)?
+                        .with_threads(config.model.threads)
+                        .with_paragraphs(config.output.paragraph_pause_ms)
+                        .with_translate(config.model.translate);
                     let text = transcriber.transcribe(&audio_data, &config.language)?;
+                    let text = postprocess::process(&text, &config, None);
                     
                     // show result
                     let _ = overlay_tx.send(OverlayCommand::Show(OverlayState::Result(text.clone())));
                     
                     // type the result
-                    println!("digitando: {}", text);
+                    set_state("inserting");
+                    if let Ok(mut status) = status.lock() {
+                        status.last_result = Some(text.clone());
+                    }
+                    println!("{}", tf("cli.typing", &[&text]));
+                    history::add(&config, &text, "daemon");
                     input.type_text(&text)?;
+                    set_state("idle");
                     
                     // hide overlay after a delay
                     thread::sleep(std::time::Duration::from_millis(2000));
                     let _ = overlay_tx.send(OverlayCommand::Hide);
                 }
-                AppEvent::StopRecording => {
-                    // handled above
+                AppEvent::StopRecording | AppEvent::CancelRecording => {
+                    // only while a toggle recording goes on, handled above
                 }
                 AppEvent::OpenSettings => {
-                    println!("configuracao aberta (nao implementado ainda)");
+                    println!("{}", t("cli.settings_unavailable"));
                 }
                 AppEvent::Quit => {
-                    println!("encerrando whisperia...");
+                    println!("{}", t("cli.quitting"));
                     break;
                 }
             }
@@ -156,6 +322,13 @@ fn run_daemon() -> Result<()> {
 fn run_cli(cli: Cli) -> Result<()> {
     let rt = tokio::runtime::Runtime::new()?;
     let config = Config::load_or_create()?;
+    i18n::set_locale(&config.ui.locale);
+    Config::set_offline(config.offline);
+    Config::set_network(&config.network);
+    transcription::set_gpu(config.model.use_gpu);
+    
+    // --translate, or model.translate in the config
+    let translate = cli.translate || config.model.translate;
     
     // initialize hardware detection
     let hardware = HardwareDetector::new()?;
@@ -199,11 +372,46 @@ fn run_cli(cli: Cli) -> Result<()> {
     
     if let Some(model) = cli.download_model {
         info!("downloading model: {}", model);
-        println!("model download not yet implemented in this version");
+        println!("{}", t("cli.download_unavailable"));
+        return Ok(());
+    }
+    
+    // transcribe a wav or video file or stdin, works without a desktop or a microphone
+    if let Some(file) = cli.file {
+        let model_path = if let Some(path) = cli.model_path {
+            PathBuf::from(path)
+        } else {
+            get_model_path(&config)?
+        };
+        
+        let audio_data = if file == "-" {
+            audio::decode_wav(std::io::BufReader::new(std::io::stdin().lock()))?
+        } else if audio::is_video(Path::new(&file)) {
+            audio::extract_audio(Path::new(&file))?
+        } else {
+            let reader = std::fs::File::open(&file)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", file, e))?;
+            audio::decode_wav(std::io::BufReader::new(reader))?
+        };
+        
+        let transcriber = Transcriber::new(&model_path)?
+            .with_threads(config.model.threads)
+            .with_paragraphs(config.output.paragraph_pause_ms)
+            .with_translate(translate);
+        let segments = transcriber.transcribe_with_segments(&audio_data, &config.language)?;
+        
+        // just the transcript, so it can be piped
+        write_transcript(&config, &transcriber, segments, cli.output_format, cli.output_file.as_deref())?;
         return Ok(());
     }
     
+    #[cfg(not(feature = "capture"))]
+    if cli.transcribe.is_some() || cli.interactive {
+        anyhow::bail!("built without the capture feature, recording is not available (use --file)");
+    }
+    
     // transcribe audio with fixed duration
+    #[cfg(feature = "capture")]
     if let Some(seconds) = cli.transcribe {
         let model_path = if let Some(path) = cli.model_path {
             PathBuf::from(path)
@@ -211,22 +419,32 @@ fn run_cli(cli: Cli) -> Result<()> {
             get_model_path(&config)?
         };
         
-        println!("\nwhisperia transcription");
+        println!("\n{}", t("cli.title"));
         println!("========================================");
-        println!("recording for {} seconds...", seconds);
-        println!("speak now!\n");
+        println!("{}", tf("cli.recording_for", &[&seconds]));
+        println!("{}\n", t("cli.speak_now"));
         
         // record audio
+        let media_guard = media::pause_for_recording(&config.media);
         let recorder = AudioRecorder::new()?;
         let audio_data = recorder.record_for_seconds(seconds)?;
+        drop(media_guard);
         
-        println!("recording complete! transcribing...\n");
+        println!("{}\n", t("cli.recording_complete"));
         
         // transcribe
-        let transcriber = Transcriber::new(&model_path)?;
-        let text = transcriber.transcribe(&audio_data, &config.language)?;
+        let transcriber = Transcriber::new(&model_path)?
+            .with_threads(config.model.threads)
+            .with_paragraphs(config.output.paragraph_pause_ms)
+            .with_translate(translate);
+        let segments = transcriber.transcribe_with_segments(&audio_data, &config.language)?;
+        let text = transcriber.join(&segments);
+        history::add(&config, &text, "cli");
+        if cli.output_format != OutputFormat::Txt || cli.output_file.is_some() {
+            return write_transcript(&config, &transcriber, segments, cli.output_format, cli.output_file.as_deref());
+        }
         
-        println!("transcription result:");
+        println!("{}", t("cli.result"));
         println!("\"{}\"", text);
         println!("========================================\n");
         
@@ -234,6 +452,7 @@ fn run_cli(cli: Cli) -> Result<()> {
     }
     
     // interactive mode: record until ctrl+c
+    #[cfg(feature = "capture")]
     if cli.interactive {
         let model_path = if let Some(path) = cli.model_path {
             PathBuf::from(path)
@@ -241,21 +460,35 @@ fn run_cli(cli: Cli) -> Result<()> {
             get_model_path(&config)?
         };
         
-        println!("\nwhisperia transcription (interactive mode)");
+        println!("\n{}", t("cli.title_interactive"));
         println!("========================================");
-        println!("gravando... pressione ctrl+c para parar\n");
+        println!("{}\n", t("cli.press_ctrl_c"));
         
         // record audio until ctrl+c
+        let media_guard = media::pause_for_recording(&config.media);
         let recorder = AudioRecorder::new()?;
         let audio_data = recorder.record_until_interrupt()?;
+        drop(media_guard);
         
-        println!("\ntranscrevendo...\n");
+        println!("\n{}\n", t("cli.transcribing"));
         
-        // transcribe
-        let transcriber = Transcriber::new(&model_path)?;
-        let text = transcriber.transcribe(&audio_data, &config.language)?;
+        // transcribe, with each segment on stderr as it is decoded (like the app's --print)
+        let transcriber = Transcriber::new(&model_path)?
+            .with_threads(config.model.threads)
+            .with_paragraphs(config.output.paragraph_pause_ms)
+            .with_translate(translate);
+        let segments = transcriber.transcribe_with_callback(&audio_data, &config.language, |segment| {
+            if !segment.text.is_empty() {
+                eprintln!("... {}", segment.text);
+            }
+        })?;
+        let text = transcriber.join(&segments);
+        history::add(&config, &text, "cli");
+        if cli.output_format != OutputFormat::Txt || cli.output_file.is_some() {
+            return write_transcript(&config, &transcriber, segments, cli.output_format, cli.output_file.as_deref());
+        }
         
-        println!("transcription result:");
+        println!("{}", t("cli.result"));
         println!("\"{}\"", text);
         println!("========================================\n");
         
@@ -263,55 +496,98 @@ fn run_cli(cli: Cli) -> Result<()> {
     }
     
     // default: show info and hardware
-    println!("\nwhisperia voice transcription tool");
+    println!("\n{}", t("cli.tool_title"));
     println!("========================================");
     println!("version: {}", env!("CARGO_PKG_VERSION"));
     println!("config: {:?}", Config::config_path()?);
-    println!("\nsystem information:");
+    println!("\n{}", t("cli.system_info"));
     
     let sys_info = hardware.get_system_info();
     println!("  cpu: {} ({} cores)", sys_info.cpu_name, sys_info.cpu_cores);
     println!("  ram: {} gb total, {} gb available", 
         sys_info.total_memory_gb, sys_info.available_memory_gb);
+    println!("  arch: {}{}", sys_info.arch, if sys_info.neon { " (neon)" } else { "" });
+    if sys_info.low_power {
+        println!("  low-power arm board, tuned defaults are used for new configs");
+    }
     
-    println!("\navailable models:");
+    println!("\n{}", t("cli.available_models"));
     for model in hardware.get_available_models() {
         let status = if model.can_run { "[ok]" } else { "[x]" };
         println!("  {} {} - {}gb ram - {}", 
             status, model.model, model.ram_required_gb, model.estimated_speed);
     }
     
-    println!("\nconfiguration:");
+    println!("\n{}", t("cli.configuration"));
     println!("  shortcut: {}", config.shortcut);
     println!("  language: {}", config.language);
     println!("  model: {} ({})", config.model.local_model, config.model.model_type);
     
-    println!("\nusage:");
-    println!("  --check-hardware      check system compatibility");
-    println!("  --check-model <id>    check if hf model works");
-    println!("  --list-models         list all available models");
-    println!("  --transcribe <secs>   record for fixed seconds");
-    println!("  --interactive         record until ctrl+c");
-    println!("  --model-path <path>   use specific model file");
-    
-    println!("\nexamples:");
+    println!("\n{}", t("cli.usage"));
+    println!("  --check-hardware      {}", t("cli.usage_check_hardware"));
+    println!("  --check-model <id>    {}", t("cli.usage_check_model"));
+    println!("  --list-models         {}", t("cli.usage_list_models"));
+    println!("  --transcribe <secs>   {}", t("cli.usage_transcribe"));
+    println!("  --interactive         {}", t("cli.usage_interactive"));
+    println!("  --file <path|->       {}", t("cli.usage_file"));
+    println!("  --output-format <fmt> {}", t("cli.usage_output_format"));
+    println!("  --output-file <path>  {}", t("cli.usage_output_file"));
+    println!("  --translate           {}", t("cli.usage_translate"));
+    println!("  --portable            {}", t("cli.usage_portable"));
+    println!("  --model-path <path>   {}", t("cli.usage_model_path"));
+    println!("  --native-messaging    {}", t("cli.usage_native_messaging"));
+    println!("  ctl <command>         {}", t("cli.usage_ctl"));
+    
+    println!("\n{}", t("cli.examples"));
     println!("  whisperia --transcribe 5");
     println!("  whisperia --interactive");
+    println!("  whisperia --file aula.mp4 --output-format srt --output-file aula.srt");
     println!("  whisperia --transcribe 10 --model-path ~/.local/share/whisperia/models/ggml-small.bin");
     println!("========================================\n");
     
     Ok(())
 }
 
+/// Post-process the segments and write them in `format`, to `file` or stdout
+fn write_transcript(
+    config: &Config,
+    transcriber: &Transcriber,
+    mut segments: Vec<Segment>,
+    format: OutputFormat,
+    file: Option<&Path>,
+) -> Result<()> {
+    let text = postprocess::process(&transcriber.join(&segments), config, None);
+    for segment in &mut segments {
+        segment.text = postprocess::process(&segment.text, config, None);
+    }
+    let output = subtitles::render(format, &text, &segments, &config.language)?;
+    match file {
+        Some(path) => {
+            std::fs::write(path, output).map_err(|e| anyhow::anyhow!("failed to write {}: {}", path.display(), e))?;
+            info!("transcript written to {}", path.display());
+        }
+        None => print!("{}", output),
+    }
+    Ok(())
+}
+
 fn get_model_path(config: &Config) -> Result<PathBuf> {
     let models_dir = Config::models_dir()?;
     
+    // A model made for the language beats the general one of the same size
+    if config.model.prefer_language_models {
+        if let Some(path) = language_model_path(config, &models_dir) {
+            info!("usando modelo para {}: {}", config.language, path.display());
+            return Ok(path);
+        }
+    }
+    
     // Try quantized version first (Q5_0 has best quality/size ratio)
     let quantized_file = models_dir.join(format!("ggml-{}-q5_0.bin", config.model.local_model));
     if quantized_file.exists() {
         // Check if file is valid (not empty/corrupted)
         if std::fs::metadata(&quantized_file)?.len() > 1000000 {
-            println!("usando modelo quantizado: {}", quantized_file.display());
+            info!("usando modelo quantizado: {}", quantized_file.display());
             return Ok(quantized_file);
         }
     }
@@ -319,9 +595,21 @@ fn get_model_path(config: &Config) -> Result<PathBuf> {
     // Fallback to standard model
     let model_file = models_dir.join(format!("ggml-{}.bin", config.model.local_model));
     if model_file.exists() {
-        println!("usando modelo padrao: {}", model_file.display());
+        info!("usando modelo padrao: {}", model_file.display());
         Ok(model_file)
     } else {
         anyhow::bail!("modelo nao encontrado. execute ./download-models.sh ou use --model-path")
     }
 }
+
+fn language_model_path(config: &Config, models_dir: &Path) -> Option<PathBuf> {
+    let files = match config.model.language_models.iter().find(|pack| pack.language == config.language) {
+        Some(pack) => vec![pack.url.rsplit('/').next().unwrap_or(&pack.name).to_string()],
+        None if config.language == "en" => {
+            let model = &config.model.local_model;
+            vec![format!("ggml-{}.en-q5_0.bin", model), format!("ggml-{}.en.bin", model)]
+        }
+        None => return None,
+    };
+    files.into_iter().map(|file| models_dir.join(file)).find(|path| path.exists())
+}
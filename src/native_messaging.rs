@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use tracing::{error, info};
+
+use crate::audio::AudioRecorder;
+use crate::config::Config;
+use crate::transcription::Transcriber;
+use crate::{get_model_path, media, postprocess};
+
+// browsers may send up to 4gb, but nothing we accept is anywhere near this
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+const MAX_DICTATION_SECONDS: u64 = 60;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Ping,
+    Dictate {
+        seconds: Option<u64>,
+        language: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Pong { version: String },
+    Status { state: String },
+    Result { text: String },
+    Error { message: String },
+}
+
+/// Chrome passes the extension origin, Firefox the manifest path and addon id
+pub fn launched_by_browser() -> bool {
+    match std::env::args().nth(1) {
+        Some(arg) => arg.starts_with("chrome-extension://") || arg.ends_with(".json"),
+        None => false,
+    }
+}
+
+/// Serve native messaging requests on stdin/stdout until the browser disconnects
+pub fn run(config: &Config) -> Result<()> {
+    info!("native messaging host started");
+
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+
+    // loaded on the first dictation and kept while the extension is connected
+    let mut transcriber: Option<Transcriber> = None;
+
+    while let Some(message) = read_message(&mut stdin)? {
+        let request: Request = match serde_json::from_slice(&message) {
+            Ok(request) => request,
+            Err(e) => {
+                write_message(&mut stdout, &Response::Error {
+                    message: format!("invalid request: {}", e),
+                })?;
+                continue;
+            }
+        };
+
+        match request {
+            Request::Ping => {
+                write_message(&mut stdout, &Response::Pong {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                })?;
+            }
+            Request::Dictate { seconds, language } => {
+                let seconds = seconds.unwrap_or(5).clamp(1, MAX_DICTATION_SECONDS);
+                let language = language.unwrap_or_else(|| config.language.clone());
+
+                let response = match dictate(config, &mut transcriber, seconds, &language, &mut stdout) {
+                    Ok(text) => Response::Result { text },
+                    Err(e) => {
+                        error!("dictation failed: {:#}", e);
+                        Response::Error { message: format!("{:#}", e) }
+                    }
+                };
+                write_message(&mut stdout, &response)?;
+            }
+        }
+    }
+
+    info!("browser disconnected, native messaging host exiting");
+    Ok(())
+}
+
+fn dictate(
+    config: &Config,
+    transcriber: &mut Option<Transcriber>,
+    seconds: u64,
+    language: &str,
+    stdout: &mut impl Write,
+) -> Result<String> {
+    write_message(stdout, &Response::Status { state: "recording".to_string() })?;
+
+    let media_guard = media::pause_for_recording(&config.media);
+    let recorder = AudioRecorder::new()?;
+    let audio_data = recorder.record_for_seconds(seconds)?;
+    drop(media_guard);
+
+    write_message(stdout, &Response::Status { state: "transcribing".to_string() })?;
+
+    if transcriber.is_none() {
+        let model_path = get_model_path(config)?;
+        *transcriber = Some(
+            Transcriber::new(&model_path)?
+                .with_threads(config.model.threads)
+                .with_paragraphs(config.output.paragraph_pause_ms)
+                .with_translate(config.model.translate),
+        );
+    }
+    let text = transcriber
+        .as_ref()
+        .context("transcriber not loaded")?
+        .transcribe(&audio_data, language)?;
+
+    Ok(postprocess::process(&text, config, None))
+}
+
+/// Messages are utf-8 json prefixed with a native-endian u32 length
+fn read_message(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len_bytes = [0u8; 4];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e).context("failed to read message length"),
+    }
+
+    let len = u32::from_ne_bytes(len_bytes) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        anyhow::bail!("native message too large: {} bytes", len);
+    }
+
+    let mut message = vec![0u8; len];
+    reader
+        .read_exact(&mut message)
+        .context("failed to read message body")?;
+    Ok(Some(message))
+}
+
+fn write_message(writer: &mut impl Write, response: &Response) -> Result<()> {
+    let payload = serde_json::to_vec(response)?;
+    writer.write_all(&(payload.len() as u32).to_ne_bytes())?;
+    writer.write_all(&payload)?;
+    writer.flush()?;
+    Ok(())
+}
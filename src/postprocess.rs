@@ -0,0 +1,324 @@
+use anyhow::{Context, Result};
+use chrono::{Duration as Days, Local};
+use regex::{Captures, NoExpand, Regex};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::{Config, OutputConfig, RedactionConfig};
+use crate::i18n::{locale, Locale};
+
+#[derive(Clone, Copy)]
+enum Macro {
+    Today,
+    Tomorrow,
+    Yesterday,
+    Time,
+}
+
+// longer phrases first, "insert today's date" before "today's date"
+const MACROS: &[(&str, Macro)] = &[
+    ("insert today's date", Macro::Today),
+    ("insert the date", Macro::Today),
+    ("today's date", Macro::Today),
+    ("tomorrow's date", Macro::Tomorrow),
+    ("yesterday's date", Macro::Yesterday),
+    ("insert the current time", Macro::Time),
+    ("insert the time", Macro::Time),
+    ("current time", Macro::Time),
+    ("inserir a data de hoje", Macro::Today),
+    ("inserir data de hoje", Macro::Today),
+    ("data de hoje", Macro::Today),
+    ("data de amanhã", Macro::Tomorrow),
+    ("data de ontem", Macro::Yesterday),
+    ("inserir a hora atual", Macro::Time),
+    ("inserir hora atual", Macro::Time),
+    ("hora atual", Macro::Time),
+];
+
+/// Run the transcript through the configured post-processing steps.
+/// `app_id` is the focused application, when known, for per-app rules.
+/// Failing steps are skipped so the user never loses the dictation.
+pub fn process(text: &str, config: &Config, app_id: Option<&str>) -> String {
+    let mut text = text.to_string();
+    let rule = app_id.and_then(|app| config.output.rule_for(app));
+
+    if config.output.macros {
+        text = expand_macros(&text, &config.output);
+    }
+
+    let pipe_command = rule
+        .and_then(|r| r.pipe_command.as_deref())
+        .or(config.output.pipe_command.as_deref());
+    if let Some(command) = pipe_command {
+        let timeout = Duration::from_millis(config.output.pipe_timeout_ms);
+        match pipe_through_command(&text, command, timeout) {
+            Ok(output) => text = output,
+            Err(e) => warn!("pipe command failed, using original text: {:#}", e),
+        }
+    }
+
+    if let Some(suffix) = rule.and_then(|r| r.suffix.as_deref()) {
+        text.push_str(suffix);
+    }
+
+    // last, so a pipe command can't bring anything back
+    if config.redaction.scope.output() {
+        text = redact(&text, &config.redaction);
+    }
+
+    text
+}
+
+// compiled on first use
+static EMAIL: OnceLock<Regex> = OnceLock::new();
+static DIGIT_GROUPS: OnceLock<Regex> = OnceLock::new();
+
+/// Mask emails, card numbers and the custom patterns in `text`. A pattern
+/// that doesn't compile is skipped with a warning.
+pub fn redact(text: &str, config: &RedactionConfig) -> String {
+    if !config.is_active() {
+        return text.to_string();
+    }
+    let mut text = text.to_string();
+
+    if config.emails {
+        let email = EMAIL.get_or_init(|| Regex::new(r"[\w.%+-]+@[\w-]+(\.[\w-]+)*\.\w{2,}").expect("valid email regex"));
+        text = email.replace_all(&text, NoExpand(&config.mask)).into_owned();
+    }
+
+    if config.card_numbers {
+        // digits in groups, "4111 1111 1111 1111" or with dashes, maybe with more numbers after
+        let digits = DIGIT_GROUPS.get_or_init(|| Regex::new(r"\b\d+(?:[ -]\d+)*\b").expect("valid card regex"));
+        text = digits
+            .replace_all(&text, |found: &Captures| mask_cards(&found[0], &config.mask))
+            .into_owned();
+    }
+
+    for custom in &config.patterns {
+        match Regex::new(&custom.pattern) {
+            Ok(pattern) => {
+                let mask = custom.mask.as_deref().unwrap_or(&config.mask);
+                text = pattern.replace_all(&text, NoExpand(mask)).into_owned();
+            }
+            Err(e) => warn!("ignoring redaction pattern {:?}: {}", custom.pattern, e),
+        }
+    }
+    text
+}
+
+/// Mask the card numbers in a run of digit groups: whole groups holding 13 to
+/// 19 digits that pass the luhn check, so a card followed by its expiry date
+/// ("4111 1111 1111 1111 12 25") is still found
+fn mask_cards(run: &str, mask: &str) -> String {
+    let groups: Vec<(usize, usize)> = run
+        .char_indices()
+        .filter(|(_, c)| c.is_ascii_digit())
+        .fold(Vec::new(), |mut groups: Vec<(usize, usize)>, (i, _)| {
+            match groups.last_mut() {
+                Some((_, end)) if *end == i => *end = i + 1,
+                _ => groups.push((i, i + 1)),
+            }
+            groups
+        });
+
+    let mut masked = String::new();
+    let mut copied = 0;
+    let mut first = 0;
+    while first < groups.len() {
+        // the longest card starting at this group
+        let mut card = None;
+        let mut digits = 0;
+        for last in first..groups.len() {
+            digits += groups[last].1 - groups[last].0;
+            if digits > 19 {
+                break;
+            }
+            if digits >= 13 && luhn(&run[groups[first].0..groups[last].1]) {
+                card = Some(last);
+            }
+        }
+        match card {
+            Some(last) => {
+                masked.push_str(&run[copied..groups[first].0]);
+                masked.push_str(mask);
+                copied = groups[last].1;
+                first = last + 1;
+            }
+            None => first += 1,
+        }
+    }
+    masked.push_str(&run[copied..]);
+    masked
+}
+
+/// Whether the digits in `number` pass the luhn checksum card numbers carry
+fn luhn(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2 == 1, d * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => d,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Replace spoken date/time macros with the current values, matching whole
+/// words case-insensitively. A period whisper put after a macro that ends
+/// the dictation is dropped, "Insert today's date." is just the date.
+pub fn expand_macros(text: &str, output: &OutputConfig) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut word_start = true;
+
+    while let Some(c) = rest.chars().next() {
+        let found = word_start
+            .then(|| {
+                MACROS.iter().find(|(phrase, _)| {
+                    rest.get(..phrase.len()).is_some_and(|head| head.eq_ignore_ascii_case(phrase))
+                        && !rest[phrase.len()..].starts_with(char::is_alphanumeric)
+                })
+            })
+            .flatten();
+        if let Some(&(phrase, kind)) = found {
+            expanded.push_str(&macro_value(kind, output));
+            rest = &rest[phrase.len()..];
+            if rest == "." {
+                rest = "";
+            }
+            word_start = false;
+            continue;
+        }
+        expanded.push(c);
+        word_start = !c.is_alphanumeric();
+        rest = &rest[c.len_utf8()..];
+    }
+    expanded
+}
+
+fn macro_value(kind: Macro, output: &OutputConfig) -> String {
+    let (date, time) = match locale() {
+        Locale::Pt => ("%d/%m/%Y", "%H:%M"),
+        Locale::En => ("%B %-d, %Y", "%-I:%M %p"),
+    };
+    let date = output.date_format.as_deref().unwrap_or(date);
+    let time = output.time_format.as_deref().unwrap_or(time);
+    let now = Local::now();
+    let format = |at: chrono::DateTime<Local>, format: &str| {
+        // an invalid format would make chrono panic while writing
+        let mut value = String::new();
+        match write!(value, "{}", at.format(format)) {
+            Ok(()) => value,
+            Err(_) => {
+                warn!("invalid date/time format '{}'", format);
+                at.format("%Y-%m-%d %H:%M").to_string()
+            }
+        }
+    };
+    match kind {
+        Macro::Today => format(now, date),
+        Macro::Tomorrow => format(now + Days::days(1), date),
+        Macro::Yesterday => format(now - Days::days(1), date),
+        Macro::Time => format(now, time),
+    }
+}
+
+/// Feed `text` to `command` on stdin and return its stdout
+pub fn pipe_through_command(text: &str, command: &str, timeout: Duration) -> Result<String> {
+    info!("piping transcript through: {}", command);
+
+    #[cfg(windows)]
+    let mut shell = {
+        let mut c = Command::new("cmd");
+        c.args(["/C", command]);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut c = Command::new("sh");
+        c.args(["-c", command]);
+        c
+    };
+
+    let mut child = shell
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("failed to start pipe command: {}", command))?;
+
+    // write and read on separate threads so a chatty script can't deadlock us
+    let mut stdin = child.stdin.take().context("pipe command has no stdin")?;
+    let input = text.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut stdout = child.stdout.take().context("pipe command has no stdout")?;
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("pipe command timed out after {:?}", timeout);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let _ = writer.join();
+    let output = reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("pipe command reader panicked"))?
+        .context("failed to read pipe command output")?;
+
+    if !status.success() {
+        anyhow::bail!("pipe command exited with {}", status);
+    }
+
+    Ok(output.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards() -> RedactionConfig {
+        RedactionConfig {
+            card_numbers: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn masks_a_card_number() {
+        assert_eq!(redact("card 4111 1111 1111 1111 ok", &cards()), "card [redacted] ok");
+        assert_eq!(redact("4111-1111-1111-1111", &cards()), "[redacted]");
+        assert_eq!(redact("4111111111111111", &cards()), "[redacted]");
+    }
+
+    #[test]
+    fn masks_a_card_followed_by_its_expiry() {
+        assert_eq!(redact("4111 1111 1111 1111 12 25", &cards()), "[redacted] 12 25");
+    }
+
+    #[test]
+    fn keeps_numbers_that_fail_luhn() {
+        assert_eq!(redact("4111 1111 1111 1112", &cards()), "4111 1111 1111 1112");
+        assert_eq!(redact("call 555 1234", &cards()), "call 555 1234");
+    }
+}
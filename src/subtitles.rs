@@ -0,0 +1,70 @@
+//! What `--output-format` writes: the plain text, or whisper's segments as
+//! srt/vtt subtitles or json, for turning recorded lectures and meetings
+//! into captions.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::transcription::Segment;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Txt,
+    Srt,
+    Vtt,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    text: &'a str,
+    language: &'a str,
+    segments: &'a [Segment],
+}
+
+/// `text` is the joined transcript, only txt and json use it
+pub fn render(format: OutputFormat, text: &str, segments: &[Segment], language: &str) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Txt => format!("{}\n", text),
+        OutputFormat::Srt => cues(segments, true, ','),
+        OutputFormat::Vtt => format!("WEBVTT\n\n{}", cues(segments, false, '.')),
+        OutputFormat::Json => {
+            let document = Document { text, language, segments };
+            format!("{}\n", serde_json::to_string_pretty(&document)?)
+        }
+    })
+}
+
+// srt numbers its cues and takes a comma before the milliseconds, vtt a dot
+fn cues(segments: &[Segment], numbered: bool, separator: char) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let range = format!(
+                "{} --> {}",
+                timestamp(segment.start_ms, separator),
+                timestamp(segment.end_ms, separator)
+            );
+            if numbered {
+                format!("{}\n{}\n{}\n", i + 1, range, segment.text)
+            } else {
+                format!("{}\n{}\n", range, segment.text)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// hh:mm:ss,mmm
+fn timestamp(ms: i64, separator: char) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
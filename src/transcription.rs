@@ -1,33 +1,118 @@
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::path::PathBuf;
-use tracing::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
 pub struct Transcriber {
     context: WhisperContext,
+    threads: i32,
+    paragraph_pause_ms: i64,
+    translate: bool,
 }
 
+/// A stretch of speech with its place in the audio, for subtitles
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+// `model.use_gpu`, see `set_gpu`
+static USE_GPU: AtomicBool = AtomicBool::new(false);
+
+/// Load models on the gpu backend of this build (cuda, vulkan or metal) from now on
+pub fn set_gpu(enabled: bool) {
+    let built = cfg!(any(feature = "cuda", feature = "vulkan", feature = "metal"));
+    if enabled && !built {
+        warn!("model.use_gpu is on, but whisperia was built without cuda, vulkan or metal");
+    }
+    USE_GPU.store(enabled && built, Ordering::Relaxed);
+}
+
+// decode threads unless the config says otherwise
+const DEFAULT_THREADS: i32 = 4;
+
 impl Transcriber {
     pub fn new(model_path: &PathBuf) -> Result<Self> {
-        info!("loading whisper model from: {:?}", model_path);
-
         if !model_path.exists() {
             anyhow::bail!("model file not found: {:?}", model_path);
         }
 
-        let context_params = WhisperContextParameters::default();
-        let context = WhisperContext::new_with_params(
-            model_path.to_str().context("invalid model path")?,
-            context_params,
-        )
-        .context("failed to load whisper model")?;
+        // the cpu when the gpu can't take the model (no device, not enough vram)
+        let gpu = USE_GPU.load(Ordering::Relaxed);
+        let context = match Self::load(model_path, gpu) {
+            Err(e) if gpu => {
+                warn!("failed to load the model on the gpu ({:#}), decoding on the cpu", e);
+                Self::load(model_path, false)?
+            }
+            loaded => loaded?,
+        };
 
         info!("whisper model loaded successfully");
 
-        Ok(Self { context })
+        Ok(Self {
+            context,
+            threads: DEFAULT_THREADS,
+            paragraph_pause_ms: 0,
+            translate: false,
+        })
+    }
+
+    fn load(model_path: &PathBuf, gpu: bool) -> Result<WhisperContext> {
+        info!("loading whisper model from: {:?} ({})", model_path, if gpu { "gpu" } else { "cpu" });
+
+        let mut context_params = WhisperContextParameters::default();
+        context_params.use_gpu(gpu);
+        WhisperContext::new_with_params(model_path.to_str().context("invalid model path")?, context_params)
+            .context("failed to load whisper model")
+    }
+
+    /// Decode with `threads` threads, 0 keeps the default
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        if threads > 0 {
+            self.threads = threads as i32;
+        }
+        self
+    }
+
+    /// Start a new paragraph where the speaker paused this long, 0 keeps one paragraph
+    pub fn with_paragraphs(mut self, pause_ms: u64) -> Self {
+        self.paragraph_pause_ms = pause_ms as i64;
+        self
+    }
+
+    /// Write the speech in english, translated from the language spoken
+    pub fn with_translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
     }
 
     pub fn transcribe(&self, audio_data: &[f32], language: &str) -> Result<String> {
+        let segments = self.transcribe_with_segments(audio_data, language)?;
+        let text = self.join(&segments);
+        info!("transcription complete: {} chars", text.len());
+        Ok(text)
+    }
+
+    /// Decode `audio_data` into whisper's segments, with their timestamps
+    pub fn transcribe_with_segments(&self, audio_data: &[f32], language: &str) -> Result<Vec<Segment>> {
+        self.transcribe_with_callback(audio_data, language, |_| {})
+    }
+
+    /// Like `transcribe_with_segments`, but calls `on_segment` for every segment
+    /// as soon as whisper finishes it
+    pub fn transcribe_with_callback<F>(
+        &self,
+        audio_data: &[f32],
+        language: &str,
+        mut on_segment: F,
+    ) -> Result<Vec<Segment>>
+    where
+        F: FnMut(Segment) + 'static,
+    {
         info!("transcribing {} samples", audio_data.len());
 
         // create a state for this transcription
@@ -38,12 +123,20 @@ impl Transcriber {
 
         let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
         params.set_language(Some(language));
-        params.set_translate(false);
+        params.set_translate(self.translate);
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
-        params.set_n_threads(4);
+        params.set_n_threads(self.threads);
+        // whisper timestamps are in centiseconds
+        params.set_segment_callback_safe_lossy(move |data: whisper_rs::SegmentCallbackData| {
+            on_segment(Segment {
+                start_ms: data.start_timestamp * 10,
+                end_ms: data.end_timestamp * 10,
+                text: data.text.trim().to_string(),
+            });
+        });
 
         // whisper aceita f32 diretamente agora
         state
@@ -51,16 +144,35 @@ impl Transcriber {
             .context("transcription failed")?;
 
         // iterar pelos segmentos usando o novo metodo as_iter
+        let segments: Vec<Segment> = state
+            .as_iter()
+            .map(|segment| Segment {
+                // whisper timestamps are in centiseconds
+                start_ms: segment.start_timestamp() * 10,
+                end_ms: segment.end_timestamp() * 10,
+                text: segment.to_string().trim().to_string(),
+            })
+            .filter(|segment| !segment.text.is_empty())
+            .collect();
+
+        info!("decoded {} segments", segments.len());
+        Ok(segments)
+    }
+
+    /// The segments as one text, split into paragraphs at long pauses
+    pub fn join(&self, segments: &[Segment]) -> String {
         let mut text = String::new();
+        let mut previous_end = None;
 
-        for segment in state.as_iter() {
-            text.push_str(&segment.to_string());
-            text.push(' ');
+        for segment in segments {
+            if let Some(end_ms) = previous_end {
+                let paused = self.paragraph_pause_ms > 0 && segment.start_ms - end_ms >= self.paragraph_pause_ms;
+                text.push_str(if paused { "\n\n" } else { " " });
+            }
+            text.push_str(&segment.text);
+            previous_end = Some(segment.end_ms);
         }
 
-        let text = text.trim().to_string();
-        info!("transcription complete: {} chars", text.len());
-
-        Ok(text)
+        text.trim().to_string()
     }
 }
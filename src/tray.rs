@@ -4,6 +4,7 @@ use std::sync::mpsc::Sender;
 pub enum AppEvent {
     StartRecording,
     StopRecording,
+    CancelRecording, // drop the toggle recording going on
     OpenSettings,
     Quit,
 }